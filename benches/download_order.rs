@@ -0,0 +1,105 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Downloading a tree's tarballs largest first, the order installs start them in when the
+//! registry reports `dist.unpackedSize`, against the arbitrary order of `tree.values()`.
+//!
+//! Each download takes time in proportion to its size, with a bounded number in flight at
+//! once like the install loop, so the difference is how late the longest download starts.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::stream::{FuturesUnordered, StreamExt};
+
+/// Downloads in flight at once.
+const CONCURRENCY: usize = 16;
+
+/// Kilobytes downloaded per millisecond.
+const BANDWIDTH: u64 = 200;
+
+/// A tree shaped like a typical install: a few large packages (typescript, esbuild's
+/// binaries) among many small ones, by name and size in kilobytes.
+fn tree() -> HashMap<String, u64> {
+    let mut tree = HashMap::new();
+
+    // a small linear congruential generator, so every run downloads the same tree
+    let mut seed: u64 = 0x5eed;
+    let mut next = move |bound: u64| {
+        seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        (seed >> 33) % bound
+    };
+
+    for index in 0..240 {
+        tree.insert(format!("small-{}", index), 20 + next(180));
+    }
+
+    for index in 0..8 {
+        tree.insert(format!("large-{}", index), 2_000 + next(10_000));
+    }
+
+    tree
+}
+
+async fn download(size: u64) {
+    tokio::time::sleep(Duration::from_micros(size * 1_000 / BANDWIDTH)).await;
+}
+
+/// Downloads every size in `order`, keeping `CONCURRENCY` downloads in flight.
+async fn install(order: &[u64]) {
+    let mut pending = order.iter();
+    let mut downloads = FuturesUnordered::new();
+
+    loop {
+        while downloads.len() < CONCURRENCY {
+            match pending.next() {
+                Some(&size) => downloads.push(download(size)),
+                None => break,
+            }
+        }
+
+        if downloads.next().await.is_none() {
+            break;
+        }
+    }
+}
+
+fn download_order(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let tree = tree();
+
+    let arbitrary: Vec<u64> = tree.values().copied().collect();
+
+    let mut largest_first = arbitrary.clone();
+    largest_first.sort_by_key(|&size| std::cmp::Reverse(size));
+
+    let mut group = c.benchmark_group("download_order");
+    group.sample_size(20);
+
+    group.bench_function("largest_first", |b| {
+        b.iter(|| runtime.block_on(install(&largest_first)));
+    });
+
+    group.bench_function("arbitrary", |b| {
+        b.iter(|| runtime.block_on(install(&arbitrary)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, download_order);
+criterion_main!(benches);
@@ -0,0 +1,149 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Decompressing a burst of downloaded tarballs on a rayon pool with one thread per cpu,
+//! the way installs use `EXTRACT_POOL`, against decompressing each one in tokio's blocking
+//! pool next to the blocking io of the other installs.
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use flate2::{write::GzEncoder, Compression};
+use futures::future::join_all;
+
+/// Tarballs that finish downloading at once.
+const TARBALLS: usize = 64;
+
+/// Files in each tarball, and the size of each.
+const FILES: usize = 64;
+const FILE_SIZE: usize = 8 * 1024;
+
+/// Blocking io every install does around its extraction, writing the partial download.
+const BLOCKING_IO: Duration = Duration::from_millis(2);
+
+/// A gzipped tarball of `FILES` source-like files.
+fn tarball(seed: usize) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for file in 0..FILES {
+        let contents: Vec<u8> = format!("module.exports = {};\n", seed * FILES + file)
+            .bytes()
+            .cycle()
+            .take(FILE_SIZE)
+            .collect();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        builder
+            .append_data(
+                &mut header,
+                format!("package/lib/{}.js", file),
+                &contents[..],
+            )
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Reads every entry of a tarball, as unpacking does without touching the disk.
+fn extract(data: &[u8]) -> usize {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(data));
+    let mut extracted = 0;
+
+    for entry in archive.entries().unwrap() {
+        let mut contents = vec![];
+        entry.unwrap().read_to_end(&mut contents).unwrap();
+
+        extracted += contents.len();
+    }
+
+    extracted
+}
+
+/// Writes a partial download, as each install does before its extraction.
+fn write_partial(data: &[u8]) {
+    std::thread::sleep(BLOCKING_IO);
+    std::io::sink().write_all(data).unwrap();
+}
+
+/// Every install's io and extraction in one blocking task.
+async fn mixed(tarballs: &[std::sync::Arc<Vec<u8>>]) {
+    let installs = tarballs.iter().cloned().map(|data| {
+        tokio::task::spawn_blocking(move || {
+            write_partial(&data);
+            extract(&data)
+        })
+    });
+
+    for extracted in join_all(installs).await {
+        assert_eq!(extracted.unwrap(), FILES * FILE_SIZE);
+    }
+}
+
+/// Every install's io in a blocking task, handing its extraction to `pool`.
+async fn split(tarballs: &[std::sync::Arc<Vec<u8>>], pool: &rayon::ThreadPool) {
+    let installs = tarballs.iter().cloned().map(|data| async move {
+        let io_data = data.clone();
+        tokio::task::spawn_blocking(move || write_partial(&io_data))
+            .await
+            .unwrap();
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+
+        pool.spawn(move || {
+            sender.send(extract(&data)).ok();
+        });
+
+        receiver.await.unwrap()
+    });
+
+    for extracted in join_all(installs).await {
+        assert_eq!(extracted, FILES * FILE_SIZE);
+    }
+}
+
+fn extract_pool(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let tarballs: Vec<_> = (0..TARBALLS)
+        .map(|seed| std::sync::Arc::new(tarball(seed)))
+        .collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|index| format!("volt-extract-{}", index))
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("extract_pool");
+    group.sample_size(20);
+
+    group.bench_function("split", |b| {
+        b.iter(|| runtime.block_on(split(&tarballs, &pool)));
+    });
+
+    group.bench_function("mixed", |b| {
+        b.iter(|| runtime.block_on(mixed(&tarballs)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, extract_pool);
+criterion_main!(benches);
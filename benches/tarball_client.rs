@@ -0,0 +1,140 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Fetching a batch of tarballs through one pooled client, the way installs share
+//! `TARBALL_CLIENT`, against building a client (and so a connection) for every tarball.
+//!
+//! The registry is a keep-alive server on localhost, so this only measures the TCP
+//! handshakes pooling saves; against a real registry each one is a TLS handshake as well.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::stream::{self, StreamExt};
+
+/// Tarballs fetched per install.
+const TARBALLS: usize = 128;
+
+/// Downloads in flight at once.
+const CONCURRENCY: usize = 16;
+
+/// Size of each tarball, a small package.
+const TARBALL_SIZE: usize = 16 * 1024;
+
+/// Same as `POOL_IDLE_TIMEOUT`, in seconds.
+const POOL_IDLE_TIMEOUT: u64 = 90;
+
+/// Answers every request on a connection with a tarball, until the client closes it.
+fn keep_alive(stream: TcpStream, body: &[u8]) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+
+    loop {
+        let mut line = String::new();
+
+        // the request line and headers, up to the blank line ending them
+        loop {
+            line.clear();
+
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+
+            if line == "\r\n" {
+                break;
+            }
+        }
+
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+
+        if writer.write_all(head.as_bytes()).is_err() || writer.write_all(body).is_err() {
+            return;
+        }
+    }
+}
+
+/// Serves tarballs on localhost, returning the port.
+fn registry() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let body: Vec<u8> = (0..TARBALL_SIZE).map(|i| i as u8).collect();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            stream.set_nodelay(true).ok();
+
+            let body = body.clone();
+            std::thread::spawn(move || keep_alive(stream, &body));
+        }
+    });
+
+    port
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT))
+        .tcp_keepalive(Duration::from_secs(POOL_IDLE_TIMEOUT))
+        .build()
+        .unwrap()
+}
+
+/// Fetches every tarball, with `shared` or a new client per tarball when it's `None`.
+async fn install(port: u16, shared: Option<&reqwest::Client>) {
+    stream::iter(0..TARBALLS)
+        .map(|index| async move {
+            let url = format!("http://127.0.0.1:{}/package-{}.tgz", port, index);
+
+            let response = match shared {
+                Some(client) => client.get(&url).send().await,
+                None => client().get(&url).send().await,
+            };
+
+            let body = response.unwrap().bytes().await.unwrap();
+            assert_eq!(body.len(), TARBALL_SIZE);
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<()>>()
+        .await;
+}
+
+fn tarball_client(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let port = registry();
+
+    // building a client loads the system's root certificates, so the slow side is very slow
+    let mut group = c.benchmark_group("tarball_client");
+    group.sample_size(10);
+
+    group.bench_function("pooled", |b| {
+        let shared = client();
+        b.iter(|| runtime.block_on(install(port, Some(&shared))));
+    });
+
+    group.bench_function("per_request", |b| {
+        b.iter(|| runtime.block_on(install(port, None)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, tarball_client);
+criterion_main!(benches);
@@ -15,21 +15,43 @@ limitations under the License.
 
 use crate::{
     core::model::lock_file::{DependencyID, DependencyLock, LockFile},
-    core::utils::voltapi::VoltPackage,
-    core::utils::{constants::PROGRESS_CHARS, install_extract_package, print_elapsed},
-    core::utils::{fetch_dep_tree, package::PackageJson},
-    core::{command::Command, VERSION},
+    core::utils::voltapi::{split_dependency, VoltPackage, VoltResponse},
+    core::utils::{
+        check_peer_dependency, clean_up_partial_installs,
+        constants::LOW_MEMORY_CONCURRENCY,
+        errors::VoltError,
+        events::{reporter_for, InstallEvent, OrderRecorder, Reporter},
+        failures::InstallFailures,
+        generate_script, install_extract_package,
+        install_lock::InstallLock,
+        layout::{check_layout, LayoutState},
+        link_strategy::create_links,
+        print_sha1_warning, prune_dependency_tree, resolve_dependencies,
+        snapshot::{inputs_hash, tree_hash, Snapshot},
+        spill::SpilledTree,
+        stats::{format_bytes, InstallStats},
+        sweep_volt_dir, upgrade_sha1_integrities,
+        workspace::find_workspace_member,
+        PeerState,
+    },
+    core::utils::{
+        package::{DependencyType, PackageJson},
+        resolve_tags, DefaultVersion,
+    },
+    core::{command::Command, net::CONCURRENCY, VERSION},
     App,
 };
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use colored::Colorize;
-use futures::{stream::FuturesUnordered, StreamExt, TryStreamExt};
-use indicatif::{ProgressBar, ProgressStyle};
+use futures::{stream::FuturesUnordered, StreamExt};
 use miette::Result;
+use node_semver::{Range, Version};
 
 #[derive(Clone, Debug)]
 pub struct Package {
@@ -37,6 +59,643 @@ pub struct Package {
     pub version: Option<String>,
 }
 
+/// Packages waiting to be installed, read from the resolved tree or from disk with `--low-memory`.
+type PendingPackages<'a> = Box<dyn Iterator<Item = Result<VoltPackage>> + Send + 'a>;
+
+/// The version the registry resolved for each requested package, keyed by the name it was
+/// requested (and is saved to package.json) as.
+///
+/// Responses come back in the order the packages were resolved in, so each package is
+/// paired with its own response exactly once. Matching by name instead could pick the
+/// response of another requested package that has it as a dependency.
+pub fn resolved_versions(
+    packages: &[Package],
+    responses: &[VoltResponse],
+) -> HashMap<String, String> {
+    packages
+        .iter()
+        .zip(responses)
+        .map(|(package, res)| (package.name.clone(), res.version.clone()))
+        .collect()
+}
+
+/// Makes sure every requested package resolved to a version inside its requested range,
+/// so a registry or resolver bug fails the install instead of installing the wrong version.
+///
+/// Requests that aren't semver ranges (urls, git specs) can't be checked and are skipped.
+pub fn check_resolved_ranges(
+    packages: &[Package],
+    versions: &HashMap<String, String>,
+) -> Result<()> {
+    for package in packages {
+        let (requested, resolved) = match (&package.version, versions.get(&package.name)) {
+            (Some(requested), Some(resolved)) => (requested, resolved),
+            _ => continue,
+        };
+
+        let satisfied = match (requested.parse::<Range>(), resolved.parse::<Version>()) {
+            (Ok(range), Ok(version)) => range.satisfies(&version),
+            _ => true,
+        };
+
+        if !satisfied {
+            return Err(VoltError::UnsatisfiedRange {
+                package: package.name.clone(),
+                requested: requested.clone(),
+                resolved: resolved.clone(),
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// With `--install-peers`, resolves the required peer dependencies that neither the tree nor
+/// node_modules has and adds them to the tree, like npm 7+. Peers of the peers it adds are
+/// picked up on the next pass. Returns the peers it added, for placing them at the top level.
+async fn resolve_missing_peers(
+    app: &App,
+    tree: &mut HashMap<String, VoltPackage>,
+    reporter: &dyn Reporter,
+) -> Result<Vec<Package>> {
+    let mut added: Vec<Package> = vec![];
+
+    loop {
+        let mut missing: Vec<Package> = vec![];
+
+        for package in tree.values() {
+            for peer in package.peer_dependencies.iter().flatten() {
+                let (name, range) = split_dependency(peer);
+
+                // a peer the registry couldn't resolve onto this platform isn't tried again
+                if package.is_optional_peer(peer)
+                    || added.iter().chain(missing.iter()).any(|p| p.name == name)
+                    || check_peer_dependency(app, tree, peer) != PeerState::Missing
+                {
+                    continue;
+                }
+
+                missing.push(Package {
+                    name: name.to_string(),
+                    version: range.map(String::from),
+                });
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(added);
+        }
+
+        let missing = resolve_tags(app, missing, DefaultVersion::Tag("latest")).await?;
+        let (_, peers, _) = resolve_dependencies(app, &missing, reporter).await?;
+
+        tree.extend(peers);
+        added.extend(missing);
+    }
+}
+
+/// With `engine-strict`, refuses to install into a project whose `engines.node` isn't satisfied
+/// by the node on PATH, like npm. Without a node to compare against there's nothing to check.
+fn check_engine_strict(app: &App) -> Result<()> {
+    if !app.has_setting("engine-strict", "engine-strict") {
+        return Ok(());
+    }
+
+    let required = match std::fs::read_to_string(app.current_dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<PackageJson>(&data).ok())
+        .and_then(|package| package.engines.get("node").cloned())
+    {
+        Some(required) => required,
+        None => return Ok(()),
+    };
+
+    let current = match std::process::Command::new("node").arg("--version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .trim_start_matches('v')
+            .to_string(),
+        Err(_) => return Ok(()),
+    };
+
+    let satisfied = match (required.parse::<Range>(), current.parse::<Version>()) {
+        (Ok(range), Ok(version)) => range.satisfies(&version),
+        _ => true,
+    };
+
+    if !satisfied {
+        return Err(VoltError::UnsupportedEngine { required, current }.into());
+    }
+
+    Ok(())
+}
+
+/// Records the requested packages in package.json with the versions they resolved to, in the
+/// section and with the prefix the `--save-*` flags and `.voltrc` ask for.
+fn save_dependencies(
+    app: &App,
+    package_file: &mut PackageJson,
+    packages: &[Package],
+    versions: &HashMap<String, String>,
+) {
+    let save_exact = app.has_setting("save-exact", "save-exact");
+
+    // --save-exact wins over any prefix, npm's ^ is the default
+    let save_prefix = if save_exact {
+        String::new()
+    } else {
+        app.args
+            .value_of("save-prefix")
+            .map(String::from)
+            .or_else(|| app.config.get("save-prefix").cloned())
+            .unwrap_or_else(|| String::from("^"))
+    };
+
+    // clap rejects more than one of these
+    let section = if app.has_flag("save-dev") {
+        DependencyType::DevDependencies
+    } else if app.has_flag("save-peer") {
+        DependencyType::PeerDependencies
+    } else if app.has_flag("save-optional") {
+        DependencyType::OptionalDependencies
+    } else {
+        DependencyType::Dependencies
+    };
+
+    for package in packages {
+        let version = match versions.get(&package.name) {
+            Some(version) => version,
+            None => continue,
+        };
+
+        // 1.2.3 with --save-exact, ^1.2.3 or ~1.2.3 otherwise
+        package_file.add_dependency_to(
+            section,
+            package.name.clone(),
+            format!("{}{}", save_prefix, version),
+        );
+    }
+}
+
+/// Resolves and installs packages into the project, saving them to package.json when `save` is set.
+///
+/// Shared by `volt add` and `volt install`, which reads its packages from package.json.
+/// With `from_lock` the tree in volt.lock is installed instead of resolving it again, when
+/// it has a version inside every requested range.
+pub async fn install_packages(
+    app: Arc<App>,
+    packages: Vec<Package>,
+    save: bool,
+    from_lock: bool,
+) -> Result<()> {
+    let reporter = reporter_for(app.args.value_of("progress"));
+
+    // --record-order logs each package step for replaying a flaky install
+    match app.args.value_of("record-order").map(PathBuf::from) {
+        Some(path) => {
+            let recorder = OrderRecorder::create(&path, reporter.as_ref()).map_err(|e| {
+                VoltError::WriteFileError {
+                    source: e,
+                    name: path.to_string_lossy().to_string(),
+                }
+            })?;
+
+            install_packages_with(app, packages, save, from_lock, &recorder).await
+        }
+        None => install_packages_with(app, packages, save, from_lock, reporter.as_ref()).await,
+    }
+}
+
+/// Same as [`install_packages`], reporting progress to `reporter` instead of the terminal's
+/// progress bars.
+pub async fn install_packages_with(
+    app: Arc<App>,
+    packages: Vec<Package>,
+    save: bool,
+    from_lock: bool,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let start = Instant::now();
+
+    // Catch an invalid `node-linker` or `link-strategy` in .voltrc before anything is downloaded
+    app.node_linker()?;
+    let link_strategy = app.link_strategy()?;
+
+    check_engine_strict(&app)?;
+
+    if app.has_flag("adaptive-concurrency") {
+        CONCURRENCY.enable();
+    }
+
+    // --fetch-only downloads the tree into ~/.volt without touching the project,
+    // e.g. to warm a CI cache layer before the sources are copied in
+    let fetch_only = app.has_flag("fetch-only");
+
+    // Wait for any other volt process writing to this project or to ~/.volt;
+    // both locks are held until the install finishes
+    let _project_lock = if fetch_only {
+        None
+    } else {
+        Some(InstallLock::acquire(
+            &app.node_modules_dir.join(".volt-install.lock"),
+        )?)
+    };
+    let _store_lock = InstallLock::acquire(&app.volt_dir.join(".lock"))?;
+
+    // Refuse to install on top of a node_modules laid out by a different volt
+    if !fetch_only {
+        check_layout(&app.node_modules_dir, link_strategy)?;
+    }
+
+    // Nothing to do when the same packages were installed the same way last time
+    let inputs = inputs_hash(&app, &packages)?;
+    let snapshot = Snapshot::load(&app.node_modules_dir).filter(|_| !fetch_only);
+
+    if !save
+        && snapshot
+            .as_ref()
+            .map(|s| s.inputs == inputs)
+            .unwrap_or(false)
+    {
+        println!(
+            "{}: node_modules is already up to date",
+            "success".bright_green()
+        );
+
+        return Ok(());
+    }
+
+    // node_modules is about to change, an install that fails partway must not look finished
+    if !fetch_only {
+        std::fs::remove_file(Snapshot::path(&app.node_modules_dir)).ok();
+    }
+
+    // Clean up packages left half-extracted by an interrupted install
+    let swept = sweep_volt_dir(&app.volt_dir)?;
+
+    if swept > 0 && app.has_flag("verbose") {
+        println!(
+            "{}: removed {} incomplete entries from {}",
+            "info".bright_purple(),
+            swept,
+            app.volt_dir.display()
+        );
+    }
+
+    // Resolve dist-tags (react@next, --tag beta) to concrete versions, packages without
+    // one follow `latest` unless --newest asks for the highest published version
+    let default_tag = app
+        .args
+        .value_of("tag")
+        .map(String::from)
+        .or_else(|| app.config.get("tag").cloned())
+        .unwrap_or_else(|| String::from("latest"));
+
+    let default_version = if app.has_flag("newest") {
+        DefaultVersion::Newest
+    } else {
+        DefaultVersion::Tag(&default_tag)
+    };
+
+    let packages = resolve_tags(&app, packages, default_version).await?;
+
+    // Load the existing package.json file
+    let (mut package_file, package_file_path) =
+        PackageJson::open(app.current_dir.join("package.json"))?;
+
+    // Construct a path to the local and global lockfile.
+    let lockfile_path = &app.lock_file_path;
+
+    let global_lockfile = &app.home_dir.join(".global.lock");
+
+    // Load local and global lockfiles.
+    let mut lock_file =
+        LockFile::load(lockfile_path).unwrap_or_else(|_| LockFile::new(lockfile_path));
+
+    let mut global_lock_file =
+        LockFile::load(global_lockfile).unwrap_or_else(|_| LockFile::new(global_lockfile));
+
+    let locked = if from_lock {
+        lock_file.locked_tree(&packages)
+    } else {
+        None
+    };
+
+    let (versions, mut tree, elapsed) = match locked {
+        // the lock was written on some platform with some flags, this install may differ
+        Some((versions, mut tree)) => {
+            prune_dependency_tree(&app, &packages, &mut tree, reporter)?;
+
+            reporter.report(InstallEvent::Info {
+                message: format!("resolved {} packages from volt.lock", tree.len()),
+            });
+
+            (versions, tree, 0.0)
+        }
+        None => {
+            let (responses, tree, elapsed) =
+                resolve_dependencies(&app, &packages, reporter).await?;
+
+            (resolved_versions(&packages, &responses), tree, elapsed)
+        }
+    };
+
+    check_resolved_ranges(&packages, &versions)?;
+
+    // installed like the requested packages, but never saved to package.json
+    let peers = if app.has_setting("install-peers", "install-peers") {
+        resolve_missing_peers(&app, &mut tree, reporter).await?
+    } else {
+        vec![]
+    };
+
+    // the same tree as last time is already in node_modules
+    let tree_hash = tree_hash(&tree)?;
+    let up_to_date = snapshot.map(|s| s.tree == tree_hash).unwrap_or(false);
+
+    // like npm, --legacy-peer-deps installs whatever versions of peers are there
+    let legacy_peer_deps = app.has_setting("legacy-peer-deps", "legacy-peer-deps");
+    let mut peer_conflicts: Vec<String> = vec![];
+
+    let mut dependencies: Vec<_> = tree
+        .values()
+        .inspect(|object| {
+            if let Some(peer_deps) = &object.peer_dependencies {
+                for dep in peer_deps {
+                    match check_peer_dependency(&app, &tree, dep) {
+                        PeerState::Met => {}
+                        // optional peers are the user's choice to install
+                        PeerState::Missing if object.is_optional_peer(dep) => {}
+                        PeerState::Missing => reporter.report(InstallEvent::Warning {
+                            message: format!("{} has unmet peer dependency {}", object.name, dep),
+                        }),
+                        PeerState::Conflict(found) => {
+                            let conflict = format!(
+                                "{}@{} needs {}, found {}",
+                                object.name,
+                                object.version,
+                                dep,
+                                found.join(", ")
+                            );
+
+                            if legacy_peer_deps {
+                                reporter.report(InstallEvent::Warning { message: conflict });
+                            } else {
+                                peer_conflicts.push(conflict);
+                            }
+                        }
+                    }
+                }
+            }
+
+            lock_file.dependencies.insert(
+                DependencyID(object.name.clone(), object.version.clone()),
+                DependencyLock::from_package(object),
+            );
+
+            global_lock_file.dependencies.insert(
+                DependencyID(object.name.clone(), object.version.clone()),
+                DependencyLock::from_package(object),
+            );
+        })
+        .collect();
+
+    if !peer_conflicts.is_empty() {
+        peer_conflicts.sort();
+
+        return Err(VoltError::PeerDependencyConflict {
+            conflicts: peer_conflicts.join("\n  "),
+        }
+        .into());
+    }
+
+    // the same tree always starts installing in the same order
+    dependencies.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    dependencies.dedup();
+
+    // start the biggest downloads first so the longest one isn't left until last
+    if dependencies.iter().any(|p| p.unpacked_size.is_some()) {
+        dependencies.sort_by_key(|p| std::cmp::Reverse(p.unpacked_size.unwrap_or(0)));
+    }
+
+    let funding = dependencies.iter().filter(|p| p.funding.is_some()).count();
+    let total = dependencies.len();
+
+    if up_to_date {
+        dependencies.clear();
+    }
+
+    reporter.report(InstallEvent::InstallStarted {
+        packages: dependencies.len(),
+    });
+
+    // where each package goes, worked out before --low-memory lets go of the tree
+    let roots: Vec<Package> = packages.iter().chain(peers.iter()).cloned().collect();
+    let plan = link_strategy.layout().plan(&app, &roots, &tree);
+
+    // --low-memory keeps the tree on disk and only a few packages in memory at a time
+    let (mut pending, limit): (PendingPackages<'_>, usize) = if app.has_flag("low-memory") {
+        let spilled = SpilledTree::create(dependencies.iter().copied())?;
+
+        drop(dependencies);
+        drop(tree);
+
+        (Box::new(spilled), LOW_MEMORY_CONCURRENCY)
+    } else {
+        let pending = dependencies.iter().map(|&package| Ok(package.clone()));
+
+        (Box::new(pending), usize::MAX)
+    };
+
+    let app_ref = &app;
+    let stats = InstallStats::default();
+    let stats_ref = &stats;
+    let install_start = Instant::now();
+
+    let mut installs = FuturesUnordered::new();
+
+    // packages that started installing and haven't finished, by name@version, with the
+    // node_modules directories they go in
+    let mut in_flight: HashMap<String, (VoltPackage, Vec<PathBuf>)> = HashMap::new();
+    let mut failures = InstallFailures::default();
+    let mut failed: Vec<(VoltPackage, Vec<PathBuf>)> = vec![];
+    let mut interrupted = false;
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        while installs.len() < limit.min(CONCURRENCY.limit()) {
+            let package = match pending.next() {
+                Some(package) => package?,
+                None => break,
+            };
+
+            let key = format!("{}@{}", package.name, package.version);
+
+            let node_modules_dirs = plan
+                .placements
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| vec![app.node_modules_dir.clone()]);
+
+            in_flight.insert(key.clone(), (package.clone(), node_modules_dirs.clone()));
+
+            installs.push(async move {
+                let result = install_extract_package(
+                    app_ref,
+                    &package,
+                    stats_ref,
+                    &node_modules_dirs,
+                    reporter,
+                )
+                .await;
+                (key, result)
+            });
+        }
+
+        tokio::select! {
+            result = installs.next() => match result {
+                Some((key, result)) => {
+                    let package = in_flight.remove(&key);
+
+                    // keep going, one failure shouldn't hide the others
+                    if let Err(error) = result {
+                        if let Some((package, _)) = &package {
+                            reporter.report(InstallEvent::PackageFailed {
+                                name: package.name.clone(),
+                                version: package.version.clone(),
+                            });
+                        }
+
+                        failures.push(key, error);
+                        failed.extend(package);
+                    }
+                }
+                None => break,
+            },
+            _ = &mut ctrl_c => {
+                interrupted = true;
+                break;
+            }
+        }
+    }
+
+    // stop any installs still in flight
+    drop(installs);
+
+    if interrupted {
+        reporter.report(InstallEvent::InstallCancelled);
+
+        // packages that never started have nothing to clean up
+        clean_up_partial_installs(
+            &app,
+            in_flight
+                .values()
+                .map(|(package, dirs)| (package, dirs.as_slice())),
+        );
+
+        return Err(VoltError::Interrupted.into());
+    }
+
+    if !failures.is_empty() {
+        reporter.report(InstallEvent::InstallFailed);
+
+        clean_up_partial_installs(
+            &app,
+            failed
+                .iter()
+                .map(|(package, dirs)| (package, dirs.as_slice())),
+        );
+        failures.print();
+
+        return Err(VoltError::InstallFailed {
+            count: failures.len(),
+        }
+        .into());
+    }
+
+    reporter.report(InstallEvent::InstallFinished);
+
+    let install_elapsed = install_start.elapsed();
+
+    if let (Some(metrics), true) = (CONCURRENCY.metrics(), app.has_flag("verbose")) {
+        println!(
+            "{}: adaptive concurrency went from {} to a peak of {}, ending at {} after {} back-offs (best {}/s)",
+            "info".bright_purple(),
+            metrics.initial,
+            metrics.peak,
+            metrics.last,
+            metrics.back_offs,
+            format_bytes(metrics.best_throughput as u64),
+        );
+    }
+
+    if fetch_only {
+        println!(
+            "{}: fetched {} packages into {}",
+            "success".bright_green(),
+            total,
+            app.volt_dir.display()
+        );
+
+        return Ok(());
+    }
+
+    create_links(&app, &plan.links)?;
+
+    for package in &plan.bins {
+        generate_script(&app, package)?;
+    }
+
+    LayoutState::current(link_strategy).save(&app.node_modules_dir)?;
+
+    // packages only published with a sha1 are locked with the sha512 computed on download
+    let sha1_only = upgrade_sha1_integrities(&app, &mut lock_file);
+    upgrade_sha1_integrities(&app, &mut global_lock_file);
+
+    print_sha1_warning(&sha1_only);
+
+    lock_file.save().map_err(VoltError::SaveLockFileError)?;
+
+    Snapshot {
+        inputs,
+        tree: tree_hash,
+        packages: total,
+    }
+    .save(&app.node_modules_dir)?;
+
+    if save {
+        save_dependencies(&app, &mut package_file, &packages, &versions);
+        package_file.save(&package_file_path)?;
+    }
+
+    if app.has_flag("stats") {
+        stats.print(
+            total,
+            &[
+                ("resolve", Duration::from_secs_f32(elapsed)),
+                ("install", install_elapsed),
+                ("total", start.elapsed()),
+            ],
+        );
+    }
+
+    let fund =
+        !app.has_flag("no-fund") && app.config.get("fund").map(|v| v != "false").unwrap_or(true);
+
+    if fund && funding > 0 {
+        println!(
+            "{} packages are looking for funding, run `{}` for details",
+            funding,
+            "volt fund".bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
 /// Struct implementation for the `Add` command.
 #[derive(Clone)]
 pub struct Add {}
@@ -48,14 +707,42 @@ impl Command for Add {
         format!(
             r#"volt {}
 
-            Add a package to your project's dependencies.
-            Usage: {} {} {} {}
-            Options:
+Add a package to your project's dependencies.
+
+Usage: {} {} {} {}
+
+Options:
 
-            {} {} Output the version number.
-            {} {} Output verbose messages on internal operations.
-            {} {} Adds package as a dev dependency
-            {} {} Disable progress bar."#,
+  {} {} Output the version number.
+  {} {} Output verbose messages on internal operations.
+  {} {} Save to devDependencies.
+  {} Save to peerDependencies.
+  {} Save to optionalDependencies.
+  {} Install without saving to package.json.
+  {} {} Save the exact version instead of a ^ range.
+  {} {} Save ranges with this prefix instead of ^ (e.g. ~).
+  {} {} Install the version behind a dist-tag (e.g. next, beta).
+  {} Install the highest published version instead of `latest`.
+  {} Don't print the funding message after installing.
+  {} {} Disable progress bar.
+  {} {} Report progress as bars or as json lines on stderr.
+  {} {} Install packages for another platform (e.g. linux-x64).
+  {} {} Copy packages into node_modules or hard link them from ~/.volt.
+  {} {} Fetch packages from this registry for this install only.
+  {} Skip optionalDependencies.
+  {} Print cache, download and timing statistics after installing.
+  {} Keep the resolved tree on disk and install a few packages at a time.
+  {} Adapt how many packages download at once to the connection.
+  {} Check packages in the store for changed files before using them.
+  {} Install even when peer dependencies conflict, warning about them.
+  {} {} Refuse tarballs larger than this (default 512 MiB).
+  {} {} Lay node_modules out flat like npm (default) or linked like pnpm.
+  {} Download packages into the store without touching node_modules or package.json.
+  {} Fail on packages the registry hasn't signed, or whose signature doesn't match.
+  {} Install missing peer dependencies, skipping optional ones.
+  {} {} Write the order packages are fetched, extracted and linked in to a file.
+  {} Allow tarballs served over plain http, warning about each one.
+  {} {} Add the packages to this workspace member instead of the root."#,
             VERSION.bright_green().bold(),
             "volt".bright_green().bold(),
             "add".bright_purple(),
@@ -65,10 +752,47 @@ impl Command for Add {
             "(-ver)".yellow(),
             "--verbose".blue(),
             "(-v)".yellow(),
-            "--dev".blue(),
+            "--save-dev".blue(),
             "(-D)".yellow(),
+            "--save-peer".blue(),
+            "--save-optional".blue(),
+            "--no-save".blue(),
+            "--save-exact".blue(),
+            "(-E)".yellow(),
+            "--save-prefix".blue(),
+            "<prefix>".yellow(),
+            "--tag".blue(),
+            "<tag>".yellow(),
+            "--newest".blue(),
+            "--no-fund".blue(),
             "--no-progress".blue(),
-            "(-np)".yellow()
+            "(-np)".yellow(),
+            "--progress".blue(),
+            "<bar|ndjson>".yellow(),
+            "--force-platform".blue(),
+            "<os>-<arch>".yellow(),
+            "--node-linker".blue(),
+            "<copy|hardlink>".yellow(),
+            "--registry".blue(),
+            "<url>".yellow(),
+            "--no-optional".blue(),
+            "--stats".blue(),
+            "--low-memory".blue(),
+            "--adaptive-concurrency".blue(),
+            "--verify-store".blue(),
+            "--legacy-peer-deps".blue(),
+            "--max-tarball-size".blue(),
+            "<bytes>".yellow(),
+            "--link-strategy".blue(),
+            "<hoisted|isolated|pnpm-strict>".yellow(),
+            "--fetch-only".blue(),
+            "--verify-signatures".blue(),
+            "--install-peers".blue(),
+            "--record-order".blue(),
+            "<file>".yellow(),
+            "--allow-insecure-tarballs".blue(),
+            "--workspace".blue(),
+            "<name>".yellow()
         )
     }
 
@@ -89,131 +813,216 @@ impl Command for Add {
         // Get input packages
         let packages = app.get_packages()?;
 
-        // Load the existing package.json file
-        let (mut package_file, package_file_path) = PackageJson::open("package.json")?;
+        // package.json, node_modules and volt.lock are all resolved from the current directory
+        let app = match app.args.value_of("workspace") {
+            Some(member) => {
+                let member_dir = find_workspace_member(&app.current_dir, member)?;
 
-        // Construct a path to the local and global lockfile.
-        let lockfile_path = &app.lock_file_path;
+                std::env::set_current_dir(&member_dir).map_err(|e| {
+                    VoltError::EnvironmentError {
+                        env: "CURRENT_DIRECTORY".to_string(),
+                        source: e,
+                    }
+                })?;
+
+                // members share the root's store
+                let mut member_app = App::initialize(&app.args)?;
+                member_app.volt_dir = app.volt_dir.clone();
 
-        let global_lockfile = &app.home_dir.join(".global.lock");
+                Arc::new(member_app)
+            }
+            None => app,
+        };
 
-        // Load local and global lockfiles.
-        let mut lock_file =
-            LockFile::load(lockfile_path).unwrap_or_else(|_| LockFile::new(lockfile_path));
+        // --no-save installs into node_modules without touching package.json
+        let save = !app.has_flag("no-save");
 
-        let mut global_lock_file =
-            LockFile::load(global_lockfile).unwrap_or_else(|_| LockFile::new(global_lockfile));
+        install_packages(app, packages, save, false).await
+    }
+}
 
-        // Create progress bar for resolving dependencies.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::config::VoltConfig;
+    use crate::core::utils::testing::{fake_registry, RecordingReporter};
+    use clap::Arg;
 
-        let progress_bar = ProgressBar::new(packages.len() as u64);
+    fn app(args: &[&str]) -> App {
+        let args = clap::App::new("add")
+            .arg(Arg::new("save-dev").long("save-dev"))
+            .arg(Arg::new("save-exact").long("save-exact"))
+            .arg(
+                Arg::new("save-prefix")
+                    .long("save-prefix")
+                    .takes_value(true),
+            )
+            .get_matches_from(std::iter::once("add").chain(args.iter().copied()));
 
-        progress_bar.set_style(
-            ProgressStyle::default_bar()
-                .progress_chars(PROGRESS_CHARS)
-                .template(&format!(
-                    "{} [{{bar:40.magenta/blue}}] {{msg:.blue}}",
-                    "Resolving Dependencies".bright_blue()
-                )),
+        App::for_tests(args, VoltConfig::default())
+    }
+
+    fn package_file() -> PackageJson {
+        serde_json::from_str(r#"{ "name": "app", "version": "1.0.0" }"#).unwrap()
+    }
+
+    fn packages(names: &[&str]) -> Vec<Package> {
+        names
+            .iter()
+            .map(|name| Package {
+                name: name.to_string(),
+                version: None,
+            })
+            .collect()
+    }
+
+    fn versions(resolved: &[(&str, &str)]) -> HashMap<String, String> {
+        resolved
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_are_saved_with_a_caret() {
+        let mut package_file = package_file();
+
+        save_dependencies(
+            &app(&[]),
+            &mut package_file,
+            &packages(&["react"]),
+            &versions(&[("react", "17.0.2")]),
         );
 
-        // Fetch pre-flattened dependency trees from the registry
-        let (responses, elapsed) = fetch_dep_tree(&packages, &progress_bar).await?;
+        assert_eq!(package_file.dependencies["react"], "^17.0.2");
+    }
+
+    #[test]
+    fn save_flags_pick_the_section_and_prefix() {
+        let requested = packages(&["typescript"]);
+        let resolved = versions(&[("typescript", "4.4.3")]);
 
-        let mut dependencies: HashMap<String, VoltPackage> = HashMap::new();
+        let mut exact = package_file();
+        save_dependencies(
+            &app(&["--save-dev", "--save-exact"]),
+            &mut exact,
+            &requested,
+            &resolved,
+        );
 
-        for res in responses.iter() {
-            let current_version = res.versions.get(&res.version).unwrap();
-            dependencies.extend(current_version.to_owned());
-        }
+        assert_eq!(exact.dev_dependencies["typescript"], "4.4.3");
+        assert!(exact.dependencies.is_empty());
 
-        progress_bar.finish_with_message("[OK]".bright_green().to_string());
+        let mut tilde = package_file();
+        save_dependencies(
+            &app(&["--save-prefix", "~"]),
+            &mut tilde,
+            &requested,
+            &resolved,
+        );
 
-        print_elapsed(dependencies.len(), elapsed);
+        assert_eq!(tilde.dependencies["typescript"], "~4.4.3");
+    }
 
-        let mut dependencies: Vec<_> = dependencies
-            .iter()
-            .map(|(_name, object)| {
-                let mut lock_dependencies: Vec<String> = vec![];
-
-                if let Some(peer_deps) = &object.peer_dependencies {
-                    for dep in peer_deps {
-                        if !crate::core::utils::check_peer_dependency(&dep) {
-                            progress_bar.println(format!(
-                                "{}{} {} has unmet peer dependency {}",
-                                " warn ".black().bright_yellow(),
-                                ":",
-                                object.name.bright_cyan(),
-                                &dep.bright_yellow()
-                            ));
-                        }
-                    }
-                }
+    #[test]
+    fn packages_that_did_not_resolve_are_not_saved() {
+        let mut package_file = package_file();
 
-                if let Some(dependencies) = &object.dependencies {
-                    for dep in dependencies {
-                        lock_dependencies.push(dep.to_string());
-                    }
-                }
+        save_dependencies(
+            &app(&[]),
+            &mut package_file,
+            &packages(&["react", "left-pad"]),
+            &versions(&[("react", "17.0.2")]),
+        );
+
+        assert_eq!(package_file.dependencies.len(), 1);
+    }
 
-                let object_instance = object.clone();
-
-                lock_file.dependencies.insert(
-                    DependencyID(object_instance.name, object_instance.version),
-                    DependencyLock {
-                        name: object.name.clone(),
-                        version: object.version.clone(),
-                        tarball: object.tarball.clone(),
-                        integrity: object.integrity.clone(),
-                        dependencies: lock_dependencies.clone(),
-                    },
-                );
-
-                let second_instance = object.clone();
-
-                global_lock_file.dependencies.insert(
-                    DependencyID(second_instance.name, second_instance.version.to_owned()),
-                    DependencyLock {
-                        name: object.name.clone(),
-                        version: object.version.clone(),
-                        tarball: object.tarball.clone(),
-                        integrity: object.integrity.clone(),
-                        dependencies: lock_dependencies,
-                    },
-                );
-
-                object
+    #[test]
+    fn each_package_is_saved_with_its_own_version() {
+        // every response also holds the others' packages, at the versions they depend on
+        let response = |version: &str| -> VoltResponse {
+            let packages: serde_json::Map<String, serde_json::Value> = [
+                ("react", "16.14.0"),
+                ("react-dom", "16.14.0"),
+                ("scheduler", "0.19.1"),
+            ]
+            .iter()
+            .map(|(name, version)| {
+                let package = serde_json::json!({
+                    "name": name,
+                    "version": version,
+                    "tarball": "",
+                    "integrity": ""
+                });
+
+                (format!("{}@{}", name, version), package)
             })
             .collect();
 
-        let progress_bar = ProgressBar::new(dependencies.len() as u64);
+            serde_json::from_value(serde_json::json!({ "version": version, version: packages }))
+                .unwrap()
+        };
 
-        progress_bar.set_style(
-            ProgressStyle::default_bar()
-                .progress_chars(PROGRESS_CHARS)
-                .template(&format!(
-                    "{} [{{bar:40.magenta/blue}}] {{msg:.blue}}",
-                    "Installing Packages".bright_blue()
-                )),
-        );
+        let requested = packages(&["react", "react-dom", "scheduler"]);
+        let responses = vec![response("17.0.2"), response("17.0.2"), response("0.20.2")];
+        let resolved = resolved_versions(&requested, &responses);
 
-        dependencies.dedup();
+        let mut package_file = package_file();
+        save_dependencies(&app(&[]), &mut package_file, &requested, &resolved);
 
-        dependencies
-            .into_iter()
-            .map(|v| install_extract_package(&app, &v))
-            .collect::<FuturesUnordered<_>>()
-            .inspect(|_| progress_bar.inc(1))
-            .try_collect::<()>()
-            .await
-            .unwrap();
+        assert_eq!(package_file.dependencies.len(), 3);
+        assert_eq!(package_file.dependencies["react"], "^17.0.2");
+        assert_eq!(package_file.dependencies["react-dom"], "^17.0.2");
+        assert_eq!(package_file.dependencies["scheduler"], "^0.20.2");
+    }
 
-        progress_bar.finish();
+    #[tokio::test]
+    async fn packages_without_dependencies_are_added() {
+        let root = std::env::temp_dir().join(format!("volt-add-is-odd-{}", std::process::id()));
+        let (registry, _) = fake_registry(&[("is-odd", "3.0.1")]);
 
-        for package in packages {
-            package_file.add_dependency(package);
-        }
+        let mut config = VoltConfig::default();
+        config.set("registry", &registry, false);
+        config.set("volt-cdn", &format!("{}/cdn", registry), false);
+        config.set("allow-insecure-tarballs", "true", false);
+
+        let args = clap::App::new("add").get_matches_from(vec!["add"]);
+        let app = Arc::new(App::for_tests_in(&root, args, config));
+
+        std::fs::create_dir_all(&app.current_dir).unwrap();
+        std::fs::create_dir_all(&app.volt_dir).unwrap();
+        std::fs::write(
+            app.current_dir.join("package.json"),
+            r#"{ "name": "app", "version": "1.0.0" }"#,
+        )
+        .unwrap();
+
+        let reporter = RecordingReporter::default();
+        let result =
+            install_packages_with(app.clone(), packages(&["is-odd"]), true, false, &reporter).await;
+
+        let installed = std::fs::read_to_string(app.node_modules_dir.join("is-odd/package.json"));
+        let package_file = PackageJson::open(app.current_dir.join("package.json"));
 
-        Ok(())
+        std::fs::remove_dir_all(&root).unwrap();
+
+        result.unwrap();
+
+        assert!(installed.unwrap().contains(r#""version": "3.0.1""#));
+        assert_eq!(package_file.unwrap().0.dependencies["is-odd"], "^3.0.1");
+
+        let events = reporter.events.into_inner().unwrap();
+        let linked = events
+            .iter()
+            .filter(|event| matches!(event, InstallEvent::Linked { .. }))
+            .count();
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, InstallEvent::ResolutionFinished { packages: 1, .. })));
+        assert!(events.contains(&InstallEvent::InstallStarted { packages: 1 }));
+        assert_eq!(linked, 1);
+        assert_eq!(events.last(), Some(&InstallEvent::InstallFinished));
     }
 }
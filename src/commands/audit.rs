@@ -11,171 +11,552 @@
     limitations under the License.
 */
 
-//! Handle an unknown command (can be listed in scripts).
+//! Audit the project's dependencies for known vulnerabilities.
 
-use crate::App;
-use crate::Command;
-use async_trait::async_trait;
+use crate::commands::add::{install_packages, Package};
+use crate::commands::outdated::upgraded_range;
+use crate::core::model::http_manager::get_package;
+use crate::core::model::lock_file::LockFile;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::npm::VersionRange;
+use crate::core::utils::package::PackageJson;
+use crate::{core::VERSION, App, Command};
 
+use async_trait::async_trait;
+use colored::Colorize;
+use isahc::{AsyncReadResponseExt, Request, RequestExt};
 use miette::Result;
+use node_semver::{Range, Version};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+/// npm's bulk advisory endpoint, which takes a map of package names to installed versions.
+const BULK_ADVISORY_URL: &str = "https://registry.npmjs.org/-/npm/v1/security/advisories/bulk";
 
 pub struct Audit {}
 
-#[derive(Debug)]
-pub struct AuditObject {
-    name: String,
-    version: String,
-    install: Vec<String>,
-    remove: Vec<String>,
-    metadata: HashMap<String, String>,
-    requires: HashMap<String, String>,
-    dependencies: HashMap<String, AuditDependency>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn parse(level: &str) -> Result<Self> {
+        match level.to_lowercase().as_str() {
+            "info" => Ok(Self::Info),
+            "low" => Ok(Self::Low),
+            "moderate" => Ok(Self::Moderate),
+            "high" => Ok(Self::High),
+            "critical" => Ok(Self::Critical),
+            _ => Err(VoltError::InvalidAuditLevel {
+                level: level.to_string(),
+            })?,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct AuditDependency {
-    version: String,
-    integrity: String,
-    requires: HashMap<String, String>,
-    dependencies: HashMap<String, AuditDependency>,
-    dev: bool,
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Low => write!(f, "low"),
+            Self::Moderate => write!(f, "moderate"),
+            Self::High => write!(f, "high"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuditResponse {
-    actions: Vec<String>,
-    advisories: HashMap<String, String>,
-    muted: Vec<String>,
-    metadata: AuditMetadata,
+/// A single advisory as returned by the bulk advisory endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: u64,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    pub severity: Severity,
+    #[serde(default)]
+    pub vulnerable_versions: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Vulnerabilities {
-    info: u128,
-    low: u128,
-    moderate: u128,
-    high: u128,
-    critical: u128,
+/// An advisory that affects an installed `name@version`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Vulnerability {
+    pub name: String,
+    pub version: String,
+    pub path: Vec<String>,
+    pub advisory: Advisory,
+}
+
+/// Queries the bulk advisory endpoint for a map of package names to installed versions.
+pub async fn fetch_advisories(
+    packages: &BTreeMap<String, Vec<String>>,
+    headers: &[(String, String)],
+) -> Result<HashMap<String, Vec<Advisory>>> {
+    let body = serde_json::to_string(packages).map_err(|_| VoltError::DeserializeError)?;
+
+    let mut request = Request::post(BULK_ADVISORY_URL).header("Content-Type", "application/json");
+
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let mut response = request
+        .body(body)
+        .map_err(VoltError::RequestBuilderError)?
+        .send_async()
+        .await
+        .map_err(VoltError::NetworkError)?;
+
+    let text = response.text().await.map_err(VoltError::IoTextRecError)?;
+
+    Ok(serde_json::from_str(&text).map_err(|_| VoltError::DeserializeError)?)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct AuditMetadata {
-    vulnerabilities: Vulnerabilities,
-    dependencies: u128,
-    dev_dependencies: u128,
-    optional_dependencies: u128,
-    total_dependencies: u128,
+/// Finds every installed package in the lockfile affected by a known advisory.
+pub async fn audit_lock_file(
+    lock_file: &LockFile,
+    headers: &[(String, String)],
+) -> Result<Vec<Vulnerability>> {
+    let mut packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for dependency in lock_file.dependencies.values() {
+        packages
+            .entry(dependency.name.clone())
+            .or_default()
+            .push(dependency.version.clone());
+    }
+
+    if packages.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let advisories = fetch_advisories(&packages, headers).await?;
+
+    let mut vulnerabilities = vec![];
+
+    for (name, advisories) in advisories {
+        let versions = match packages.get(&name) {
+            Some(versions) => versions,
+            None => continue,
+        };
+
+        for advisory in advisories {
+            let range: Option<Range> = advisory.vulnerable_versions.parse().ok();
+
+            for version in versions {
+                // if the range can't be parsed, err on the side of reporting it
+                let affected = match (&range, version.parse::<Version>()) {
+                    (Some(range), Ok(version)) => range.satisfies(&version),
+                    _ => true,
+                };
+
+                if affected {
+                    vulnerabilities.push(Vulnerability {
+                        name: name.clone(),
+                        version: version.clone(),
+                        path: lock_file.path_to(&name),
+                        advisory: advisory.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    // most severe first, then alphabetically
+    vulnerabilities.sort_by(|a, b| {
+        b.advisory
+            .severity
+            .cmp(&a.advisory.severity)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(vulnerabilities)
+}
+
+/// What `volt audit fix` can do about a vulnerable package.
+#[derive(Debug, Clone)]
+pub enum AuditFix {
+    /// A patched version the range in package.json already allows.
+    InRange {
+        name: String,
+        from: String,
+        to: String,
+    },
+    /// Only versions outside the range in package.json are patched, so fixing it is a
+    /// breaking upgrade that's only applied with `--force`.
+    Breaking {
+        name: String,
+        from: String,
+        to: String,
+        range: String,
+        dev: bool,
+    },
+    /// Nothing volt can bump by itself.
+    Manual {
+        name: String,
+        version: String,
+        reason: String,
+    },
+}
+
+/// Whether any of the advisories affects `version`, assuming it does when one's range can't
+/// be parsed.
+fn is_affected(advisories: &[Advisory], version: &Version) -> bool {
+    advisories.iter().any(|advisory| {
+        advisory
+            .vulnerable_versions
+            .parse::<Range>()
+            .map(|range| range.satisfies(version))
+            .unwrap_or(true)
+    })
+}
+
+/// Works out the fix for one vulnerable `name@version`: the highest unaffected version its
+/// range allows, or else the highest unaffected version published.
+///
+/// Only dependencies listed in package.json can be bumped, a vulnerable package further down
+/// the tree is fixed by updating whatever depends on it.
+async fn plan_fix(
+    app: &App,
+    package_file: &PackageJson,
+    vulnerability: &Vulnerability,
+    advisories: &[Advisory],
+) -> Result<AuditFix> {
+    let name = vulnerability.name.clone();
+    let version = vulnerability.version.clone();
+
+    let direct = package_file
+        .dependencies
+        .get(&name)
+        .map(|range| (range.clone(), false))
+        .or_else(|| {
+            package_file
+                .dev_dependencies
+                .get(&name)
+                .map(|range| (range.clone(), true))
+        });
+
+    let (range, dev) = match direct {
+        Some(direct) => direct,
+        None => {
+            return Ok(AuditFix::Manual {
+                reason: format!(
+                    "update the packages depending on it ({})",
+                    vulnerability.path.join(" > ")
+                ),
+                name,
+                version,
+            })
+        }
+    };
+
+    let lookup_error = || VoltError::VersionLookupError { name: name.clone() };
+
+    let registry = app.registry_for(&name);
+
+    let packument = get_package(&registry, &name, &app.registry_headers(&registry))
+        .await
+        .map_err(|_| lookup_error())?
+        .ok_or_else(lookup_error)?;
+
+    let mut unaffected: Vec<Version> = packument
+        .versions
+        .keys()
+        .filter_map(|version| version.parse().ok())
+        .filter(|version: &Version| version.pre_release.is_empty())
+        .filter(|version| !is_affected(advisories, version))
+        .collect();
+
+    unaffected.sort();
+
+    let allowed: Option<VersionRange> = range.parse().ok();
+
+    let in_range = unaffected.iter().rev().find(|version| {
+        allowed
+            .as_ref()
+            .is_some_and(|range| range.satisfies(version))
+    });
+
+    Ok(match (in_range, unaffected.last()) {
+        (Some(to), _) => AuditFix::InRange {
+            name,
+            from: version,
+            to: to.to_string(),
+        },
+        (None, Some(to)) => AuditFix::Breaking {
+            name,
+            from: version,
+            to: to.to_string(),
+            range,
+            dev,
+        },
+        (None, None) => AuditFix::Manual {
+            name,
+            version,
+            reason: String::from("no published version is clear of its advisories"),
+        },
+    })
 }
 
-// pub fn flatten_dependency_tree(
-//     package: &VoltPackage,
-//     volt_packages: &HashMap<String, VoltPackage>,
-// ) -> Vec<AuditDependency> {
-//     let mut packages: Vec<AuditDependency> = vec![];
-
-//     if package.dependencies.is_some() {
-//         for dep in package.dependencies.as_ref().unwrap().iter() {
-//             let dependency = volt_packages.get(dep).unwrap();
-
-//             let mut requires: HashMap<String, String> = HashMap::new();
-
-//             if dependency.dependencies.is_some() {
-//                 for de in dependency.dependencies.as_ref().unwrap() {
-//                     let pkg = volt_packages.get(de).unwrap();
-//                     requires.insert(de.to_owned(), pkg.version.clone());
-//                 }
-//             }
-
-//             let fdt = &mut flatten_dependency_tree(dependency, volt_packages);
-
-//             packages.push(AuditDependency {
-//                 version: dependency.version,
-//                 integrity: dependency.integrity,
-//                 requires: requires,
-//                 dependencies: HashMap::new()
-//                     .insert(
-//                         dep.to_owned(),
-//                         ,
-//                     )
-//                     .unwrap(),
-//                 dev: false,
-//             });
-//         }
-//     }
-
-//     packages
-// }
+/// `volt audit fix`: installs the patched version of every vulnerable dependency that has one,
+/// and with `force` the ones that need a breaking upgrade as well, rewriting their ranges.
+async fn fix(app: Arc<App>, force: bool) -> Result<()> {
+    let (mut package_file, package_file_path) = PackageJson::open("package.json")?;
+
+    if !app.lock_file_path.exists() {
+        return Err(VoltError::LockFileNotFound.into());
+    }
+
+    let lock_file = LockFile::load(&app.lock_file_path).map_err(VoltError::ReadLockFileError)?;
+
+    let vulnerabilities = audit_lock_file(&lock_file, &app.request_headers()).await?;
+
+    if vulnerabilities.is_empty() {
+        println!("{}: found 0 vulnerabilities", "success".bright_green());
+        return Ok(());
+    }
+
+    // one fix per installed name@version, however many advisories affect it
+    let mut affected: BTreeMap<(String, String), (&Vulnerability, Vec<Advisory>)> = BTreeMap::new();
+
+    for vulnerability in &vulnerabilities {
+        affected
+            .entry((vulnerability.name.clone(), vulnerability.version.clone()))
+            .or_insert_with(|| (vulnerability, vec![]))
+            .1
+            .push(vulnerability.advisory.clone());
+    }
+
+    let mut fixes = vec![];
+
+    for (vulnerability, advisories) in affected.values() {
+        fixes.push(plan_fix(&app, &package_file, vulnerability, advisories).await?);
+    }
+
+    let mut packages: Vec<Package> = vec![];
+    let mut breaking: Vec<(String, String, bool)> = vec![];
+
+    for fix in &fixes {
+        match fix {
+            AuditFix::InRange { name, to, .. } => packages.push(Package {
+                name: name.clone(),
+                version: Some(to.clone()),
+            }),
+            AuditFix::Breaking {
+                name,
+                to,
+                range,
+                dev,
+                ..
+            } if force => {
+                packages.push(Package {
+                    name: name.clone(),
+                    version: Some(to.clone()),
+                });
+                breaking.push((name.clone(), upgraded_range(range, to), *dev));
+            }
+            _ => {}
+        }
+    }
+
+    // several installed versions of one dependency are fixed by the same install
+    packages.dedup_by(|a, b| a.name == b.name);
+    breaking.dedup_by(|a, b| a.0 == b.0);
+
+    if !packages.is_empty() {
+        install_packages(app.clone(), packages, false, false).await?;
+    }
+
+    // patched versions in range still satisfy package.json, only breaking ones move it
+    if !breaking.is_empty() {
+        for (name, range, dev) in breaking {
+            if dev {
+                package_file.dev_dependencies.insert(name, range);
+            } else {
+                package_file.add_dependency(name, range);
+            }
+        }
+
+        package_file.save(&package_file_path)?;
+    }
+
+    println!();
+
+    for fix in &fixes {
+        match fix {
+            AuditFix::InRange { name, from, to } => {
+                println!(
+                    "  {} {} {} -> {}",
+                    "fixed".bright_green(),
+                    name.bright_cyan(),
+                    from,
+                    to
+                )
+            }
+            AuditFix::Breaking { name, from, to, .. } if force => println!(
+                "  {} {} {} -> {} (breaking)",
+                "fixed".bright_green(),
+                name.bright_cyan(),
+                from,
+                to
+            ),
+            AuditFix::Breaking { name, from, to, .. } => println!(
+                "  {} {} {} -> {} is a breaking upgrade, run `{}` to apply it",
+                "skipped".bright_yellow(),
+                name.bright_cyan(),
+                from,
+                to,
+                "volt audit fix --force".bright_cyan()
+            ),
+            AuditFix::Manual {
+                name,
+                version,
+                reason,
+            } => println!(
+                "  {} {}@{}: {}",
+                "not fixed".bright_red(),
+                name.bright_cyan(),
+                version,
+                reason
+            ),
+        }
+    }
+
+    Ok(())
+}
 
 #[async_trait]
 impl Command for Audit {
+    /// Display a help menu for the `volt audit` command.
     fn help() -> String {
-        todo!()
+        format!(
+            r#"volt {}
+
+Check installed packages for known vulnerabilities.
+
+Usage: {} {} {}
+
+Commands:
+
+  fix   Install patched versions of vulnerable dependencies their ranges allow.
+
+Options:
+
+  {} {} Minimum severity that fails the audit (info, low, moderate, high, critical).
+  {} Output the report as json.
+  {} With fix, also apply upgrades outside the ranges in package.json."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "audit".bright_purple(),
+            "[flags]".white(),
+            "--audit-level".blue(),
+            "<severity>".yellow(),
+            "--json".blue(),
+            "--force".blue()
+        )
     }
 
     /// Execute the `volt audit` command
     ///
-    /// Execute a audit command
+    /// Check the packages in the lockfile against the npm advisory database.
     /// ## Arguments
-    /// * `error` - Instance of the command (`Arc<App>`)
+    /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
     /// ```
-    /// //
+    /// // Audit the current project
     /// // .exec() is an async call so you need to await it
     /// Audit.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
-        // let package_json = PackageJson::from("package.json");
+    async fn exec(app: Arc<App>) -> Result<()> {
+        if let Some(("fix", args)) = app.args.subcommand() {
+            return fix(app.clone(), args.is_present("force")).await;
+        }
+
+        let level = match app.args.value_of("audit-level") {
+            Some(level) => Severity::parse(level)?,
+            None => Severity::Low,
+        };
+
+        // without a lock there's nothing to audit, which isn't the same as no vulnerabilities
+        if !app.lock_file_path.exists() {
+            return Err(VoltError::LockFileNotFound.into());
+        }
 
-        // let mut requires = package_json.dependencies;
-        // requires.extend(package_json.dev_dependencies);
+        let lock_file =
+            LockFile::load(&app.lock_file_path).map_err(VoltError::ReadLockFileError)?;
 
-        // let responses =
-        //     utils::get_volt_response_multi(requires.keys().cloned().collect::<Vec<String>>()).await;
+        let vulnerabilities = audit_lock_file(&lock_file, &app.request_headers()).await?;
 
-        // let mut dependencies: HashMap<String, AuditDependency> = HashMap::new();
+        if app.has_flag("json") {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&vulnerabilities)
+                    .map_err(|_| VoltError::DeserializeError)?
+            );
+        } else if vulnerabilities.is_empty() {
+            println!("{}: found 0 vulnerabilities", "success".bright_green());
+        } else {
+            let mut current: Option<Severity> = None;
 
-        // let start = Instant::now();
+            for vulnerability in &vulnerabilities {
+                let severity = vulnerability.advisory.severity;
 
-        // for res in responses {
-        //     let version = res.version;
+                if current != Some(severity) {
+                    let count = vulnerabilities
+                        .iter()
+                        .filter(|v| v.advisory.severity == severity)
+                        .count();
 
-        //     let packages = &res.versions.get(&version).unwrap().packages;
+                    let heading = format!("{} ({})", severity, count);
 
-        //     for package in packages {
-        //         let flattened_dependency_tree: Vec<AuditDependency> =
-        //             flatten_dependency_tree(package.1, packages);
+                    let heading = match severity {
+                        Severity::Critical | Severity::High => heading.bright_red().bold(),
+                        Severity::Moderate => heading.bright_yellow().bold(),
+                        Severity::Low | Severity::Info => heading.bright_blue().bold(),
+                    };
 
-        //         for dp in flattened_dependency_tree {
-        //             dependencies.insert(package.0.to_owned(), dp);
-        //         }
-        //     }
-        // }
+                    println!("\n{}", heading);
+                    current = Some(severity);
+                }
 
-        // let audit = AuditObject {
-        //     name: package_json.name,
-        //     version: package_json.version,
-        //     install: vec![],
-        //     remove: vec![],
-        //     metadata: HashMap::new(),
-        //     requires: requires,
-        //     dependencies: dependencies,
-        // };
+                println!(
+                    "  {}@{} {}",
+                    vulnerability.name.bright_cyan(),
+                    vulnerability.version,
+                    vulnerability.advisory.title
+                );
+                println!("    path: {}", vulnerability.path.join(" > "));
+                println!("    {}", vulnerability.advisory.url.bright_black());
+            }
 
-        // let mut response = chttp::post_async(
-        //     "http://registry.npmjs.org/-/npm/v1/security/audits",
-        //     format!("{:?}", audit),
-        // )
-        // .await
-        // .unwrap();
+            println!();
+        }
 
-        // let text = response.text_async().await.unwrap();
+        let count = vulnerabilities
+            .iter()
+            .filter(|v| v.advisory.severity >= level)
+            .count();
 
-        // let response: AuditResponse = serde_json::from_str(text.as_str()).unwrap();
+        if count > 0 {
+            return Err(VoltError::AuditFailed {
+                count,
+                level: level.to_string(),
+            }
+            .into());
+        }
 
         Ok(())
     }
@@ -0,0 +1,140 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Remove a project's node_modules, and optionally its packages in the store.
+
+use crate::commands::why_not::installed_manifests;
+use crate::core::model::lock_file::LockFile;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::linker::remove_dir_without_following;
+use crate::core::utils::{store_directory_for, sweep_volt_dir};
+use crate::{core::VERSION, App, Command};
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Clean` command.
+pub struct Clean;
+
+fn remove(path: &Path) -> Result<()> {
+    remove_dir_without_following(path).map_err(|e| VoltError::RemoveDirError {
+        source: e,
+        name: path.to_string_lossy().to_string(),
+    })?;
+
+    Ok(())
+}
+
+/// Every `(name, version)` the project installed, from node_modules and volt.lock.
+fn project_packages(app: &App) -> BTreeSet<(String, String)> {
+    let mut packages: BTreeSet<(String, String)> = installed_manifests(&app.node_modules_dir)
+        .into_iter()
+        .filter_map(|(_, manifest)| {
+            Some((
+                manifest.get("name")?.as_str()?.to_string(),
+                manifest.get("version")?.as_str()?.to_string(),
+            ))
+        })
+        .collect();
+
+    if let Ok(lock_file) = LockFile::load(&app.lock_file_path) {
+        packages.extend(
+            lock_file
+                .dependencies
+                .values()
+                .map(|dependency| (dependency.name.clone(), dependency.version.clone())),
+        );
+    }
+
+    packages
+}
+
+#[async_trait]
+impl Command for Clean {
+    /// Display a help menu for the `volt clean` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Remove the project's node_modules.
+
+Usage: {} {} {}
+
+Options:
+
+  {} Also remove the project's packages from the volt store."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "clean".bright_purple(),
+            "[flags]".white(),
+            "--store".blue(),
+        )
+    }
+
+    /// Execute the `volt clean` command
+    ///
+    /// Removes `node_modules` without following the symlinks and junctions inside it.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Remove node_modules
+    /// // .exec() is an async call so you need to await it
+    /// Clean.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        // read what's installed before node_modules is gone
+        if app.has_flag("store") {
+            let mut removed = 0;
+
+            for (name, version) in project_packages(&app) {
+                let directory = store_directory_for(&app, &name, &version);
+
+                if directory.exists() {
+                    remove(&directory)?;
+                    removed += 1;
+                }
+            }
+
+            // drops the scope directories left empty
+            sweep_volt_dir(&app.volt_dir)?;
+
+            println!(
+                "{}: removed {} packages from {}",
+                "success".bright_green(),
+                removed,
+                app.volt_dir.display()
+            );
+        }
+
+        if std::fs::symlink_metadata(&app.node_modules_dir).is_ok() {
+            remove(&app.node_modules_dir)?;
+
+            println!("{}: removed node_modules", "success".bright_green());
+        } else {
+            println!("No node_modules to remove.");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Manage volt configuration stored in `.voltrc`.
+
+use crate::{core::VERSION, App, Command};
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Config` command.
+pub struct Config;
+
+#[async_trait]
+impl Command for Config {
+    /// Display a help menu for the `volt config` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Manage the volt configuration files.
+
+Usage: {} {} {} {}
+
+Commands:
+  get <key>           Print the value of a key.
+  set <key> <value>   Set the value of a key.
+  delete <key>        Remove a key.
+  list                Print all settings.
+
+Options:
+
+  {} {} Read and write ~/.voltrc instead of the project's .voltrc."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "config".bright_purple(),
+            "[command]".white(),
+            "[flags]".white(),
+            "--global".blue(),
+            "(-g)".yellow()
+        )
+    }
+
+    /// Execute the `volt config` command
+    ///
+    /// Read or update settings in the project (`./.voltrc`) or home (`~/.voltrc`) configuration.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Set the registry for the current project
+    /// // .exec() is an async call so you need to await it
+    /// Config.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let mut config = app.config.clone();
+
+        match app.args.subcommand() {
+            Some(("get", args)) => {
+                let key = args.value_of("key").unwrap();
+
+                match config.get(key) {
+                    Some(value) => println!("{}", value),
+                    None => println!("{}", "undefined".bright_black()),
+                }
+            }
+            Some(("set", args)) => {
+                let key = args.value_of("key").unwrap();
+                let value = args.value_of("value").unwrap();
+
+                config.set(key, value, args.is_present("global"));
+                config.save(args.is_present("global"))?;
+
+                println!(
+                    "{}: set {} = {}",
+                    "success".bright_green(),
+                    key.bright_cyan(),
+                    value
+                );
+            }
+            Some(("delete", args)) => {
+                let key = args.value_of("key").unwrap();
+
+                if config.delete(key, args.is_present("global")).is_some() {
+                    config.save(args.is_present("global"))?;
+                    println!(
+                        "{}: deleted {}",
+                        "success".bright_green(),
+                        key.bright_cyan()
+                    );
+                } else {
+                    println!("{} is not set", key.bright_cyan());
+                }
+            }
+            Some(("list", _)) => {
+                for (key, value) in config.list() {
+                    println!("{} = {}", key.bright_cyan(), value);
+                }
+            }
+            _ => println!("{}", Self::help()),
+        }
+
+        Ok(())
+    }
+}
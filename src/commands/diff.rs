@@ -0,0 +1,317 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Compare the published contents of two package versions.
+
+use crate::commands::view::select_version;
+use crate::core::model::http_manager::get_package;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::events::TerminalReporter;
+use crate::core::utils::hasher::integrity_from_shasum;
+use crate::core::utils::npm::parse_versions;
+use crate::core::utils::tarball::TarballFormat;
+use crate::core::utils::voltapi::VoltPackage;
+use crate::core::utils::{fetch_verified_tarball, store_directory_for};
+use crate::{core::VERSION, App, Command};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+use tar::Archive;
+use walkdir::WalkDir;
+
+/// Struct implementation for the `Diff` command.
+pub struct Diff;
+
+/// Files of a package version, by their path inside the package.
+type PackageFiles = BTreeMap<String, Vec<u8>>;
+
+/// Largest line diff computed, in lines of the old file times lines of the new one.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Reads a package already extracted in the store.
+fn read_store(directory: &Path) -> Result<PackageFiles> {
+    let mut files = PackageFiles::new();
+
+    for entry in WalkDir::new(directory).into_iter().flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(directory).unwrap();
+
+        let data = std::fs::read(entry.path()).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: entry.path().to_string_lossy().to_string(),
+        })?;
+
+        files.insert(relative.to_string_lossy().replace('\\', "/"), data);
+    }
+
+    Ok(files)
+}
+
+/// Reads the files out of a tarball, dropping its top-level `package/` directory.
+fn read_tarball(data: &[u8], package: &str) -> Result<PackageFiles> {
+    let mut files = PackageFiles::new();
+    let format = TarballFormat::detect(data, package)?;
+    let mut archive = Archive::new(format.reader(data));
+
+    let corrupt = |_| VoltError::CorruptTarball {
+        package: package.to_string(),
+    };
+
+    for entry in archive.entries().map_err(corrupt)? {
+        let mut entry = entry.map_err(corrupt)?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path().map_err(corrupt)?;
+
+        let relative = path
+            .components()
+            .skip(1)
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let mut data = vec![];
+        entry.read_to_end(&mut data).map_err(corrupt)?;
+
+        files.insert(relative, data);
+    }
+
+    Ok(files)
+}
+
+/// Gets the files of a package version, from the store when it's there and otherwise by
+/// downloading and verifying its tarball. Nothing is written to the store.
+async fn package_files(app: &App, spec: &str) -> Result<(String, PackageFiles)> {
+    let package = parse_versions(&vec![spec.to_string()])?
+        .pop()
+        .ok_or_else(|| VoltError::VersionLookupError {
+            name: spec.to_string(),
+        })?;
+
+    let registry = app.registry_for(&package.name);
+
+    let lookup_error = || VoltError::PackageNotFound {
+        url: format!("{}/{}", registry, package.name),
+        package_name: package.name.clone(),
+    };
+
+    let packument = get_package(&registry, &package.name, &app.registry_headers(&registry))
+        .await
+        .map_err(|_| lookup_error())?
+        .ok_or_else(lookup_error)?;
+
+    let version = select_version(&packument, package.version.as_deref()).ok_or_else(|| {
+        VoltError::VersionLookupError {
+            name: package.name.clone(),
+        }
+    })?;
+    let label = format!("{}@{}", package.name, version.version);
+
+    let stored = store_directory_for(app, &package.name, &version.version).join(&package.name);
+
+    if stored.join("package.json").exists() {
+        return Ok((label, read_store(&stored)?));
+    }
+
+    let integrity = if version.dist.integrity.is_empty() {
+        integrity_from_shasum(&version.dist.shasum)?.to_string()
+    } else {
+        version.dist.integrity.clone()
+    };
+
+    let tarball = VoltPackage {
+        name: package.name.clone(),
+        version: version.version.clone(),
+        tarball: version.dist.tarball.clone(),
+        bin: None,
+        integrity,
+        peer_dependencies: None,
+        peer_dependencies_meta: None,
+        dependencies: None,
+        optional_dependencies: None,
+        os: None,
+        cpu: None,
+        funding: None,
+        unpacked_size: None,
+    };
+
+    let (data, _) = fetch_verified_tarball(app, &tarball, &TerminalReporter::new()).await?;
+
+    Ok((label, read_tarball(&data, &package.name)?))
+}
+
+/// Prints the lines removed from `old` and added in `new`, grouped into hunks.
+fn print_line_diff(old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    if old.len() * new.len() > MAX_DIFF_CELLS {
+        println!(
+            "{}",
+            format!("({} -> {} lines, too large to diff)", old.len(), new.len()).bright_black()
+        );
+        return;
+    }
+
+    // longest common subsequence, lcs[i][j] for old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut in_hunk = false;
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+            in_hunk = false;
+            continue;
+        }
+
+        if !in_hunk {
+            println!("{}", format!("@@ -{} +{} @@", i + 1, j + 1).bright_cyan());
+            in_hunk = true;
+        }
+
+        if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            println!("{}", format!("-{}", old[i]).bright_red());
+            i += 1;
+        } else {
+            println!("{}", format!("+{}", new[j]).bright_green());
+            j += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl Command for Diff {
+    /// Display a help menu for the `volt diff` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Compare the published files of two package versions.
+
+Usage: {} {} {} {} {}
+
+Versions can be exact, a range or a dist-tag, e.g. volt diff react@16 react@17.
+
+Options:
+
+  {} Only list the paths that changed."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "diff".bright_purple(),
+            "<package>@<version>".white(),
+            "<package>@<version>".white(),
+            "[flags]".white(),
+            "--name-only".blue(),
+        )
+    }
+
+    /// Execute the `volt diff` command
+    ///
+    /// Shows which files were added, removed or changed between two package versions.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Compare react 16 and 17
+    /// // .exec() is an async call so you need to await it
+    /// Diff.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let first = app.args.value_of("first").unwrap();
+        let second = app.args.value_of("second").unwrap();
+
+        let ((old_label, old), (new_label, new)) =
+            futures::try_join!(package_files(&app, first), package_files(&app, second))?;
+
+        let paths: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        let mut changed = 0;
+
+        for path in paths {
+            let (before, after) = (old.get(path), new.get(path));
+
+            if before == after {
+                continue;
+            }
+
+            changed += 1;
+
+            if app.has_flag("name-only") {
+                println!("{}", path);
+                continue;
+            }
+
+            println!(
+                "{}",
+                format!("diff {}/{} {}/{}", old_label, path, new_label, path).bold()
+            );
+
+            match (before, after) {
+                (None, Some(_)) => println!("{}", "added".bright_green()),
+                (Some(_), None) => println!("{}", "removed".bright_red()),
+                (Some(before), Some(after)) => {
+                    match (std::str::from_utf8(before), std::str::from_utf8(after)) {
+                        (Ok(before), Ok(after)) => print_line_diff(before, after),
+                        _ => println!("{}", "binary files differ".bright_black()),
+                    }
+                }
+                (None, None) => {}
+            }
+
+            println!();
+        }
+
+        if !app.has_flag("name-only") {
+            println!(
+                "{} files changed between {} and {}",
+                changed,
+                old_label.bright_cyan(),
+                new_label.bright_cyan()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! List installed packages that are looking for funding.
+
+use crate::core::utils::voltapi::Funding;
+use crate::{core::VERSION, App, Command};
+
+use std::collections::BTreeMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+use serde_json::Value;
+
+/// Struct implementation for the `Fund` command.
+pub struct Fund;
+
+/// Reads the `funding` field of every package installed in `node_modules`, keyed by `name@version`.
+pub fn installed_funding(node_modules: &Path) -> BTreeMap<String, Funding> {
+    let mut funding = BTreeMap::new();
+
+    let entries = match read_dir(node_modules) {
+        Ok(entries) => entries,
+        Err(_) => return funding,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // @types/node lives in node_modules/@types/node
+        let packages = if name.starts_with('@') {
+            read_dir(&path)
+                .map(|scoped| scoped.flatten().map(|e| e.path()).collect())
+                .unwrap_or_else(|_| vec![])
+        } else {
+            vec![path]
+        };
+
+        for package in packages {
+            let manifest: Value = match read_to_string(package.join("package.json"))
+                .ok()
+                .and_then(|data| serde_json::from_str(&data).ok())
+            {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+
+            let package_funding = match manifest
+                .get("funding")
+                .and_then(|value| serde_json::from_value::<Funding>(value.clone()).ok())
+            {
+                Some(package_funding) => package_funding,
+                None => continue,
+            };
+
+            funding.insert(
+                format!(
+                    "{}@{}",
+                    manifest["name"].as_str().unwrap_or_default(),
+                    manifest["version"].as_str().unwrap_or_default()
+                ),
+                package_funding,
+            );
+        }
+    }
+
+    funding
+}
+
+#[async_trait]
+impl Command for Fund {
+    /// Display a help menu for the `volt fund` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+List installed packages that are looking for funding.
+
+Usage: {} {}"#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "fund".bright_purple(),
+        )
+    }
+
+    /// Execute the `volt fund` command
+    ///
+    /// List installed packages that are looking for funding, along with their funding urls.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // List packages looking for funding
+    /// // .exec() is an async call so you need to await it
+    /// Fund.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let funding = installed_funding(&app.node_modules_dir);
+
+        if funding.is_empty() {
+            println!("No installed packages are looking for funding.");
+            return Ok(());
+        }
+
+        for (package, funding) in funding {
+            println!("{}", package.bright_cyan());
+
+            for url in funding.urls() {
+                println!("  {}", url);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Import the versions pinned by another package manager's lockfile.
+
+use crate::core::model::lock_file::{DependencyID, DependencyLock, LockFile};
+use crate::core::utils::{
+    clean_up_partial_installs,
+    errors::VoltError,
+    events::{InstallEvent, Reporter, TerminalReporter},
+    failures::InstallFailures,
+    install_extract_package,
+    install_lock::InstallLock,
+    layout::{check_layout, LayoutState},
+    link_strategy::LinkStrategy,
+    platform::Platform,
+    print_sha1_warning,
+    stats::InstallStats,
+    upgrade_sha1_integrities,
+    voltapi::VoltPackage,
+};
+use crate::{core::VERSION, App, Command};
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use futures::{stream::FuturesUnordered, StreamExt};
+use miette::Result;
+use serde::Deserialize;
+
+/// Struct implementation for the `Import` command.
+pub struct Import;
+
+/// A `package-lock.json`, only the parts volt needs.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NpmLockfile {
+    lockfile_version: u32,
+    #[serde(default)]
+    packages: HashMap<String, NpmLockEntry>,
+}
+
+/// An entry of `packages` in a `package-lock.json`, keyed by its path, e.g. `node_modules/a/node_modules/b`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NpmLockEntry {
+    name: Option<String>,
+    version: Option<String>,
+    resolved: Option<String>,
+    integrity: Option<String>,
+    #[serde(default)]
+    link: bool,
+    #[serde(default)]
+    in_bundle: bool,
+    bin: Option<HashMap<String, String>>,
+    #[serde(default)]
+    dependencies: HashMap<String, String>,
+    #[serde(default)]
+    optional_dependencies: HashMap<String, String>,
+    os: Option<Vec<String>>,
+    cpu: Option<Vec<String>>,
+}
+
+/// Package name of a lockfile path, `node_modules/a/node_modules/@scope/b` -> `@scope/b`.
+fn package_name(path: &str) -> &str {
+    path.rsplit("node_modules/").next().unwrap_or(path)
+}
+
+/// Finds the entry a dependency of the package at `path` resolves to, the way node does:
+/// the closest `node_modules` walking up from the package.
+fn resolve_dependency<'a>(
+    packages: &'a HashMap<String, NpmLockEntry>,
+    path: &str,
+    dependency: &str,
+) -> Option<&'a NpmLockEntry> {
+    let mut directory = path;
+
+    loop {
+        let candidate = if directory.is_empty() {
+            format!("node_modules/{}", dependency)
+        } else {
+            format!("{}/node_modules/{}", directory, dependency)
+        };
+
+        if let Some(entry) = packages.get(&candidate) {
+            return Some(entry);
+        }
+
+        if directory.is_empty() {
+            return None;
+        }
+
+        // node_modules/a/node_modules/b -> node_modules/a
+        directory = match directory.rfind("/node_modules/") {
+            Some(index) => &directory[..index],
+            None => "",
+        };
+    }
+}
+
+/// The `node_modules` directories, relative to the project, each `name@version` is installed in.
+type Placements = HashMap<String, Vec<PathBuf>>;
+
+/// Converts a `package-lock.json` (v2 or v3) into the packages it pins, keyed by `name@version`,
+/// and the `node_modules` directories (relative to the project) npm put each of them in.
+fn read_npm_lockfile(data: &str) -> Result<(HashMap<String, VoltPackage>, Placements)> {
+    let lockfile: NpmLockfile = serde_json::from_str(data).map_err(|e| VoltError::ImportError {
+        file: String::from("package-lock.json"),
+        reason: e.to_string(),
+    })?;
+
+    // v1 lockfiles only have the nested `dependencies` tree, without os/cpu or bin
+    if lockfile.lockfile_version < 2 {
+        return Err(VoltError::ImportError {
+            file: String::from("package-lock.json"),
+            reason: format!(
+                "lockfileVersion {} is not supported, run `npm install` with npm 7 or newer to upgrade it",
+                lockfile.lockfile_version
+            ),
+        }
+        .into());
+    }
+
+    let key = |name: &str, entry: &NpmLockEntry| {
+        format!("{}@{}", name, entry.version.as_deref().unwrap_or_default())
+    };
+
+    let mut packages = HashMap::new();
+    let mut placements: Placements = HashMap::new();
+
+    for (path, entry) in lockfile.packages.iter() {
+        // the project itself, workspace links and packages shipped inside another one
+        if path.is_empty() || entry.link || entry.in_bundle {
+            continue;
+        }
+
+        let name = entry.name.as_deref().unwrap_or_else(|| package_name(path));
+
+        let (version, tarball, integrity) =
+            match (&entry.version, &entry.resolved, &entry.integrity) {
+                (Some(version), Some(tarball), Some(integrity)) => (version, tarball, integrity),
+                _ => {
+                    return Err(VoltError::ImportError {
+                        file: String::from("package-lock.json"),
+                        reason: format!("{} has no version, resolved url or integrity", path),
+                    }
+                    .into());
+                }
+            };
+
+        let edges = |dependencies: &HashMap<String, String>| {
+            let mut edges: Vec<String> = dependencies
+                .keys()
+                .filter_map(|dependency| {
+                    resolve_dependency(&lockfile.packages, path, dependency)
+                        .map(|child| key(dependency, child))
+                })
+                .collect();
+
+            edges.sort();
+            edges
+        };
+
+        let optional_dependencies = edges(&entry.optional_dependencies);
+
+        // volt lists optional dependencies among the dependencies too
+        let mut dependencies = edges(&entry.dependencies);
+        dependencies.extend(optional_dependencies.iter().cloned());
+
+        // node_modules/a/node_modules/b -> node_modules/a/node_modules
+        let directory = path[..path.len() - package_name(path).len()].trim_end_matches('/');
+
+        placements
+            .entry(key(name, entry))
+            .or_default()
+            .push(PathBuf::from(directory));
+
+        packages.insert(
+            key(name, entry),
+            VoltPackage {
+                name: name.to_string(),
+                version: version.clone(),
+                tarball: tarball.clone(),
+                bin: entry.bin.clone(),
+                integrity: integrity.clone(),
+                peer_dependencies: None,
+                peer_dependencies_meta: None,
+                dependencies: Some(dependencies),
+                optional_dependencies: Some(optional_dependencies),
+                os: entry.os.clone(),
+                cpu: entry.cpu.clone(),
+                funding: None,
+                unpacked_size: None,
+            },
+        );
+    }
+
+    Ok((packages, placements))
+}
+
+#[async_trait]
+impl Command for Import {
+    /// Display a help menu for the `volt import` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Install the exact versions pinned by another package manager's lockfile.
+
+Usage: {} {}
+
+Reads package-lock.json (lockfileVersion 2 or 3), writes volt.lock and installs
+the packages without resolving them again."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "import".bright_purple(),
+        )
+    }
+
+    /// Execute the `volt import` command
+    ///
+    /// Translates `package-lock.json` into `volt.lock` and installs what it pins.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Switch a project from npm to volt
+    /// // .exec() is an async call so you need to await it
+    /// Import.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let package_lock = app.current_dir.join("package-lock.json");
+
+        if !package_lock.exists() {
+            // recognise the other lockfiles, they just aren't translated yet
+            for file in ["yarn.lock", "pnpm-lock.yaml"].iter() {
+                if app.current_dir.join(file).exists() {
+                    return Err(VoltError::ImportError {
+                        file: file.to_string(),
+                        reason: String::from("only package-lock.json can be imported for now"),
+                    }
+                    .into());
+                }
+            }
+
+            return Err(VoltError::NoLockfileToImport.into());
+        }
+
+        let data = read_to_string(&package_lock).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: String::from("package-lock.json"),
+        })?;
+
+        let (mut packages, placements) = read_npm_lockfile(&data)?;
+
+        // npm locks optional platform packages (fsevents, esbuild binaries) for every platform
+        let platform = Platform::host();
+        packages.retain(|_, package| platform.supports(package));
+
+        // Catch an invalid `node-linker` in .voltrc before anything is downloaded
+        app.node_linker()?;
+
+        let _project_lock = InstallLock::acquire(&app.node_modules_dir.join(".volt-install.lock"))?;
+        let _store_lock = InstallLock::acquire(&app.volt_dir.join(".lock"))?;
+
+        check_layout(&app.node_modules_dir, LinkStrategy::Hoisted)?;
+
+        let mut lock_file = LockFile::new(&app.lock_file_path);
+
+        for package in packages.values() {
+            lock_file.dependencies.insert(
+                DependencyID(package.name.clone(), package.version.clone()),
+                DependencyLock::from_package(package),
+            );
+        }
+
+        let reporter = TerminalReporter::new();
+        let reporter_ref = &reporter;
+
+        reporter.report(InstallEvent::InstallStarted {
+            packages: packages.len(),
+        });
+
+        let app_ref = &app;
+        let stats = InstallStats::default();
+        let stats_ref = &stats;
+
+        // keep npm's layout, nested versions included
+        let placements: HashMap<&str, Vec<PathBuf>> = placements
+            .iter()
+            .map(|(key, directories)| {
+                let directories = directories
+                    .iter()
+                    .map(|directory| app.current_dir.join(directory))
+                    .collect();
+
+                (key.as_str(), directories)
+            })
+            .collect();
+
+        let placements_ref = &placements;
+
+        let mut installs = packages
+            .iter()
+            .map(|(key, package)| async move {
+                let node_modules_dirs = &placements_ref[key.as_str()];
+                let result = install_extract_package(
+                    app_ref,
+                    package,
+                    stats_ref,
+                    node_modules_dirs,
+                    reporter_ref,
+                )
+                .await;
+                (package, node_modules_dirs, result)
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut failures = InstallFailures::default();
+        let mut failed = vec![];
+
+        while let Some((package, node_modules_dirs, result)) = installs.next().await {
+            if let Err(error) = result {
+                reporter.report(InstallEvent::PackageFailed {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                });
+
+                failures.push(package.name.clone(), error);
+                failed.push((package, node_modules_dirs.as_slice()));
+            }
+        }
+
+        if !failures.is_empty() {
+            reporter.report(InstallEvent::InstallFailed);
+
+            clean_up_partial_installs(&app, failed.into_iter());
+            failures.print();
+
+            return Err(VoltError::InstallFailed {
+                count: failures.len(),
+            }
+            .into());
+        }
+
+        reporter.report(InstallEvent::InstallFinished);
+
+        LayoutState::current(LinkStrategy::Hoisted).save(&app.node_modules_dir)?;
+
+        // packages only published with a sha1 are locked with the sha512 computed on download
+        let sha1_only = upgrade_sha1_integrities(&app, &mut lock_file);
+
+        lock_file.save().map_err(VoltError::SaveLockFileError)?;
+
+        print_sha1_warning(&sha1_only);
+
+        println!(
+            "{}: imported {} packages from package-lock.json",
+            "success".bright_green(),
+            packages.len()
+        );
+
+        Ok(())
+    }
+}
@@ -16,8 +16,18 @@
 
 //! Installs dependencies for a project.
 
+use crate::commands::add::{install_packages, Add, Package};
+use crate::core::model::lock_file::LockFile;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::package::{DependencyType, PackageJson};
+use crate::core::utils::store_directory_for;
+use crate::core::utils::voltapi::VoltPackage;
 use crate::{core::VERSION, App, Command};
 
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -27,6 +37,250 @@ use miette::Result;
 /// Struct implementation for the `Install` command.
 pub struct Install;
 
+/// A way `node_modules` differs from the lockfile, found by `volt install --check`.
+#[derive(Debug)]
+pub enum Discrepancy {
+    Missing {
+        name: String,
+        version: String,
+    },
+    WrongVersion {
+        name: String,
+        expected: String,
+        found: String,
+    },
+    NotInStore {
+        name: String,
+        version: String,
+    },
+    Modified {
+        name: String,
+        file: String,
+    },
+    NotLocked {
+        name: String,
+        range: String,
+    },
+    BrokenLink {
+        link: String,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Discrepancy::Missing { name, version } => {
+                write!(f, "{}@{} is not installed", name, version)
+            }
+            Discrepancy::WrongVersion {
+                name,
+                expected,
+                found,
+            } => write!(f, "{} is {}, the lockfile has {}", name, found, expected),
+            Discrepancy::NotInStore { name, version } => {
+                write!(f, "{}@{} is missing from the volt store", name, version)
+            }
+            Discrepancy::Modified { name, file } => {
+                write!(f, "{} differs from the store copy ({})", name, file)
+            }
+            Discrepancy::NotLocked { name, range } => {
+                write!(f, "{}@{} in package.json is not locked", name, range)
+            }
+            Discrepancy::BrokenLink { link } => {
+                write!(f, "{} doesn't link to its package", link)
+            }
+        }
+    }
+}
+
+/// Picks the package.json sections to install from `--include` and `--omit`.
+///
+/// Everything but peerDependencies is installed by default. Like npm, a section both
+/// included and omitted is installed.
+fn selected_sections(app: &App) -> Vec<DependencyType> {
+    let listed = |arg: &str| -> Vec<DependencyType> {
+        app.args
+            .values_of(arg)
+            .map(|kinds| kinds.filter_map(DependencyType::parse).collect())
+            .unwrap_or_default()
+    };
+
+    let include = listed("include");
+    let omit = listed("omit");
+
+    [
+        DependencyType::Dependencies,
+        DependencyType::DevDependencies,
+        DependencyType::OptionalDependencies,
+        DependencyType::PeerDependencies,
+    ]
+    .iter()
+    .copied()
+    .filter(|section| {
+        include.contains(section)
+            || (*section != DependencyType::PeerDependencies && !omit.contains(section))
+    })
+    .collect()
+}
+
+/// The packages `volt install` installs from package.json, in the sections picked by
+/// `--include` and `--omit`.
+fn project_packages(app: &App, package_file: &PackageJson) -> Vec<Package> {
+    // a library's peers are usually in devDependencies too, the first section listing a
+    // dependency decides its range
+    let mut ranges: BTreeMap<&str, &str> = BTreeMap::new();
+
+    for section in selected_sections(app) {
+        for (name, range) in package_file.dependencies_in(section) {
+            ranges.entry(name).or_insert(range);
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(name, range)| Package {
+            name: name.to_string(),
+            version: Some(range.to_string()),
+        })
+        .collect()
+}
+
+/// Version of an installed package, from its `package.json`.
+fn installed_version(package_directory: &Path) -> Option<String> {
+    read_to_string(package_directory.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+        .and_then(|manifest| manifest["version"].as_str().map(String::from))
+}
+
+/// Finds the first file of a package in node_modules whose contents differ from the store.
+fn modified_file(store: &Path, installed: &Path) -> Option<String> {
+    walkdir::WalkDir::new(store)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .find_map(|entry| {
+            let relative = entry.path().strip_prefix(store).ok()?;
+            let expected = std::fs::read(entry.path()).ok()?;
+
+            match std::fs::read(installed.join(relative)) {
+                Ok(actual) if actual == expected => None,
+                _ => Some(relative.to_string_lossy().to_string()),
+            }
+        })
+}
+
+/// Where a package or link is in node_modules, e.g. `express/node_modules/qs`.
+fn node_modules_label(app: &App, path: &Path) -> String {
+    path.strip_prefix(&app.node_modules_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Whether `link` resolves to the same directory as `target`.
+fn links_to(link: &Path, target: &Path) -> bool {
+    match (std::fs::canonicalize(link), std::fs::canonicalize(target)) {
+        (Ok(link), Ok(target)) => link == target,
+        _ => false,
+    }
+}
+
+/// Checks one placement of a package: installed at its locked version, present in the volt
+/// store, and with the same contents as its store copy.
+fn check_package(app: &App, package: &VoltPackage, installed: &Path) -> Option<Discrepancy> {
+    let label = node_modules_label(app, installed);
+
+    let version = match installed_version(installed) {
+        Some(version) => version,
+        None => {
+            return Some(Discrepancy::Missing {
+                name: label,
+                version: package.version.clone(),
+            })
+        }
+    };
+
+    if version != package.version {
+        return Some(Discrepancy::WrongVersion {
+            name: label,
+            expected: package.version.clone(),
+            found: version,
+        });
+    }
+
+    let store = store_directory_for(app, &package.name, &package.version).join(&package.name);
+
+    if !store.exists() {
+        return Some(Discrepancy::NotInStore {
+            name: package.name.clone(),
+            version: package.version.clone(),
+        });
+    }
+
+    modified_file(&store, installed).map(|file| Discrepancy::Modified { name: label, file })
+}
+
+/// Compares node_modules with the lockfile.
+///
+/// The tree locked for `roots` is laid out with the project's link strategy the way an
+/// install would, and every placement of every package is checked, nested versions and
+/// the isolated layouts' `.volt` directories included, along with the links the layout
+/// relies on.
+pub fn check_node_modules(
+    app: &App,
+    lock_file: &LockFile,
+    roots: &[Package],
+) -> Result<Vec<Discrepancy>> {
+    let mut discrepancies = vec![];
+    let reached = lock_file.reachable(roots);
+
+    for root in roots {
+        if !reached.versions.contains_key(&root.name) {
+            discrepancies.push(Discrepancy::NotLocked {
+                name: root.name.clone(),
+                range: root.version.clone().unwrap_or_default(),
+            });
+        }
+    }
+
+    let tree: HashMap<String, VoltPackage> = reached
+        .entries
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.to_partial_package()))
+        .collect();
+
+    let plan = app.link_strategy()?.layout().plan(app, roots, &tree);
+
+    let mut keys: Vec<&String> = tree.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let package = &tree[key];
+
+        // left out of the plan means the project's node_modules
+        let directories: Vec<PathBuf> = plan
+            .placements
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| vec![app.node_modules_dir.clone()]);
+
+        for directory in directories {
+            discrepancies.extend(check_package(app, package, &directory.join(&package.name)));
+        }
+    }
+
+    for (link, target) in plan.links.iter() {
+        if !links_to(link, target) {
+            discrepancies.push(Discrepancy::BrokenLink {
+                link: node_modules_label(app, link),
+            });
+        }
+    }
+
+    Ok(discrepancies)
+}
+
 #[async_trait]
 impl Command for Install {
     /// Display a help menu for the `volt install` command.
@@ -34,42 +288,205 @@ impl Command for Install {
         format!(
             r#"volt {}
         
-Install dependencies for a project.
+Install dependencies for a project, or add packages to it.
 
-Usage: {} {} {}
+Usage: {} {} {} {}
     
 Options: 
     
   {} {} Accept all prompts while installing dependencies.  
-  {} {} Output verbose messages on internal operations."#,
+  {} {} Output verbose messages on internal operations.
+  {} Install the given packages without saving them to package.json.
+  {} Skip optionalDependencies.
+  {} Print cache, download and timing statistics after installing.
+  {} Keep the resolved tree on disk and install a few packages at a time.
+  {} Adapt how many packages download at once to the connection.
+  {} Check packages in the store for changed files before using them.
+  {} Install even when peer dependencies conflict, warning about them.
+  {} {} Refuse tarballs larger than this (default 512 MiB).
+  {} {} Lay node_modules out flat like npm (default) or linked like pnpm.
+  {} Download packages into the store without touching node_modules or package.json.
+  {} Fail on packages the registry hasn't signed, or whose signature doesn't match.
+  {} Install missing peer dependencies, skipping optional ones.
+  {} {} Write the order packages are fetched, extracted and linked in to a file.
+  {} Allow tarballs served over plain http, warning about each one.
+  {} Install nothing, fail if node_modules doesn't match volt.lock.
+  {} {} Also install these package.json sections (prod, dev, optional, peer).
+  {} {} Skip these package.json sections."#,
             VERSION.bright_green().bold(),
             "volt".bright_green().bold(),
             "install".bright_purple(),
+            "[packages]".white(),
             "[flags]".white(),
             "--yes".blue(),
             "(-y)".yellow(),
             "--verbose".blue(),
-            "(-v)".yellow()
+            "(-v)".yellow(),
+            "--no-save".blue(),
+            "--no-optional".blue(),
+            "--stats".blue(),
+            "--low-memory".blue(),
+            "--adaptive-concurrency".blue(),
+            "--verify-store".blue(),
+            "--legacy-peer-deps".blue(),
+            "--max-tarball-size".blue(),
+            "<bytes>".yellow(),
+            "--link-strategy".blue(),
+            "<hoisted|isolated|pnpm-strict>".yellow(),
+            "--fetch-only".blue(),
+            "--verify-signatures".blue(),
+            "--install-peers".blue(),
+            "--record-order".blue(),
+            "<file>".yellow(),
+            "--allow-insecure-tarballs".blue(),
+            "--check".blue(),
+            "--include".blue(),
+            "<types>".yellow(),
+            "--omit".blue(),
+            "<types>".yellow()
         )
     }
 
     /// Execute the `volt install` command
     ///
-    /// Install dependencies for a project.
+    /// Install the dependencies in package.json, or add packages like `volt add` when given any.
+    /// The tree in volt.lock is reused when it still satisfies every range in package.json.
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
-    /// * `packages` - List of packages to add (`Vec<String>`)
-    /// * `flags` - List of flags passed in through the CLI (`Vec<String>`)
     /// ## Examples
     /// ```
     /// // Install dependencies for a project with logging level verbose
     /// // .exec() is an async call so you need to await it
-    /// Install.exec(app, vec![], vec!["--verbose"]).await;
+    /// Install.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
-        // let package_file
-        Ok(())
+    async fn exec(app: Arc<App>) -> Result<()> {
+        // a guard for cached node_modules in CI, nothing is installed
+        if app.has_flag("check") {
+            if !app.lock_file_path.exists() {
+                return Err(VoltError::LockFileNotFound.into());
+            }
+
+            let lock_file =
+                LockFile::load(&app.lock_file_path).map_err(VoltError::ReadLockFileError)?;
+
+            let (package_file, _) = PackageJson::open("package.json")?;
+            let roots = project_packages(&app, &package_file);
+
+            let discrepancies = check_node_modules(&app, &lock_file, &roots)?;
+
+            if discrepancies.is_empty() {
+                println!(
+                    "{}: node_modules matches volt.lock ({} packages)",
+                    "success".bright_green(),
+                    lock_file.dependencies.len()
+                );
+
+                return Ok(());
+            }
+
+            for discrepancy in discrepancies.iter() {
+                println!("  {} {}", "-".bright_red(), discrepancy);
+            }
+
+            println!();
+
+            return Err(VoltError::NodeModulesOutOfDate {
+                count: discrepancies.len(),
+            }
+            .into());
+        }
+
+        // volt install express behaves like volt add express
+        if app.args.values_of("package-names").is_some() {
+            return Add::exec(app).await;
+        }
+
+        let (package_file, _) = PackageJson::open("package.json")?;
+        let packages = project_packages(&app, &package_file);
+
+        if packages.is_empty() {
+            println!("{}: no dependencies to install", "info".bright_purple());
+            return Ok(());
+        }
+
+        install_packages(app, packages, false, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::add::install_packages_with;
+    use crate::core::model::config::VoltConfig;
+    use crate::core::utils::events::InstallEvent;
+    use crate::core::utils::testing::{fake_registry, RecordingReporter};
+
+    #[tokio::test]
+    async fn installs_reuse_the_lock_written_by_add() {
+        let root = std::env::temp_dir().join(format!("volt-install-lock-{}", std::process::id()));
+        let (registry, requests) = fake_registry(&[("is-odd", "3.0.1")]);
+
+        let mut config = VoltConfig::default();
+        config.set("registry", &registry, false);
+        config.set("volt-cdn", &format!("{}/cdn", registry), false);
+        config.set("allow-insecure-tarballs", "true", false);
+
+        let args = clap::App::new("install").get_matches_from(vec!["install"]);
+        let app = Arc::new(App::for_tests_in(&root, args, config));
+
+        std::fs::create_dir_all(&app.current_dir).unwrap();
+        std::fs::create_dir_all(&app.volt_dir).unwrap();
+        std::fs::write(
+            app.current_dir.join("package.json"),
+            r#"{ "name": "app", "version": "1.0.0" }"#,
+        )
+        .unwrap();
+
+        let added = vec![Package {
+            name: String::from("is-odd"),
+            version: None,
+        }];
+
+        install_packages_with(
+            app.clone(),
+            added,
+            true,
+            false,
+            &RecordingReporter::default(),
+        )
+        .await
+        .unwrap();
+
+        let lock_file = LockFile::load(&app.lock_file_path).unwrap();
+        assert_eq!(lock_file.dependencies.len(), 1);
+
+        // a fresh checkout: the project files, without node_modules
+        std::fs::remove_dir_all(&app.node_modules_dir).unwrap();
+        let resolved = requests.lock().unwrap().len();
+
+        let (package_file, _) = PackageJson::open(app.current_dir.join("package.json")).unwrap();
+        let packages = project_packages(&app, &package_file);
+        let reporter = RecordingReporter::default();
+
+        install_packages_with(app.clone(), packages, false, true, &reporter)
+            .await
+            .unwrap();
+
+        let installed = app.node_modules_dir.join("is-odd").join("package.json");
+        let events = reporter.events.lock().unwrap();
+
+        assert_eq!(
+            installed_version(installed.parent().unwrap()).as_deref(),
+            Some("3.0.1")
+        );
+        assert!(events.contains(&InstallEvent::Info {
+            message: String::from("resolved 1 packages from volt.lock"),
+        }));
+        // nothing was resolved or downloaded again
+        assert_eq!(requests.lock().unwrap().len(), resolved);
+
+        std::fs::remove_dir_all(&root).ok();
     }
 }
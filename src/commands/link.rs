@@ -0,0 +1,179 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Symlink a package in development into the global link store or a project.
+
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::linker::{
+    copy_local_package, is_symlink, links_dir, remove_symlink_dir, symlink_dir,
+};
+use crate::core::utils::package::PackageJson;
+use crate::{core::VERSION, App, Command};
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Link` command.
+pub struct Link;
+
+#[async_trait]
+impl Command for Link {
+    /// Display a help menu for the `volt link` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Symlink a package in development so other projects can use it.
+
+Usage: {} {} {}
+
+Run without packages inside a package to register it in the global link store,
+or with packages inside a project to symlink registered packages into node_modules.
+
+Options:
+
+  {} {} Link with junctions or symlinks on windows (defaults to auto).
+  {} Copy linked packages into node_modules instead of symlinking them."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "link".bright_purple(),
+            "[packages]".white(),
+            "--windows-link-type".blue(),
+            "<junction|symlink|auto>".yellow(),
+            "--install-links".blue(),
+        )
+    }
+
+    /// Execute the `volt link` command
+    ///
+    /// Registers the current package in `~/.volt/.links`, or symlinks linked packages into `node_modules`.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Link the package in the current directory
+    /// // .exec() is an async call so you need to await it
+    /// Link.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let links = links_dir(&app.volt_dir);
+        let link_type = app.windows_link_type()?;
+
+        // bundlers that don't follow symlinks get a copy, like npm's install-links
+        let install_links = app.has_setting("install-links", "install-links");
+
+        let packages = match app.args.values_of("package-names") {
+            Some(packages) => packages.map(String::from).collect::<Vec<String>>(),
+            None => {
+                // register the current package globally
+                let (package_file, _) = PackageJson::open("package.json")?;
+
+                let link = links.join(&package_file.name);
+
+                if is_symlink(&link) {
+                    remove_symlink_dir(&link).map_err(|e| VoltError::RemoveDirError {
+                        source: e,
+                        name: link.to_string_lossy().to_string(),
+                    })?;
+                }
+
+                symlink_dir(&app.current_dir, &link, link_type).map_err(|e| {
+                    VoltError::LinkError {
+                        source: e,
+                        name: package_file.name.clone(),
+                    }
+                })?;
+
+                println!(
+                    "{}: linked {} -> {}",
+                    "success".bright_green(),
+                    package_file.name.bright_cyan(),
+                    app.current_dir.display()
+                );
+
+                return Ok(());
+            }
+        };
+
+        for package in packages {
+            let link = links.join(&package);
+
+            if !is_symlink(&link) {
+                return Err(VoltError::PackageNotLinked { name: package }.into());
+            }
+
+            // point node_modules straight at the package, not at the link store
+            let source = std::fs::read_link(&link).map_err(|e| VoltError::LinkError {
+                source: e,
+                name: package.clone(),
+            })?;
+
+            // the linked project was moved or deleted, the link would dangle
+            if !source.exists() {
+                return Err(VoltError::LinkError {
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("{} no longer exists", source.display()),
+                    ),
+                    name: package.clone(),
+                }
+                .into());
+            }
+
+            let destination = app.node_modules_dir.join(&package);
+
+            // replace whatever volt add installed there
+            let removed = if is_symlink(&destination) {
+                remove_symlink_dir(&destination)
+            } else if destination.exists() {
+                std::fs::remove_dir_all(&destination)
+            } else {
+                Ok(())
+            };
+
+            removed.map_err(|e| VoltError::RemoveDirError {
+                source: e,
+                name: destination.to_string_lossy().to_string(),
+            })?;
+
+            let (linked, action) = if install_links {
+                (copy_local_package(&source, &destination), "copied")
+            } else {
+                (symlink_dir(&source, &destination, link_type), "linked")
+            };
+
+            linked.map_err(|e| VoltError::LinkError {
+                source: e,
+                name: package.clone(),
+            })?;
+
+            println!(
+                "{}: {} {} -> {}",
+                "success".bright_green(),
+                action,
+                package.bright_cyan(),
+                source.display()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -11,141 +11,322 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-//! Compress node_modules into node_modules.pack.
-
-use std::sync::Arc;
+//! List installed packages.
 
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::json_output::to_json;
+use crate::core::utils::package::PackageJson;
 use crate::App;
 use crate::{core::VERSION, Command};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use colored::Colorize;
 use miette::Result;
+use serde::Serialize;
+use serde_json::Value;
 
 pub struct List;
 
+/// Sections of a manifest whose packages are installed.
+const INSTALLED_SECTIONS: [&str; 2] = ["dependencies", "optionalDependencies"];
+
+/// What `volt list` shows, printed as is by `--json`.
+#[derive(Serialize)]
+struct Listing {
+    name: String,
+    version: String,
+    /// The dependencies in package.json, dev dependencies included.
+    dependencies: BTreeMap<String, Listed>,
+    /// Packages at the top of node_modules that nothing depends on.
+    extraneous: BTreeMap<String, Listed>,
+}
+
+/// A package in the listing.
+#[derive(Serialize)]
+struct Listed {
+    /// `None` when it isn't installed.
+    version: Option<String>,
+    missing: bool,
+    dev: bool,
+    /// Only filled in up to `--depth`, by name.
+    dependencies: BTreeMap<String, Listed>,
+}
+
+impl Listed {
+    fn missing(dev: bool) -> Self {
+        Self {
+            version: None,
+            missing: true,
+            dev,
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    fn installed(manifest: &Value, dev: bool, dependencies: BTreeMap<String, Listed>) -> Self {
+        Self {
+            version: Some(manifest_version(manifest).to_string()),
+            missing: false,
+            dev,
+            dependencies,
+        }
+    }
+}
+
+/// Reads the manifest of the package installed at `directory`.
+fn read_manifest(directory: &Path) -> Option<Value> {
+    read_to_string(directory.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+}
+
+/// The `version` of a manifest, empty when it has none.
+fn manifest_version(manifest: &Value) -> &str {
+    manifest
+        .get("version")
+        .and_then(|version| version.as_str())
+        .unwrap_or("")
+}
+
+/// Names of the packages directly in a node_modules directory (`react`, `@types/node`).
+fn top_level_packages(node_modules: &Path) -> Vec<String> {
+    let mut names = vec![];
+
+    for entry in read_dir(node_modules).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if name.starts_with('@') {
+            for scoped in read_dir(entry.path()).into_iter().flatten().flatten() {
+                names.push(format!("{}/{}", name, scoped.file_name().to_string_lossy()));
+            }
+        } else {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Finds where node would load `name` from for the package at `directory`: its own
+/// node_modules first, then the node_modules of every package it's nested in.
+fn resolve_installed(directory: &Path, name: &str) -> Option<PathBuf> {
+    std::iter::once(directory.join("node_modules").join(name))
+        .chain(
+            directory
+                .ancestors()
+                .filter(|ancestor| ancestor.ends_with("node_modules"))
+                .map(|node_modules| node_modules.join(name)),
+        )
+        .find(|candidate| candidate.join("package.json").exists())
+}
+
+/// Collects the dependencies of the package at `directory`, `depth` levels deep.
+fn list_dependencies(
+    directory: &Path,
+    manifest: &Value,
+    level: usize,
+    depth: usize,
+) -> BTreeMap<String, Listed> {
+    let mut listed = BTreeMap::new();
+
+    if level > depth {
+        return listed;
+    }
+
+    let names = INSTALLED_SECTIONS
+        .iter()
+        .filter_map(|section| manifest.get(section)?.as_object())
+        .flat_map(|section| section.keys());
+
+    for name in names {
+        let dependency = match resolve_installed(directory, name)
+            .and_then(|path| read_manifest(&path).map(|manifest| (path, manifest)))
+        {
+            Some((path, dependency)) => Listed::installed(
+                &dependency,
+                false,
+                list_dependencies(&path, &dependency, level + 1, depth),
+            ),
+            None => Listed::missing(false),
+        };
+
+        listed.insert(name.clone(), dependency);
+    }
+
+    listed
+}
+
+/// Prints a package of the listing and its dependencies, indented by `level`.
+fn print_listed(name: &str, listed: &Listed, level: usize, note: Option<&str>) {
+    let indent = "  ".repeat(level);
+
+    let label = match &listed.version {
+        Some(version) => format!("{}@{}", name.bright_blue(), version),
+        None => format!("{} {}", name.bright_red(), "(missing)".bright_red()),
+    };
+
+    let mut notes = String::new();
+
+    if listed.dev {
+        notes.push_str(&format!(" {}", "(dev)".bright_black()));
+    }
+
+    if let Some(note) = note {
+        notes.push_str(&format!(" {}", note.bright_yellow()));
+    }
+
+    println!("{}{} {}{}", indent, "-".bright_cyan(), label, notes);
+
+    for (name, dependency) in listed.dependencies.iter() {
+        print_listed(name, dependency, level + 1, None);
+    }
+}
+
 #[async_trait]
 impl Command for List {
     /// Display a help menu for the `volt list` command.
     fn help() -> String {
         format!(
             r#"volt {}
-    
-List dependency tree from node_modules.
-Usage: {} {} {} {}
-Options: 
-    
-  {} {} Output verbose messages on internal operations."#,
+
+List the packages installed in node_modules.
+
+Usage: {} {} {}
+
+Options:
+
+  {} {} How many levels of dependencies to list (defaults to 0, the project's own).
+  {} Print the listing as json, with keys in a stable order."#,
             VERSION.bright_green().bold(),
             "volt".bright_green().bold(),
-            "clone".bright_purple(),
-            "[repository]".white(),
+            "list".bright_purple(),
             "[flags]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
+            "--depth".blue(),
+            "<n>".yellow(),
+            "--json".blue(),
         )
     }
 
     /// Execute the `volt list` command
     ///
-    /// List node_modules into node_modules.pack.
+    /// Lists the dependencies in package.json with the versions installed for them, marking
+    /// ones that are missing and packages nothing depends on (extraneous).
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
     /// ```
-    /// // List node_modules into node_modules.pack
+    /// // List the project's direct dependencies
     /// // .exec() is an async call so you need to await it
-    /// Add.exec(app).await;
+    /// List.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        // let flags = &app.flags;
-
-        // let mut depth: u64 = 2;
-
-        // // if flags.contains(&"--depth".to_string()) {
-        // //     depth = app.args.iter().find_map(|s| s.parse().ok()).unwrap_or(2);
-        // // }
-
-        // let dirs = WalkDir::new("node_modules");
-
-        // let dependency_paths: Vec<_> = dirs
-        //     .into_iter()
-        //     .filter_map(Result::ok)
-        //     .filter(|entry| entry.file_type().is_dir() || entry.file_type().is_symlink())
-        //     .collect();
-
-        // if dependency_paths.len() == 1 {
-        //     println!("{}", "No Dependencies Found!".bright_cyan());
-        //     return Ok(());
-        // } else if dependency_paths.is_empty() {
-        //     println!(
-        //         "{} {} {}",
-        //         "Failed to find".bright_cyan(),
-        //         "node_modules".bright_yellow().bold(),
-        //         "folder".bright_cyan(),
-        //     );
-        //     return Ok(());
-        // }
-
-        // for dep in dependency_paths {
-        //     let dep_path = dep.path().to_str().unwrap();
-        //     let dep_path_split: Vec<&str> = dep_path.split('\\').collect();
-        //     let dep_name: &str = dep_path_split[dep_path_split.len() - 1];
-        //     if dep_name != "node_modules"
-        //         && dep_name != "scripts"
-        //         && !dep_name.starts_with("node_modules")
-        //     {
-        //         println!("{} {}", "-".bright_cyan(), dep_name.bright_blue().bold());
-        //         let dirs = WalkDir::new(format!("node_modules/{}/node_modules", dep_name))
-        //             .follow_links(true)
-        //             .max_depth((depth - 1) as usize);
-        //         let dependency_paths: Vec<_> = dirs
-        //             .into_iter()
-        //             .filter_map(Result::ok)
-        //             .filter(|entry| entry.file_type().is_dir() || entry.file_type().is_symlink())
-        //             .collect();
-
-        //         for dep in dependency_paths {
-        //             let dep_path = dep.path().to_str().unwrap();
-        //             let dep_path_split: Vec<&str> = dep_path.split('\\').collect();
-        //             let dep_name: &str = dep_path_split[dep_path_split.len() - 1];
-        //             if dep_name != "node_modules"
-        //                 && dep_name != "scripts"
-        //                 && !dep_path.contains("lib")
-        //                 && !dep_path.contains("src")
-        //                 && !dep_path.contains("dist")
-        //                 && !dep_path.contains("test")
-        //                 && !dep_name.starts_with("node_modules")
-        //             {
-        //                 for _ in 0..dep_path_split.len() {
-        //                     print!("  ");
-        //                 }
-        //                 let mut version = "".to_owned();
-        //                 for file in read_dir(std::env::temp_dir().join("volt")).unwrap() {
-        //                     let file_path: PathBuf = file.unwrap().path();
-        //                     let file_name: &str = file_path.to_str().unwrap();
-        //                     let file_split: Vec<&str> = file_name.split('\\').collect();
-        //                     let name: &str = file_split[file_split.len() - 1];
-        //                     if name.starts_with(dep_name) {
-        //                         let file_split: Vec<&str> = name.split('@').collect();
-        //                         let file_end = file_split[1];
-        //                         let file_split: Vec<&str> = file_end.split(".tgz").collect();
-        //                         version = file_split[0].to_owned();
-        //                     }
-        //                 }
-        //                 let padding = 50 - (dep_path_split.len() * 2);
-        //                 print!(
-        //                     "{} {:<width$}",
-        //                     "-".bright_purple(),
-        //                     dep_name,
-        //                     width = padding
-        //                 );
-        //                 println!("{}", version.clone().truecolor(190, 190, 190));
-        //             }
-        //         }
-        //     }
-        // }
+        let depth = match app.args.value_of("depth") {
+            Some(depth) => depth.parse().map_err(|_| VoltError::InvalidDepth {
+                depth: depth.to_string(),
+            })?,
+            None => 0,
+        };
+
+        let (package_file, _) = PackageJson::open("package.json")?;
+
+        // name -> the section it's in, dev dependencies are listed too
+        let mut requested: BTreeMap<&String, &str> = BTreeMap::new();
+
+        for (section, dependencies) in [
+            ("dependencies", &package_file.dependencies),
+            ("optionalDependencies", &package_file.optional_dependencies),
+            ("devDependencies", &package_file.dev_dependencies),
+        ]
+        .iter()
+        {
+            for name in dependencies.keys() {
+                requested.entry(name).or_insert(*section);
+            }
+        }
+
+        let mut listing = Listing {
+            name: package_file.name.clone(),
+            version: package_file.version.clone(),
+            dependencies: BTreeMap::new(),
+            extraneous: BTreeMap::new(),
+        };
+
+        for (name, section) in requested.iter() {
+            let directory = app.node_modules_dir.join(name);
+            let dev = *section == "devDependencies";
+
+            let listed = match read_manifest(&directory) {
+                Some(manifest) => Listed::installed(
+                    &manifest,
+                    dev,
+                    list_dependencies(&directory, &manifest, 1, depth),
+                ),
+                None => Listed::missing(dev),
+            };
+
+            listing.dependencies.insert(name.to_string(), listed);
+        }
+
+        // hoisted dependencies of dependencies aren't extraneous, only what nothing asks for
+        let top_level = top_level_packages(&app.node_modules_dir);
+
+        let manifests: Vec<(String, Value)> = top_level
+            .iter()
+            .filter_map(|name| {
+                read_manifest(&app.node_modules_dir.join(name))
+                    .map(|manifest| (name.clone(), manifest))
+            })
+            .collect();
+
+        let depended_on: HashSet<&String> = manifests
+            .iter()
+            .flat_map(|(_, manifest)| {
+                INSTALLED_SECTIONS
+                    .iter()
+                    .chain(["peerDependencies"].iter())
+                    .filter_map(move |section| manifest.get(section)?.as_object())
+                    .flat_map(|section| section.keys())
+            })
+            .collect();
+
+        for (name, manifest) in manifests.iter() {
+            if !requested.contains_key(name) && !depended_on.contains(name) {
+                listing.extraneous.insert(
+                    name.clone(),
+                    Listed::installed(manifest, false, BTreeMap::new()),
+                );
+            }
+        }
+
+        if app.has_flag("json") {
+            println!("{}", to_json(&listing)?);
+            return Ok(());
+        }
+
+        println!(
+            "{}@{} {}",
+            listing.name.bright_green(),
+            listing.version,
+            app.current_dir.display().to_string().bright_black()
+        );
+
+        for (name, listed) in listing.dependencies.iter() {
+            print_listed(name, listed, 0, None);
+        }
+
+        for (name, listed) in listing.extraneous.iter() {
+            print_listed(name, listed, 0, Some("(extraneous)"));
+        }
 
         Ok(())
     }
@@ -1,15 +1,20 @@
 pub mod add;
 pub mod audit;
 pub mod check;
+pub mod clean;
 pub mod clone;
 pub mod compress;
+pub mod config;
 pub mod create;
 pub mod deploy;
+pub mod diff;
 pub mod fix;
+pub mod fund;
 pub mod help;
-pub mod info;
+pub mod import;
 pub mod init;
 pub mod install;
+pub mod link;
 pub mod list;
 pub mod login;
 pub mod logout;
@@ -18,11 +23,17 @@ pub mod outdated;
 pub mod owner;
 pub mod publish;
 pub mod remove;
+pub mod resolve;
 pub mod run;
 pub mod search;
 pub mod set;
 pub mod stat;
+pub mod store;
 pub mod tag;
 pub mod team;
+pub mod unlink;
 pub mod update;
+pub mod use_node;
+pub mod view;
 pub mod watch;
+pub mod why_not;
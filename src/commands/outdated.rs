@@ -13,25 +13,362 @@
 
 //! Check for outdated packages.
 
+use crate::commands::add::{install_packages, Package};
+use crate::core::model::http_manager::get_package;
+use crate::core::model::lock_file::LockFile;
+use crate::core::prompt::prompts::MultiSelect;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::npm::VersionRange;
+use crate::core::utils::package::{NpmPackage, PackageJson};
+use crate::{core::VERSION, App, Command};
+
+use std::path::Path;
 use std::sync::Arc;
 
-use crate::App;
-use crate::Command;
 use async_trait::async_trait;
+use colored::Colorize;
 use miette::Result;
+use node_semver::Version;
+
+/// A dependency with a newer version available than the one installed.
+pub struct OutdatedPackage {
+    pub name: String,
+    /// The version in node_modules, if it's installed at all.
+    pub current: Option<String>,
+    /// The highest version satisfying the range in package.json, or the version it's pinned to.
+    pub wanted: String,
+    pub latest: String,
+    pub range: String,
+    /// Whether package.json asks for an exact version rather than a range.
+    pub pinned: bool,
+    pub dev: bool,
+}
+
+/// The version a dependency is pinned to when package.json asks for an exact version
+/// (`1.2.3`, `=1.2.3` or `v1.2.3`) rather than a range.
+pub fn pinned_version(range: &str) -> Option<String> {
+    let version = range.trim().trim_start_matches('=').trim_start_matches('v');
+
+    version
+        .parse::<Version>()
+        .ok()
+        .map(|version| version.to_string())
+}
+
+/// Reads the version of a package installed in node_modules.
+fn installed_version(node_modules_dir: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(node_modules_dir.join(name).join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+        .and_then(|manifest| manifest.get("version")?.as_str().map(String::from))
+}
+
+/// Finds the highest published version satisfying a range.
+fn highest_satisfying(packument: &NpmPackage, range: &str) -> Option<String> {
+    let range: VersionRange = range.parse().ok()?;
+
+    packument
+        .versions
+        .keys()
+        .filter_map(|version| version.parse().ok())
+        .filter(|version: &Version| range.satisfies(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
+/// Rewrites a range for a new version, keeping its `^` or `~` prefix (or lack of one).
+pub fn upgraded_range(range: &str, version: &str) -> String {
+    if range.starts_with('^') || range.starts_with('~') {
+        format!("{}{}", &range[..1], version)
+    } else if range.parse::<Version>().is_ok() {
+        version.to_string()
+    } else {
+        format!("^{}", version)
+    }
+}
+
+/// Looks up a dependency in the registry, returning it if it isn't on its latest version.
+async fn check_outdated(
+    app: &App,
+    name: &str,
+    range: &str,
+    dev: bool,
+) -> Result<Option<OutdatedPackage>> {
+    let lookup_error = || VoltError::VersionLookupError {
+        name: name.to_string(),
+    };
+
+    let registry = app.registry_for(name);
+
+    let packument = get_package(&registry, name, &app.registry_headers(&registry))
+        .await
+        .map_err(|_| lookup_error())?
+        .ok_or_else(lookup_error)?;
+
+    let latest = match packument.dist_tags.get("latest") {
+        Some(latest) => latest.clone(),
+        None => return Ok(None),
+    };
+
+    let current = installed_version(&app.node_modules_dir, name);
+
+    if current.as_ref() == Some(&latest) {
+        return Ok(None);
+    }
+
+    let pinned = pinned_version(range);
+
+    // a pin has no range to move within, only choosing latest moves it
+    let wanted = match &pinned {
+        Some(version) => version.clone(),
+        None => highest_satisfying(&packument, range)
+            .or_else(|| current.clone())
+            .unwrap_or_else(|| latest.clone()),
+    };
+
+    Ok(Some(OutdatedPackage {
+        name: name.to_string(),
+        current,
+        wanted,
+        latest,
+        range: range.to_string(),
+        pinned: pinned.is_some(),
+        dev,
+    }))
+}
+
+/// Looks up every dependency in package.json, returning the ones that aren't on their latest version.
+pub async fn find_outdated(app: &App, package_file: &PackageJson) -> Result<Vec<OutdatedPackage>> {
+    let dependencies = package_file
+        .dependencies
+        .iter()
+        .map(|(name, range)| check_outdated(app, name, range, false));
+
+    let dev_dependencies = package_file
+        .dev_dependencies
+        .iter()
+        .map(|(name, range)| check_outdated(app, name, range, true));
+
+    let mut outdated = futures::future::join_all(dependencies.chain(dev_dependencies))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    outdated.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(outdated)
+}
+
+/// A dependency whose installed version isn't the one volt.lock records.
+struct LockDrift {
+    name: String,
+    /// The version in node_modules, if it's installed at all.
+    current: Option<String>,
+    locked: String,
+    dev: bool,
+}
+
+/// The version volt.lock records for a dependency: the highest locked version satisfying its
+/// range, or the highest locked version when none do.
+fn locked_version(lock_file: &LockFile, name: &str, range: &str) -> Option<String> {
+    let range: Option<VersionRange> = range.parse().ok();
+
+    let mut locked: Vec<Version> = lock_file
+        .dependencies
+        .keys()
+        .filter(|id| id.0 == name)
+        .filter_map(|id| id.1.parse().ok())
+        .collect();
+
+    locked.sort();
+
+    let satisfying = locked
+        .iter()
+        .rev()
+        .find(|version| range.as_ref().is_some_and(|range| range.satisfies(version)));
+
+    satisfying
+        .or_else(|| locked.last())
+        .map(|version| version.to_string())
+}
+
+/// Compares node_modules with volt.lock without the registry, returning the dependencies in
+/// package.json whose installed version isn't the locked one.
+fn find_lock_drift(app: &App, package_file: &PackageJson, lock_file: &LockFile) -> Vec<LockDrift> {
+    let sections = package_file
+        .dependencies
+        .iter()
+        .map(|dependency| (dependency, false))
+        .chain(
+            package_file
+                .dev_dependencies
+                .iter()
+                .map(|dependency| (dependency, true)),
+        );
+
+    let mut drift = vec![];
+
+    for ((name, range), dev) in sections {
+        let locked = match locked_version(lock_file, name, range) {
+            Some(locked) => locked,
+            None => continue,
+        };
+
+        let current = installed_version(&app.node_modules_dir, name);
+
+        if current.as_ref() != Some(&locked) {
+            drift.push(LockDrift {
+                name: name.clone(),
+                current,
+                locked,
+                dev,
+            });
+        }
+    }
+
+    drift.sort_by(|a, b| a.name.cmp(&b.name));
+    drift
+}
+
+/// Prints the dependencies that differ from volt.lock, for `--offline`.
+fn print_lock_drift(app: &App, package_file: &PackageJson) -> Result<()> {
+    if !app.lock_file_path.exists() {
+        return Err(VoltError::LockFileNotFound.into());
+    }
+
+    let lock_file = LockFile::load(&app.lock_file_path).map_err(VoltError::ReadLockFileError)?;
+    let drift = find_lock_drift(app, package_file, &lock_file);
+
+    if drift.is_empty() {
+        println!(
+            "{}: node_modules matches volt.lock",
+            "success".bright_green()
+        );
+    } else {
+        let width = drift
+            .iter()
+            .map(|package| package.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Package".len());
+
+        println!(
+            "{:<width$}  {:<12}  {:<12}  {}",
+            "Package".underline(),
+            "Current".underline(),
+            "Locked".underline(),
+            "Type".underline(),
+            width = width
+        );
+
+        for package in drift.iter() {
+            let kind = if package.dev {
+                "devDependencies"
+            } else {
+                "dependencies"
+            };
+
+            println!(
+                "{:<width$}  {:<12}  {:<12}  {}",
+                package.name.bright_red(),
+                package.current.as_deref().unwrap_or("missing"),
+                package.locked.bright_green(),
+                kind,
+                width = width
+            );
+        }
+    }
+
+    println!(
+        "{}",
+        "latest versions come from the registry, run without --offline to see them".bright_black()
+    );
+
+    Ok(())
+}
+
+/// Prints outdated packages as a table, red when an update within the range is available.
+fn print_table(outdated: &[OutdatedPackage]) {
+    let width = outdated
+        .iter()
+        .map(|package| package.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Package".len());
+
+    println!(
+        "{:<width$}  {:<12}  {:<12}  {:<12}  {}",
+        "Package".underline(),
+        "Current".underline(),
+        "Wanted".underline(),
+        "Latest".underline(),
+        "Type".underline(),
+        width = width
+    );
+
+    for package in outdated {
+        let current = package.current.as_deref().unwrap_or("missing");
+
+        let name = if current != package.wanted {
+            package.name.bright_red()
+        } else {
+            package.name.bright_yellow()
+        };
+
+        let kind = if package.dev {
+            "devDependencies"
+        } else {
+            "dependencies"
+        };
+
+        println!(
+            "{:<width$}  {:<12}  {:<12}  {:<12}  {}{}",
+            name,
+            current,
+            package.wanted.bright_green(),
+            package.latest.bright_magenta(),
+            kind,
+            if package.pinned { " (pinned)" } else { "" },
+            width = width
+        );
+    }
+}
 
 pub struct Outdated {}
+
 #[async_trait]
 impl Command for Outdated {
+    /// Display a help menu for the `volt outdated` command.
     fn help() -> String {
-        todo!()
+        format!(
+            r#"volt {}
+
+Check for outdated packages.
+
+Usage: {} {} {}
+
+Options:
+
+  {} {} Pick packages to upgrade to their latest version from a checklist.
+  {} Compare node_modules with volt.lock only, without the registry."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "outdated".bright_purple(),
+            "[flags]".white(),
+            "--interactive".blue(),
+            "(-i)".yellow(),
+            "--offline".blue()
+        )
     }
 
     /// Execute the `volt outdated` command
     ///
     /// Check for outdated packages
     /// ## Arguments
-    /// * `error` - Instance of the command (`Arc<App>`)
+    /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
     /// ```
     /// // Check for outdated packages
@@ -40,7 +377,231 @@ impl Command for Outdated {
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let (mut package_file, package_file_path) = PackageJson::open("package.json")?;
+
+        if app.has_flag("offline") {
+            return print_lock_drift(&app, &package_file);
+        }
+
+        let outdated = match find_outdated(&app, &package_file).await {
+            Ok(outdated) => outdated,
+            // without the registry, what volt.lock records is still worth showing
+            Err(error) if app.lock_file_path.exists() => {
+                println!(
+                    "{}: {}, showing volt.lock instead",
+                    " warn ".black().bright_yellow(),
+                    error
+                );
+
+                return print_lock_drift(&app, &package_file);
+            }
+            Err(error) => return Err(error),
+        };
+
+        if outdated.is_empty() {
+            println!(
+                "{}: all dependencies are up to date",
+                "success".bright_green()
+            );
+            return Ok(());
+        }
+
+        // the checklist needs a terminal, piped output gets the table
+        if !app.has_flag("interactive") || !console::Term::stdout().is_term() {
+            print_table(&outdated);
+            return Ok(());
+        }
+
+        let items = outdated
+            .iter()
+            .map(|package| {
+                format!(
+                    "{} {} -> {}",
+                    package.name,
+                    package.current.as_deref().unwrap_or("missing"),
+                    package.latest
+                )
+            })
+            .collect();
+
+        let selected = MultiSelect {
+            message: String::from("Choose which packages to upgrade"),
+            paged: true,
+            items,
+        }
+        .run()
+        .map_err(VoltError::PromptError)?;
+
+        if selected.is_empty() {
+            return Ok(());
+        }
+
+        let packages = selected
+            .iter()
+            .map(|&index| Package {
+                name: outdated[index].name.clone(),
+                version: Some(outdated[index].latest.clone()),
+            })
+            .collect();
+
+        install_packages(app, packages, false, false).await?;
+
+        // keep each dependency in its own section, with the prefix it already had
+        for &index in selected.iter() {
+            let package = &outdated[index];
+            let range = upgraded_range(&package.range, &package.latest);
+
+            if package.dev {
+                package_file
+                    .dev_dependencies
+                    .insert(package.name.clone(), range);
+            } else {
+                package_file.add_dependency(package.name.clone(), range);
+            }
+        }
+
+        package_file.save(&package_file_path)?;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::config::VoltConfig;
+    use crate::core::model::lock_file::{DependencyID, DependencyLock};
+
+    fn lock(entries: &[(&str, &str)]) -> LockFile {
+        let mut lock_file = LockFile::new("volt.lock");
+
+        for (name, version) in entries {
+            lock_file.dependencies.insert(
+                DependencyID(name.to_string(), version.to_string()),
+                DependencyLock {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    tarball: String::new(),
+                    integrity: String::new(),
+                    dependencies: vec![],
+                    manifest: None,
+                },
+            );
+        }
+
+        lock_file
+    }
+
+    #[test]
+    fn exact_versions_are_pins() {
+        assert_eq!(pinned_version("1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(pinned_version("=1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(pinned_version("v1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(pinned_version("^1.2.3"), None);
+        assert_eq!(pinned_version("1.x"), None);
+        assert_eq!(pinned_version(">=1.2.3 <2"), None);
+    }
+
+    #[test]
+    fn upgraded_ranges_keep_their_prefix() {
+        assert_eq!(upgraded_range("^1.2.3", "2.0.0"), "^2.0.0");
+        assert_eq!(upgraded_range("~1.2.3", "2.0.0"), "~2.0.0");
+        assert_eq!(upgraded_range("1.2.3", "2.0.0"), "2.0.0");
+        assert_eq!(upgraded_range(">=1.2.3", "2.0.0"), "^2.0.0");
+    }
+
+    #[test]
+    fn wanted_is_the_highest_version_in_range() {
+        let packument: NpmPackage = serde_json::from_value(serde_json::json!({
+            "versions": { "1.1.0": {}, "1.2.0": {}, "1.3.0-beta.0": {}, "2.0.0": {} }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            highest_satisfying(&packument, "^1.0.0").as_deref(),
+            Some("1.2.0")
+        );
+        assert_eq!(highest_satisfying(&packument, "^3.0.0"), None);
+    }
+
+    #[test]
+    fn the_locked_version_is_the_highest_in_range() {
+        let lock_file = lock(&[
+            ("react", "16.14.0"),
+            ("react", "17.0.2"),
+            ("lodash", "4.17.21"),
+        ]);
+
+        assert_eq!(
+            locked_version(&lock_file, "react", "^16.0.0").as_deref(),
+            Some("16.14.0")
+        );
+        assert_eq!(
+            locked_version(&lock_file, "react", "^18.0.0").as_deref(),
+            Some("17.0.2")
+        );
+        assert_eq!(locked_version(&lock_file, "vue", "^3.0.0"), None);
+    }
+
+    #[test]
+    fn installed_versions_are_compared_with_the_lock() {
+        let dir = std::env::temp_dir().join(format!("volt-drift-{}", std::process::id()));
+
+        for (name, version) in &[("react", "17.0.2"), ("lodash", "4.17.20")] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(
+                dir.join(name).join("package.json"),
+                format!(r#"{{ "version": "{}" }}"#, version),
+            )
+            .unwrap();
+        }
+
+        let package_file: PackageJson = serde_json::from_str(
+            r#"{
+                "name": "app",
+                "version": "1.0.0",
+                "dependencies": { "react": "^17.0.0", "lodash": "^4.17.0" },
+                "devDependencies": { "jest": "^27.0.0", "unlocked": "^1.0.0" }
+            }"#,
+        )
+        .unwrap();
+
+        let lock_file = lock(&[
+            ("react", "17.0.2"),
+            ("lodash", "4.17.21"),
+            ("jest", "27.2.0"),
+        ]);
+
+        let mut app = App::for_tests(
+            clap::App::new("outdated").get_matches_from(vec!["outdated"]),
+            VoltConfig::default(),
+        );
+        app.node_modules_dir = dir.clone();
+
+        let mut drift = find_lock_drift(&app, &package_file, &lock_file);
+        drift.sort_by(|a, b| a.name.cmp(&b.name));
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let drift: Vec<(&str, Option<&str>, &str, bool)> = drift
+            .iter()
+            .map(|d| {
+                (
+                    d.name.as_str(),
+                    d.current.as_deref(),
+                    d.locked.as_str(),
+                    d.dev,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            drift,
+            vec![
+                ("jest", None, "27.2.0", true),
+                ("lodash", Some("4.17.20"), "4.17.21", false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Resolve packages without installing them.
+
+use crate::commands::add::{check_resolved_ranges, resolved_versions};
+use crate::core::utils::events::SilentReporter;
+use crate::core::utils::json_output::to_json;
+use crate::core::utils::voltapi::VoltPackage;
+use crate::core::utils::{resolve_dependencies, resolve_tags, DefaultVersion};
+use crate::{core::VERSION, App, Command};
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+use serde::Serialize;
+
+/// Struct implementation for the `Resolve` command.
+pub struct Resolve;
+
+/// What `volt resolve` prints.
+#[derive(Serialize)]
+struct Resolution {
+    /// The version each requested package resolved to, by name.
+    packages: BTreeMap<String, String>,
+    /// Every package in the flattened tree, by `name@version`.
+    dependencies: BTreeMap<String, VoltPackage>,
+}
+
+#[async_trait]
+impl Command for Resolve {
+    /// Display a help menu for the `volt resolve` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Resolve packages and print the flattened dependency tree as json, without installing anything.
+
+Usage: {} {} {} {}
+
+Options:
+
+  {} {} Resolve the version behind a dist-tag (e.g. next, beta).
+  {} {} Resolve for another platform (e.g. linux-x64).
+  {} Leave optionalDependencies out of the tree."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "resolve".bright_purple(),
+            "[packages]".white(),
+            "[flags]".white(),
+            "--tag".blue(),
+            "<tag>".yellow(),
+            "--force-platform".blue(),
+            "<os>-<arch>".yellow(),
+            "--no-optional".blue()
+        )
+    }
+
+    /// Execute the `volt resolve` command
+    ///
+    /// Runs the resolution phase of `volt add` and prints the requested versions
+    /// along with every package in the flattened tree.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Print the tree react would install
+    /// // .exec() is an async call so you need to await it
+    /// Resolve.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let packages = app.get_packages()?;
+
+        let default_tag = app
+            .args
+            .value_of("tag")
+            .map(String::from)
+            .or_else(|| app.config.get("tag").cloned())
+            .unwrap_or_else(|| String::from("latest"));
+
+        let packages = resolve_tags(&app, packages, DefaultVersion::Tag(&default_tag)).await?;
+
+        // stdout is reserved for the json output
+        let (responses, dependencies, _) =
+            resolve_dependencies(&app, &packages, &SilentReporter).await?;
+
+        let versions = resolved_versions(&packages, &responses);
+
+        check_resolved_ranges(&packages, &versions)?;
+
+        let requested: BTreeMap<_, _> = versions.into_iter().collect();
+
+        let resolution = Resolution {
+            packages: requested,
+            dependencies: dependencies.into_iter().collect(),
+        };
+
+        println!("{}", to_json(&resolution)?);
+
+        Ok(())
+    }
+}
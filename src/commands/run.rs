@@ -14,9 +14,14 @@
     limitations under the License.
 */
 
+use std::collections::BTreeMap;
+use std::process::Command as Process;
 use std::sync::Arc;
 
-// use crate::commands::scripts::Script;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::node::{ensure_node, requested_node_version};
+use crate::core::utils::package::PackageJson;
+use crate::core::utils::scripts::script_path;
 use crate::core::VERSION;
 use crate::App;
 use crate::Command;
@@ -35,7 +40,7 @@ impl Command for Run {
         format!(
             r#"volt {}
     
-Run a pre-defined package script
+Run a pre-defined package script, or list them all when no script is given
 
 Usage: {} {} {}
     
@@ -45,7 +50,7 @@ Options:
             VERSION.bright_green().bold(),
             "volt".bright_green().bold(),
             "run".bright_purple(),
-            "file-name".white(),
+            "[script]".white(),
             "--verbose".blue(),
             "(-v)".yellow()
         )
@@ -53,77 +58,242 @@ Options:
 
     /// Execute the `volt run` command
     ///
-    /// Interactively create or update a package.json file for a project.
+    /// Runs a script from package.json, using the project's pinned node version when it has one.
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
-    /// * `packages` - List of packages to add (`Vec<String>`)
-    /// * `flags` - List of flags passed in through the CLI (`Vec<String>`)
     /// ## Examples
     /// ```
     /// // Run a defined script.
     /// // .exec() is an async call so you need to await it
-    /// Run.exec(app, vec![], vec!["--yes"]).await;
+    /// Run.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        // if app.clone().args.len() == 1_usize {
-        //     let package_json = PackageJson::from("package.json");
-
-        //     let args = app.args.clone();
-        //     let command: &str = args[0].as_str();
-
-        //     if package_json.scripts.contains_key(command) {
-        //         Script::exec(app.clone()).await.unwrap();
-        //         std::process::exit(0);
-        //     }
-        // }
-
-        // let path = Path::new("node_modules/scripts");
-
-        // if path.exists() {
-        //     let files = read_dir("node_modules/scripts").unwrap();
-
-        //     let mut files_vec: Vec<String> = vec![];
-
-        //     for f in files {
-        //         let f = f.unwrap();
-        //         let file_name = f.file_name();
-        //         let file_name_str = file_name.to_string_lossy();
-
-        //         files_vec.push(file_name_str.to_string());
-        //     }
-
-        //     if app.clone().args.len() == 1_usize {
-        //         let print_string = files_vec.join(", ");
-        //         println!(
-        //             "{}{} {}",
-        //             "scripts".bright_cyan().bold(),
-        //             ":".bright_magenta().bold(),
-        //             print_string
-        //         );
-        //         std::process::exit(1);
-        //     }
-
-        //     if files_vec.contains(&app.args[1]) {
-        //         let location = format!("node_modules/scripts/{}", &app.args[1]);
-
-        //         let command = format!("scripts/{}", &app.args[1]);
-        //         println!("{} {}", ">".bright_magenta().bold(), command);
-
-        //         std::process::Command::new("cmd.exe")
-        //             .arg("/C")
-        //             .arg(location.replace("/", r"\"))
-        //             .spawn()
-        //             .unwrap();
-        //     } else {
-        //         error!(
-        //             "{} 'is not a valid script.'",
-        //             &app.args[1].bright_yellow().bold(),
-        //         );
-        //     }
-        // }
+        let (package_file, _) = PackageJson::open("package.json")?;
+
+        // like `npm run`, no script name lists what there is to run
+        let name = match app.args.value_of("script") {
+            Some(name) => name,
+            None => {
+                list_scripts(&package_file);
+                return Ok(());
+            }
+        };
+
+        let mut script = package_file
+            .scripts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| script_not_found(&package_file, name))?;
+
+        // volt run test -- --watch
+        if let Some(args) = app.args.values_of("script-args") {
+            for arg in args {
+                script.push(' ');
+
+                if cfg!(windows) {
+                    script.push_str(&quote_cmd(arg));
+                } else {
+                    script.push_str(&quote_sh(arg));
+                }
+            }
+        }
+
+        // cmd.exe reads its command line as it is, so the script is passed raw rather than
+        // quoted again the way `arg` would; /S strips just the outer quotes
+        #[cfg(windows)]
+        let mut command = {
+            use std::os::windows::process::CommandExt;
+
+            let mut command = Process::new("cmd.exe");
+            command.raw_arg(format!("/S /C \"{}\"", script));
+            command
+        };
+
+        #[cfg(not(windows))]
+        let mut command = {
+            let mut command = Process::new("sh");
+            command.arg("-c").arg(&script);
+            command
+        };
+
+        // run against the project's pinned node version, if it has one
+        let mut extra = vec![];
+
+        if let Some(requested) = requested_node_version(&app.current_dir) {
+            let (_, bin_dir) = ensure_node(&app.volt_dir, &requested).await?;
+            extra.push(bin_dir);
+        }
+
+        // bins installed in node_modules/.bin here and in every parent directory
+        if let Some(path) = script_path(&app.current_dir, &extra) {
+            command.env("PATH", path);
+        }
+
+        println!("{} {}", ">".bright_magenta().bold(), script);
+
+        let status = command
+            .current_dir(&app.current_dir)
+            .status()
+            .map_err(|_| VoltError::LifecycleScriptError {
+                package: package_file.name.clone(),
+                script: name.to_string(),
+                code: String::from("spawn"),
+            })?;
+
+        if !status.success() {
+            return Err(VoltError::LifecycleScriptError {
+                package: package_file.name,
+                script: name.to_string(),
+                code: status
+                    .code()
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "signal".to_string()),
+            }
+            .into());
+        }
 
         Ok(())
     }
 }
+
+/// Characters an argument can be passed to a shell with unquoted.
+fn is_plain(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+".contains(c))
+}
+
+/// Quotes an argument for `sh -c`, as one word with nothing in it expanded.
+fn quote_sh(arg: &str) -> String {
+    if is_plain(arg) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Quotes an argument for `cmd /C`, as one word the program's argv parser reads back as it is.
+fn quote_cmd(arg: &str) -> String {
+    if is_plain(arg) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // backslashes before a quote escape each other, then the quote itself
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+
+    // the closing quote mustn't be escaped by trailing backslashes
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    quoted
+}
+
+/// Print every script in package.json with the command it runs.
+fn list_scripts(package_file: &PackageJson) {
+    if package_file.scripts.is_empty() {
+        println!("{} has no scripts", package_file.name.bright_cyan());
+        return;
+    }
+
+    let scripts: BTreeMap<&String, &String> = package_file.scripts.iter().collect();
+
+    println!("Scripts available in {}:", package_file.name.bright_cyan());
+
+    for (name, command) in scripts {
+        println!("  {}", name.bright_green());
+        println!("    {}", command.bright_black());
+    }
+}
+
+/// A missing script error, suggesting the script the user most likely meant.
+fn script_not_found(package_file: &PackageJson, name: &str) -> VoltError {
+    let hint = match closest_script(package_file, name) {
+        Some(closest) => format!("did you mean `{}`?", closest),
+        None => String::from("run `volt run` to list the scripts in package.json"),
+    };
+
+    VoltError::ScriptNotFound {
+        name: name.to_string(),
+        hint,
+    }
+}
+
+/// The script whose name is closest to `name`, if any is close enough to be a typo.
+fn closest_script<'a>(package_file: &'a PackageJson, name: &str) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(2);
+
+    package_file
+        .scripts
+        .keys()
+        .map(|script| (edit_distance(name, script), script))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min()
+        .map(|(_, script)| script.as_str())
+}
+
+/// Levenshtein distance between two strings, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_arguments_are_left_alone() {
+        assert_eq!(quote_sh("--watch"), "--watch");
+        assert_eq!(quote_cmd("--port=3000"), "--port=3000");
+    }
+
+    #[test]
+    fn sh_arguments_are_single_quoted() {
+        assert_eq!(quote_sh("a b"), "'a b'");
+        assert_eq!(quote_sh("$HOME; rm -rf /"), "'$HOME; rm -rf /'");
+        assert_eq!(quote_sh("it's"), "'it'\\''s'");
+        assert_eq!(quote_sh(""), "''");
+    }
+
+    #[test]
+    fn cmd_arguments_are_double_quoted() {
+        assert_eq!(quote_cmd("a b"), "\"a b\"");
+        assert_eq!(quote_cmd("a & b"), "\"a & b\"");
+        assert_eq!(quote_cmd("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_cmd("C:\\dir\\"), "\"C:\\dir\\\\\"");
+        assert_eq!(quote_cmd(""), "\"\"");
+    }
+}
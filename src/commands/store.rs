@@ -0,0 +1,168 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Manage the packages in the volt store.
+
+use crate::core::utils::add_tarball_to_store;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::install_lock::InstallLock;
+use crate::core::utils::store_index::{check_entry, store_entries, EntryState};
+use crate::{core::VERSION, App, Command};
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use clap::ArgMatches;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Store` command.
+pub struct Store;
+
+/// Adds local tarballs to the store.
+fn add(app: &App, args: &ArgMatches) -> Result<()> {
+    let expected = args.value_of("integrity");
+
+    for path in args.values_of("tarballs").unwrap() {
+        let data = std::fs::read(path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: path.to_string(),
+        })?;
+
+        let stored = add_tarball_to_store(app, &data, path, expected)?;
+
+        let status = if stored.added {
+            "added".bright_green()
+        } else {
+            "already stored".bright_black()
+        };
+
+        println!(
+            "{} {}@{} {}",
+            status,
+            stored.name.bright_cyan(),
+            stored.version,
+            stored.integrity.to_string().bright_black()
+        );
+    }
+
+    Ok(())
+}
+
+/// Hashes every entry of the store again, removing the ones whose files changed since they
+/// were extracted so the next install extracts them afresh.
+fn verify(app: &App) -> Result<()> {
+    let mut intact = 0;
+    let mut unindexed = 0;
+    let mut removed = 0;
+
+    for entry in store_entries(&app.volt_dir) {
+        match check_entry(&entry) {
+            EntryState::Intact => intact += 1,
+            EntryState::Unindexed => unindexed += 1,
+            EntryState::Corrupt(file) => {
+                std::fs::remove_dir_all(&entry).map_err(|e| VoltError::RemoveDirError {
+                    source: e,
+                    name: entry.to_string_lossy().to_string(),
+                })?;
+
+                println!(
+                    "{} {} {}",
+                    "removed".bright_red(),
+                    entry
+                        .strip_prefix(&app.volt_dir)
+                        .unwrap_or(&entry)
+                        .display(),
+                    format!("({} changed)", file).bright_black()
+                );
+
+                removed += 1;
+            }
+        }
+    }
+
+    println!(
+        "{}: {} intact, {} removed, {} extracted before volt indexed the store",
+        "success".bright_green(),
+        intact,
+        removed,
+        unindexed
+    );
+
+    Ok(())
+}
+
+#[async_trait]
+impl Command for Store {
+    /// Display a help menu for the `volt store` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Manage the packages in the volt store.
+
+Usage: {} {} {}
+
+Commands:
+  add <tarballs...> - Add local package tarballs to the store, for installs without the registry.
+  verify            - Check the store for changed files and remove the entries they're in.
+
+Options:
+
+  {} {} Refuse a tarball that doesn't match this integrity."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "store".bright_purple(),
+            "[command]".bright_purple(),
+            "--integrity".blue(),
+            "<sri>".yellow(),
+        )
+    }
+
+    /// Execute the `volt store` command
+    ///
+    /// Adds local tarballs to the store, so installs on a machine without access to the
+    /// registry's tarballs use them instead of downloading, or checks the store for entries
+    /// that changed since they were extracted.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Add tarballs copied from another machine to the store
+    /// // .exec() is an async call so you need to await it
+    /// Store.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        // an install writing to the store at the same time would race on the same entries
+        let _store_lock = match app.args.subcommand_name() {
+            Some("add") | Some("verify") => {
+                Some(InstallLock::acquire(&app.volt_dir.join(".lock"))?)
+            }
+            _ => None,
+        };
+
+        match app.args.subcommand() {
+            Some(("add", args)) => add(&app, args),
+            Some(("verify", _)) => verify(&app),
+            _ => {
+                println!("{}", Self::help());
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Remove symlinks created by `volt link`.
+
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::linker::{is_symlink, links_dir, remove_symlink_dir};
+use crate::core::utils::package::PackageJson;
+use crate::{core::VERSION, App, Command};
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Unlink` command.
+pub struct Unlink;
+
+/// Removes a symlink created by `volt link`, returning whether there was one.
+fn unlink(link: &Path) -> Result<bool> {
+    if !is_symlink(link) {
+        return Ok(false);
+    }
+
+    remove_symlink_dir(link).map_err(|e| VoltError::RemoveDirError {
+        source: e,
+        name: link.to_string_lossy().to_string(),
+    })?;
+
+    Ok(true)
+}
+
+#[async_trait]
+impl Command for Unlink {
+    /// Display a help menu for the `volt unlink` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Remove symlinks created by volt link.
+
+Usage: {} {} {}
+
+Run without packages inside a package to remove it from the global link store,
+or with packages inside a project to remove their symlinks from node_modules."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "unlink".bright_purple(),
+            "[packages]".white(),
+        )
+    }
+
+    /// Execute the `volt unlink` command
+    ///
+    /// Removes the current package from `~/.volt/.links`, or linked packages from `node_modules`.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Unlink the package in the current directory
+    /// // .exec() is an async call so you need to await it
+    /// Unlink.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let links = match app.args.values_of("package-names") {
+            Some(packages) => packages
+                .map(|package| (package.to_string(), app.node_modules_dir.join(package)))
+                .collect::<Vec<_>>(),
+            None => {
+                let (package_file, _) = PackageJson::open("package.json")?;
+                let link = links_dir(&app.volt_dir).join(&package_file.name);
+
+                vec![(package_file.name, link)]
+            }
+        };
+
+        for (package, link) in links {
+            if unlink(&link)? {
+                println!(
+                    "{}: unlinked {}",
+                    "success".bright_green(),
+                    package.bright_cyan()
+                );
+            } else {
+                println!("{} is not linked", package.bright_cyan());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::utils::linker::{symlink_dir, WindowsLinkType};
+    use std::fs;
+
+    #[test]
+    fn only_links_are_removed() {
+        let dir = std::env::temp_dir().join(format!("volt-unlink-{}", std::process::id()));
+        let (project, link) = (dir.join("project"), dir.join("node_modules/lib"));
+
+        fs::create_dir_all(&project).unwrap();
+
+        assert!(!unlink(&link).unwrap());
+        assert!(!unlink(&project).unwrap());
+        assert!(project.exists());
+
+        symlink_dir(&project, &link, WindowsLinkType::Auto).unwrap();
+
+        assert!(unlink(&link).unwrap());
+        assert!(!is_symlink(&link));
+        assert!(project.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -14,6 +14,11 @@
     limitations under the License.
 */
 
+//! Update project dependencies.
+
+use crate::commands::add::{install_packages, Package};
+use crate::commands::outdated::{find_outdated, upgraded_range};
+use crate::core::utils::package::PackageJson;
 use crate::{core::VERSION, App, Command};
 
 use async_trait::async_trait;
@@ -30,18 +35,21 @@ impl Command for Update {
     fn help() -> String {
         format!(
             r#"volt {}
-    
-Update project dependencies
+
+Update project dependencies to the highest version their range in package.json allows.
+Dependencies pinned to an exact version are left alone unless --latest is passed.
 
 Usage: {} {} {}
-    
+
 Options:
-    
+
+  {} Update to the latest version, rewriting package.json (pins included).
   {} {} Output verbose messages on internal operations."#,
             VERSION.bright_green().bold(),
             "volt".bright_green().bold(),
             "update".bright_purple(),
-            "file-name".white(),
+            "[flags]".white(),
+            "--latest".blue(),
             "--verbose".blue(),
             "(-v)".yellow()
         )
@@ -52,17 +60,73 @@ Options:
     /// update project dependencies
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
-    /// * `packages` - List of packages to add (`Vec<String>`)
-    /// * `flags` - List of flags passed in through the CLI (`Vec<String>`)
     /// ## Examples
     /// ```
     /// // update project dependencies
     /// // .exec() is an async call so you need to await it
-    /// update.exec(app, vec![], vec!["--yes"]).await;
+    /// Update.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let (mut package_file, package_file_path) = PackageJson::open("package.json")?;
+
+        let latest = app.has_flag("latest");
+
+        // without --latest, pins want the version they're pinned to and ranges stay put
+        let updates: Vec<_> = find_outdated(&app, &package_file)
+            .await?
+            .into_iter()
+            .filter(|package| {
+                let target = if latest {
+                    &package.latest
+                } else {
+                    &package.wanted
+                };
+
+                package.current.as_ref() != Some(target)
+            })
+            .collect();
+
+        if updates.is_empty() {
+            println!(
+                "{}: all dependencies are up to date",
+                "success".bright_green()
+            );
+            return Ok(());
+        }
+
+        let packages = updates
+            .iter()
+            .map(|package| Package {
+                name: package.name.clone(),
+                version: Some(if latest {
+                    package.latest.clone()
+                } else {
+                    package.wanted.clone()
+                }),
+            })
+            .collect();
+
+        install_packages(app, packages, false, false).await?;
+
+        // the wanted versions still satisfy package.json, only --latest moves the ranges
+        if latest {
+            for package in updates.iter() {
+                let range = upgraded_range(&package.range, &package.latest);
+
+                if package.dev {
+                    package_file
+                        .dev_dependencies
+                        .insert(package.name.clone(), range);
+                } else {
+                    package_file.add_dependency(package.name.clone(), range);
+                }
+            }
+
+            package_file.save(&package_file_path)?;
+        }
+
         Ok(())
     }
 }
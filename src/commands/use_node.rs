@@ -0,0 +1,95 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Pin and download the node version a project runs on.
+
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::node::{ensure_node, requested_node_version};
+use crate::{core::VERSION, App, Command};
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+
+/// Struct implementation for the `Use` command.
+pub struct Use;
+
+#[async_trait]
+impl Command for Use {
+    /// Display a help menu for the `volt use` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Download a node version and pin the project to it.
+
+Usage: {} {} {}
+
+Without a version, the one in .nvmrc (or engines.node in package.json) is downloaded.
+Versions can be ranges (16, ^14.17.0), node, lts/* or lts/<codename>."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "use".bright_purple(),
+            "[version]".white(),
+        )
+    }
+
+    /// Execute the `volt use` command
+    ///
+    /// Downloads the requested node runtime into `~/.volt/.node` and writes it to `.nvmrc`.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Pin the project to the latest node 16
+    /// // .exec() is an async call so you need to await it
+    /// Use.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let requested = app.args.value_of("node-version").map(String::from);
+        let pin = requested.is_some();
+
+        let requested = requested
+            .or_else(|| requested_node_version(&app.current_dir))
+            .ok_or(VoltError::NodeVersionNotPinned)?;
+
+        let (version, bin_dir) = ensure_node(&app.volt_dir, &requested).await?;
+
+        if pin {
+            let nvmrc = app.current_dir.join(".nvmrc");
+
+            std::fs::write(&nvmrc, format!("{}\n", version)).map_err(|e| {
+                VoltError::WriteFileError {
+                    source: e,
+                    name: nvmrc.to_string_lossy().to_string(),
+                }
+            })?;
+        }
+
+        println!(
+            "{}: using node {} ({})",
+            "success".bright_green(),
+            version.bright_cyan(),
+            bin_dir.display()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Display info about a package.
+
+use crate::core::model::http_manager::get_package_full;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::npm::{parse_versions, VersionRange};
+use crate::core::utils::package::{NpmPackage, Version};
+use crate::{core::VERSION, App, Command};
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+use node_semver::Version as SemVer;
+use serde_json::Value;
+
+/// Struct implementation for the `View` command.
+pub struct View;
+
+/// Picks the version a spec asks for: an exact version, a dist-tag or the highest match of a range.
+pub fn select_version<'a>(
+    packument: &'a NpmPackage,
+    requested: Option<&str>,
+) -> Option<&'a Version> {
+    let requested = requested.unwrap_or("latest");
+
+    if let Some(version) = packument
+        .versions
+        .get(requested)
+        .or_else(|| packument.versions.get(packument.dist_tags.get(requested)?))
+    {
+        return Some(version);
+    }
+
+    let range: VersionRange = requested.parse().ok()?;
+
+    packument
+        .versions
+        .iter()
+        .filter_map(|(key, version)| key.parse().ok().map(|semver: SemVer| (semver, version)))
+        .filter(|(semver, _)| range.satisfies(semver))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+/// Looks up a dotted field (`dist.tarball`) in a version, falling back to the packument for
+/// fields only it has (`dist-tags`, `license`, `time`).
+fn select_field(packument: &Value, version: &Value, field: &str) -> Option<Value> {
+    let lookup = |root: &Value| {
+        field
+            .split('.')
+            .try_fold(root, |value, key| value.get(key))
+            .filter(|value| !is_empty(value))
+            .cloned()
+    };
+
+    lookup(version).or_else(|| lookup(packument))
+}
+
+/// Fields a registry left out are deserialized to empty defaults, treat those as missing.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(string) => string.is_empty(),
+        Value::Array(array) => array.is_empty(),
+        Value::Object(object) => object.is_empty(),
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl Command for View {
+    /// Display a help menu for the `volt view` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Show registry information about a package.
+
+Usage: {} {} {} {}
+
+Versions can be exact, a range or a dist-tag (react@next), the latest version is shown otherwise.
+Pass a field (description, dependencies, dist.integrity) to print only that field.
+
+Options:
+
+  {} {} Fetch the package from this registry."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "view".bright_purple(),
+            "<package>[@version]".white(),
+            "[field]".white(),
+            "--registry".blue(),
+            "<url>".yellow()
+        )
+    }
+
+    /// Execute the `volt view` command
+    ///
+    /// Display info about a package
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Display info about a package
+    /// // .exec() is an async call so you need to await it
+    /// View.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let spec = app.args.value_of("package").unwrap().to_string();
+
+        let package = parse_versions(&vec![spec.clone()])?
+            .pop()
+            .ok_or(VoltError::VersionLookupError { name: spec })?;
+
+        let registry = app.registry_for(&package.name);
+
+        let lookup_error = || VoltError::PackageNotFound {
+            url: format!("{}/{}", registry, package.name),
+            package_name: package.name.clone(),
+        };
+
+        let headers = app.registry_headers(&registry);
+
+        // the full document, the abbreviated one has no description or maintainers
+        let packument = get_package_full(&registry, &package.name, &headers)
+            .await
+            .map_err(|_| lookup_error())?
+            .ok_or_else(lookup_error)?;
+
+        let version = select_version(&packument, package.version.as_deref()).ok_or_else(|| {
+            VoltError::VersionLookupError {
+                name: package.name.clone(),
+            }
+        })?;
+
+        if let Some(field) = app.args.value_of("field") {
+            let packument_value =
+                serde_json::to_value(&packument).map_err(|_| VoltError::DeserializeError)?;
+            let version_value =
+                serde_json::to_value(version).map_err(|_| VoltError::DeserializeError)?;
+
+            // like npm, a missing field prints nothing
+            match select_field(&packument_value, &version_value, field) {
+                Some(Value::String(value)) => println!("{}", value),
+                Some(value) => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value)
+                        .map_err(|_| VoltError::DeserializeError)?
+                ),
+                None => {}
+            }
+
+            return Ok(());
+        }
+
+        println!(
+            "{}@{} | {} | deps: {} | versions: {}",
+            packument.name.bright_cyan().bold(),
+            version.version.bright_green(),
+            packument
+                .license
+                .as_deref()
+                .unwrap_or("no license")
+                .bright_yellow(),
+            version.dependencies.len(),
+            packument.versions.len()
+        );
+
+        let description = if version.description.is_empty() {
+            packument.description.clone().unwrap_or_default()
+        } else {
+            version.description.clone()
+        };
+
+        if !description.is_empty() {
+            println!("{}", description);
+        }
+
+        let homepage = if version.homepage.is_empty() {
+            packument.homepage.clone().unwrap_or_default()
+        } else {
+            version.homepage.clone()
+        };
+
+        if !homepage.is_empty() {
+            println!("{}", homepage.bright_blue().underline());
+        }
+
+        let mut dist_tags: Vec<_> = packument.dist_tags.iter().collect();
+        dist_tags.sort();
+
+        println!("\n{}:", "dist-tags".bright_cyan());
+
+        for (tag, tagged) in dist_tags {
+            println!("  {}: {}", tag, tagged.bright_green());
+        }
+
+        let mut dependencies: Vec<_> = version.dependencies.iter().collect();
+        dependencies.sort();
+
+        if !dependencies.is_empty() {
+            println!("\n{}:", "dependencies".bright_cyan());
+
+            for (name, range) in dependencies {
+                println!(
+                    "  {} {} {}",
+                    "-".bright_magenta(),
+                    name,
+                    range.bright_black()
+                );
+            }
+        }
+
+        let maintainers = if version.maintainers.is_empty() {
+            &packument.maintainers
+        } else {
+            &version.maintainers
+        };
+
+        if !maintainers.is_empty() {
+            println!("\n{}:", "maintainers".bright_cyan());
+
+            for maintainer in maintainers {
+                println!(
+                    "  {} {} <{}>",
+                    "-".bright_magenta(),
+                    maintainer.name.yellow().bold(),
+                    maintainer.email
+                );
+            }
+        }
+
+        println!("\n{}:", "dist".bright_cyan());
+        println!(
+            "  tarball: {}",
+            version.dist.tarball.bright_blue().underline()
+        );
+
+        if !version.dist.integrity.is_empty() {
+            println!("  integrity: {}", version.dist.integrity.bright_green());
+        }
+
+        if !version.dist.shasum.is_empty() {
+            println!("  shasum: {}", version.dist.shasum.bright_green());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn packument() -> Value {
+        json!({
+            "name": "react",
+            "license": "MIT",
+            "dist-tags": { "latest": "17.0.2", "next": "18.0.0-rc.0" },
+            "versions": {
+                "16.14.0": { "version": "16.14.0" },
+                "17.0.2": {
+                    "version": "17.0.2",
+                    "description": "React is a JavaScript library for building user interfaces.",
+                    "dependencies": { "loose-envify": "^1.1.0" },
+                    "dist": { "integrity": "sha512-abc", "tarball": "https://registry.npmjs.org/react/-/react-17.0.2.tgz" }
+                },
+                "18.0.0-rc.0": { "version": "18.0.0-rc.0" }
+            }
+        })
+    }
+
+    fn selected(requested: Option<&str>) -> Option<String> {
+        let packument: NpmPackage = serde_json::from_value(packument()).unwrap();
+
+        select_version(&packument, requested).map(|version| version.version.clone())
+    }
+
+    #[test]
+    fn versions_are_selected_by_version_tag_or_range() {
+        assert_eq!(selected(None).as_deref(), Some("17.0.2"));
+        assert_eq!(selected(Some("16.14.0")).as_deref(), Some("16.14.0"));
+        assert_eq!(selected(Some("next")).as_deref(), Some("18.0.0-rc.0"));
+        assert_eq!(
+            selected(Some("^16.0.0 || ^17.0.0")).as_deref(),
+            Some("17.0.2")
+        );
+        assert_eq!(selected(Some("^19.0.0")), None);
+    }
+
+    #[test]
+    fn fields_are_looked_up_in_the_version_then_the_packument() {
+        let packument = packument();
+        let version = &packument["versions"]["17.0.2"];
+
+        assert_eq!(
+            select_field(&packument, version, "dist.integrity"),
+            Some(json!("sha512-abc"))
+        );
+        assert_eq!(
+            select_field(&packument, version, "dependencies"),
+            Some(json!({ "loose-envify": "^1.1.0" }))
+        );
+        assert_eq!(
+            select_field(&packument, version, "license"),
+            Some(json!("MIT"))
+        );
+        assert_eq!(select_field(&packument, version, "homepage"), None);
+    }
+}
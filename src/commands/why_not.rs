@@ -0,0 +1,316 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Explain why a version of a package was or wasn't installed.
+
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::json_output::to_json;
+use crate::core::utils::npm::VersionRange;
+use crate::core::utils::voltapi::dependency_name;
+use crate::{core::VERSION, App, Command};
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use colored::Colorize;
+use miette::Result;
+use node_semver::Version;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Struct implementation for the `WhyNot` command.
+pub struct WhyNot;
+
+/// Sections of the project's package.json whose ranges constrain which version gets installed.
+const PROJECT_SECTIONS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+/// The same for installed packages, whose devDependencies are never installed.
+const DEPENDENT_SECTIONS: [&str; 3] = ["dependencies", "optionalDependencies", "peerDependencies"];
+
+/// A range some package (or the project itself) asks for.
+#[derive(Serialize, Debug)]
+pub struct Constraint {
+    /// `name@version` of the dependent, or `package.json` for the project.
+    pub dependent: String,
+    pub range: String,
+    pub section: String,
+    /// Whether the range allows the version asked about, `None` if it isn't a semver range.
+    pub allows: Option<bool>,
+}
+
+/// Why a version of a package was or wasn't installed.
+#[derive(Serialize, Debug)]
+pub struct Explanation {
+    pub name: String,
+    pub version: String,
+    /// Whether that version is installed anywhere in node_modules.
+    pub chosen: bool,
+    /// The versions that are installed instead, with where they are.
+    pub installed: Vec<(String, PathBuf)>,
+    pub constraints: Vec<Constraint>,
+}
+
+/// Reads the manifest of every package in `node_modules` and the node_modules nested inside them.
+pub fn installed_manifests(node_modules: &Path) -> Vec<(PathBuf, Value)> {
+    let mut manifests = vec![];
+
+    let entries = match read_dir(node_modules) {
+        Ok(entries) => entries,
+        Err(_) => return manifests,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // @types/node lives in node_modules/@types/node
+        let packages = if name.starts_with('@') {
+            read_dir(&path)
+                .map(|scoped| scoped.flatten().map(|e| e.path()).collect())
+                .unwrap_or_else(|_| vec![])
+        } else if name.starts_with('.') {
+            vec![]
+        } else {
+            vec![path]
+        };
+
+        for package in packages {
+            if let Some(manifest) = read_to_string(package.join("package.json"))
+                .ok()
+                .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+            {
+                manifests.extend(installed_manifests(&package.join("node_modules")));
+                manifests.push((package, manifest));
+            }
+        }
+    }
+
+    manifests
+}
+
+/// Collects the ranges `manifest` puts on `name`, attributed to `dependent`.
+fn constraints_from(
+    dependent: &str,
+    manifest: &Value,
+    name: &str,
+    version: &Version,
+    sections: &[&str],
+) -> Vec<Constraint> {
+    sections
+        .iter()
+        .filter_map(|section| {
+            let range = manifest.get(section)?.get(name)?.as_str()?;
+
+            Some(Constraint {
+                dependent: dependent.to_string(),
+                range: range.to_string(),
+                section: section.to_string(),
+                allows: range
+                    .parse::<VersionRange>()
+                    .ok()
+                    .map(|range| range.satisfies(version)),
+            })
+        })
+        .collect()
+}
+
+/// Explains why `name@version` is or isn't in node_modules, from the installed manifests.
+pub fn explain(app: &App, name: &str, version: &str) -> Result<Explanation> {
+    let parsed: Version = version.parse().map_err(|_| VoltError::InvalidVersion {
+        version: version.to_string(),
+    })?;
+
+    let root = read_to_string(app.current_dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+        .unwrap_or(Value::Null);
+
+    let mut constraints = constraints_from("package.json", &root, name, &parsed, &PROJECT_SECTIONS);
+    let mut installed = vec![];
+
+    for (directory, manifest) in installed_manifests(&app.node_modules_dir) {
+        let package_name = manifest["name"].as_str().unwrap_or_default();
+        let package_version = manifest["version"].as_str().unwrap_or_default();
+
+        if package_name == name {
+            installed.push((package_version.to_string(), directory));
+            continue;
+        }
+
+        constraints.extend(constraints_from(
+            &format!("{}@{}", package_name, package_version),
+            &manifest,
+            name,
+            &parsed,
+            &DEPENDENT_SECTIONS,
+        ));
+    }
+
+    installed.sort();
+    constraints.sort_by(|a, b| a.dependent.cmp(&b.dependent));
+
+    Ok(Explanation {
+        name: name.to_string(),
+        version: version.to_string(),
+        chosen: installed.iter().any(|(installed, _)| installed == version),
+        installed,
+        constraints,
+    })
+}
+
+#[async_trait]
+impl Command for WhyNot {
+    /// Display a help menu for the `volt why-not` command.
+    fn help() -> String {
+        format!(
+            r#"volt {}
+
+Explain why a version of a package was or wasn't installed.
+
+Usage: {} {} {} {}
+
+Options:
+
+  {} Output the explanation as json."#,
+            VERSION.bright_green().bold(),
+            "volt".bright_green().bold(),
+            "why-not".bright_purple(),
+            "<package>@<version>".white(),
+            "[flags]".white(),
+            "--json".blue(),
+        )
+    }
+
+    /// Execute the `volt why-not` command
+    ///
+    /// Lists the ranges dependents put on a package and which of them rule out the version asked about.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```
+    /// // Explain why react@16.14.0 wasn't installed
+    /// // .exec() is an async call so you need to await it
+    /// WhyNot.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let package = app.args.value_of("package").unwrap();
+        let name = dependency_name(package);
+
+        // react@16.14.0 -> 16.14.0
+        let version = package[name.len()..].trim_start_matches('@');
+
+        if version.is_empty() {
+            return Err(VoltError::InvalidVersion {
+                version: package.to_string(),
+            }
+            .into());
+        }
+
+        let explanation = explain(&app, name, version)?;
+
+        if app.has_flag("json") {
+            println!("{}", to_json(&explanation)?);
+
+            return Ok(());
+        }
+
+        let requested = format!("{}@{}", name, version);
+
+        if explanation.chosen {
+            println!("{} is installed", requested.bright_cyan());
+        } else if explanation.installed.is_empty() {
+            println!(
+                "{} was not chosen, no version of {} is installed",
+                requested.bright_cyan(),
+                name
+            );
+        } else {
+            let installed = explanation
+                .installed
+                .iter()
+                .map(|(version, _)| format!("{}@{}", name, version))
+                .collect::<Vec<String>>();
+
+            println!(
+                "{} was not chosen, node_modules has {}",
+                requested.bright_cyan(),
+                installed.join(", ").bright_green()
+            );
+        }
+
+        let ruled_out: Vec<&Constraint> = explanation
+            .constraints
+            .iter()
+            .filter(|constraint| constraint.allows == Some(false))
+            .collect();
+
+        if !ruled_out.is_empty() {
+            println!("\n{}", "ruled out by:".bright_red().bold());
+
+            for constraint in ruled_out {
+                println!(
+                    "  {} requires {} {} ({})",
+                    constraint.dependent.bright_cyan(),
+                    name,
+                    constraint.range.bright_yellow(),
+                    constraint.section
+                );
+            }
+        }
+
+        let allowed: Vec<&Constraint> = explanation
+            .constraints
+            .iter()
+            .filter(|constraint| constraint.allows != Some(false))
+            .collect();
+
+        if !allowed.is_empty() {
+            println!("\n{}", "allowed by:".bright_green().bold());
+
+            for constraint in allowed {
+                println!(
+                    "  {} requires {} {} ({})",
+                    constraint.dependent.bright_cyan(),
+                    name,
+                    constraint.range,
+                    constraint.section
+                );
+            }
+        }
+
+        if !explanation.chosen
+            && explanation
+                .constraints
+                .iter()
+                .all(|c| c.allows != Some(false))
+        {
+            println!(
+                "\nnothing rules it out, volt installs the highest version matching each range"
+            );
+        }
+
+        Ok(())
+    }
+}
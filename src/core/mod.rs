@@ -20,6 +20,7 @@ pub mod classes;
 pub mod command;
 pub mod io;
 pub mod model;
+pub mod net;
 pub mod prompt;
 pub mod utils;
 
@@ -14,5 +14,7 @@
     limitations under the License.
 */
 
+pub mod config;
 pub mod http_manager;
 pub mod lock_file;
+pub mod npmrc;
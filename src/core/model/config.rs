@@ -0,0 +1,362 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use miette::Result;
+
+use crate::core::model::npmrc;
+use crate::core::utils::errors::VoltError;
+
+/// Name of the configuration file read from the project and home directories.
+pub const CONFIG_FILE_NAME: &str = ".voltrc";
+
+/// User configuration for volt, stored as flat TOML in `.voltrc` files.
+///
+/// Settings are read from the home directory first and then from the project
+/// directory, so a key set in the project's `.voltrc` overrides the same key in
+/// `~/.voltrc`.
+///
+/// Keys not set in either `.voltrc` fall back to npm's `.npmrc` files (project, then
+/// `~/.npmrc`, then the global `npmrc`), which volt reads but never writes.
+///
+/// ## Examples
+///
+/// ```
+/// let mut config = VoltConfig::load(&current_dir, &home_dir)?;
+///
+/// config.set("registry", "https://registry.npmjs.org", false);
+/// config.save(false)?;
+///
+/// assert_eq!(config.get("registry").unwrap(), "https://registry.npmjs.org");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct VoltConfig {
+    pub project_path: PathBuf,
+    pub home_path: PathBuf,
+    pub project: BTreeMap<String, String>,
+    pub home: BTreeMap<String, String>,
+    /// The merged `.npmrc` settings.
+    pub npmrc: BTreeMap<String, String>,
+}
+
+impl VoltConfig {
+    /// Loads and merges the project and home `.voltrc` files, and the `.npmrc` files under them.
+    pub fn load<P: AsRef<Path>>(current_dir: P, home_dir: P) -> Result<Self> {
+        let project_path = current_dir.as_ref().join(CONFIG_FILE_NAME);
+        let home_path = home_dir.as_ref().join(CONFIG_FILE_NAME);
+
+        let npmrc_paths = npmrc::npmrc_paths(current_dir.as_ref(), home_dir.as_ref());
+
+        Ok(Self {
+            project: Self::read(&project_path)?,
+            home: Self::read(&home_path)?,
+            npmrc: npmrc::load(&npmrc_paths)?,
+            project_path,
+            home_path,
+        })
+    }
+
+    /// Reads a single `.voltrc` file, returning an empty map if it does not exist.
+    fn read(path: &Path) -> Result<BTreeMap<String, String>> {
+        Ok(Self::read_table(path)?
+            .into_iter()
+            .map(|(key, value)| (key, setting(value)))
+            .collect())
+    }
+
+    /// Reads a single `.voltrc` file with the TOML types of its values.
+    fn read_table(path: &Path) -> Result<BTreeMap<String, toml::Value>> {
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let data = read_to_string(path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?;
+
+        let table = toml::from_str(&data).map_err(|e| VoltError::ConfigParseError {
+            path: path.to_string_lossy().to_string(),
+            source: e,
+        })?;
+
+        Ok(table)
+    }
+
+    /// Get the value of a key, preferring the project configuration.
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.project
+            .get(key)
+            .or_else(|| self.home.get(key))
+            .or_else(|| self.npmrc.get(key))
+    }
+
+    /// Set a key in the project configuration, or the home configuration if `global` is set.
+    pub fn set(&mut self, key: &str, value: &str, global: bool) {
+        let values = if global {
+            &mut self.home
+        } else {
+            &mut self.project
+        };
+
+        values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Remove a key from the project configuration, or the home configuration if `global` is set.
+    pub fn delete(&mut self, key: &str, global: bool) -> Option<String> {
+        if global {
+            self.home.remove(key)
+        } else {
+            self.project.remove(key)
+        }
+    }
+
+    /// All effective settings, with project values overriding home values and both
+    /// overriding `.npmrc`.
+    pub fn list(&self) -> BTreeMap<String, String> {
+        let mut merged = self.npmrc.clone();
+        merged.extend(self.home.clone());
+        merged.extend(self.project.clone());
+        merged
+    }
+
+    /// Finds the auth token for the registry serving `url`, from npmrc-style
+    /// `//host/path/:_authToken` keys. The most specific matching registry wins.
+    ///
+    /// Tokens are scoped to their registry's host, so a tarball served from a
+    /// different host (a CDN or mirror) is fetched without credentials, and they're
+    /// only ever sent over https.
+    pub fn auth_token(&self, url: &str) -> Option<String> {
+        let (scheme, rest) = url.split_once("://")?;
+
+        if !scheme.eq_ignore_ascii_case("https") {
+            return None;
+        }
+
+        // https://registry.npmjs.org/react/-/react-17.0.2.tgz -> //registry.npmjs.org/react/-/...
+        let location = format!("//{}", rest);
+
+        self.list()
+            .into_iter()
+            .filter_map(|(key, token)| {
+                key.strip_suffix(":_authToken")
+                    .map(|registry| (registry.to_string(), token))
+            })
+            .filter(|(registry, _)| registry_matches(registry, &location))
+            .max_by_key(|(registry, _)| registry.len())
+            .map(|(_, token)| token)
+    }
+
+    /// Writes the home configuration back to disk if `global` is set, otherwise the
+    /// project's. The other file is left as it is.
+    pub fn save(&self, global: bool) -> Result<()> {
+        if global {
+            Self::write(&self.home_path, &self.home)
+        } else {
+            Self::write(&self.project_path, &self.project)
+        }
+    }
+
+    /// Settings the file already had keep their TOML type, new ones are written as a
+    /// boolean or an integer when they read as one and as a string otherwise.
+    fn write(path: &Path, values: &BTreeMap<String, String>) -> Result<()> {
+        // don't leave behind empty config files that were never created
+        if values.is_empty() && !path.exists() {
+            return Ok(());
+        }
+
+        let written = Self::read_table(path)?;
+
+        let table: BTreeMap<&String, toml::Value> = values
+            .iter()
+            .map(|(key, value)| match written.get(key) {
+                Some(original) if setting(original.clone()) == *value => (key, original.clone()),
+                _ => (key, typed(value)),
+            })
+            .collect();
+
+        let data = toml::to_string(&table).map_err(|_| VoltError::ConfigSerializeError {
+            path: path.to_string_lossy().to_string(),
+        })?;
+
+        let mut file = File::create(path).map_err(|e| VoltError::WriteFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?;
+
+        file.write_all(data.as_bytes())
+            .map_err(|e| VoltError::WriteFileError {
+                source: e,
+                name: path.to_string_lossy().to_string(),
+            })?;
+
+        Ok(())
+    }
+}
+
+/// A `.voltrc` value as volt reads it: strings without quotes, everything else as written.
+fn setting(value: toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// The TOML value a setting from the command line is written as.
+fn typed(value: &str) -> toml::Value {
+    match value {
+        "true" => toml::Value::Boolean(true),
+        "false" => toml::Value::Boolean(false),
+        _ => match value.parse::<i64>() {
+            // 010 or +1 would be written back differently, keep them as they were typed
+            Ok(number) if number.to_string() == value => toml::Value::Integer(number),
+            _ => toml::Value::String(value.to_string()),
+        },
+    }
+}
+
+/// Whether an npmrc registry key like `//registry.corp.com/npm/` covers `location`.
+///
+/// The key has to end at a path or port boundary of the location, so a token for
+/// `//registry.corp.com` isn't sent to `//registry.corp.com.evil.io`.
+fn registry_matches(registry: &str, location: &str) -> bool {
+    match location.strip_prefix(registry) {
+        Some(rest) => {
+            registry.ends_with('/') || rest.is_empty() || rest.starts_with(&['/', ':'][..])
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(tokens: &[(&str, &str)]) -> VoltConfig {
+        let mut config = VoltConfig::default();
+
+        for (registry, token) in tokens {
+            config.set(&format!("{}:_authToken", registry), token, false);
+        }
+
+        config
+    }
+
+    #[test]
+    fn tokens_follow_the_tarball_host() {
+        let config = config(&[("//registry.corp.com/", "corp")]);
+
+        assert_eq!(
+            config.auth_token("https://registry.corp.com/react/-/react-17.0.2.tgz"),
+            Some(String::from("corp"))
+        );
+
+        // a packument on the registry pointing dist.tarball at a cdn
+        assert_eq!(
+            config.auth_token("https://cdn.corp.com/react/-/react-17.0.2.tgz"),
+            None
+        );
+    }
+
+    #[test]
+    fn the_most_specific_registry_wins() {
+        let config = config(&[
+            ("//registry.corp.com/", "corp"),
+            ("//registry.corp.com/npm/private/", "private"),
+        ]);
+
+        assert_eq!(
+            config.auth_token("https://registry.corp.com/npm/private/a/-/a-1.0.0.tgz"),
+            Some(String::from("private"))
+        );
+    }
+
+    #[test]
+    fn tokens_stop_at_the_host_boundary() {
+        let config = config(&[("//registry.corp.com", "corp")]);
+
+        assert_eq!(
+            config.auth_token("https://registry.corp.com.evil.io/a/-/a-1.0.0.tgz"),
+            None
+        );
+        assert_eq!(
+            config.auth_token("https://registry.corp.com/a/-/a-1.0.0.tgz"),
+            Some(String::from("corp"))
+        );
+        assert_eq!(
+            config.auth_token("https://registry.corp.com:8443/a/-/a-1.0.0.tgz"),
+            Some(String::from("corp"))
+        );
+    }
+
+    #[test]
+    fn tokens_are_never_sent_over_http() {
+        let config = config(&[("//registry.corp.com/", "corp")]);
+
+        assert_eq!(
+            config.auth_token("http://registry.corp.com/a/-/a-1.0.0.tgz"),
+            None
+        );
+    }
+
+    #[test]
+    fn saving_writes_only_the_changed_scope_and_keeps_types() {
+        let root = std::env::temp_dir().join(format!("volt-config-save-{}", std::process::id()));
+        let (project, home) = (root.join("project"), root.join("home"));
+
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&home).unwrap();
+
+        let home_file = "registry = \"https://registry.corp.com\"\n";
+        std::fs::write(home.join(CONFIG_FILE_NAME), home_file).unwrap();
+        std::fs::write(
+            project.join(CONFIG_FILE_NAME),
+            "max-sockets = 16\nsave-prefix = \"~\"\n",
+        )
+        .unwrap();
+
+        let mut config = VoltConfig::load(&project, &home).unwrap();
+        config.set("strict-ssl", "false", false);
+        config.set("network-concurrency", "8", false);
+        config.set("save-prefix", "^", false);
+        config.save(false).unwrap();
+
+        let saved = VoltConfig::read_table(&project.join(CONFIG_FILE_NAME)).unwrap();
+        let home_saved = std::fs::read_to_string(home.join(CONFIG_FILE_NAME)).unwrap();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(saved["max-sockets"], toml::Value::Integer(16));
+        assert_eq!(saved["strict-ssl"], toml::Value::Boolean(false));
+        assert_eq!(saved["network-concurrency"], toml::Value::Integer(8));
+        assert_eq!(saved["save-prefix"], toml::Value::String(String::from("^")));
+        assert!(!saved.contains_key("registry"));
+        assert_eq!(home_saved, home_file);
+    }
+
+    #[test]
+    fn only_plain_numbers_and_booleans_are_typed() {
+        assert_eq!(typed("true"), toml::Value::Boolean(true));
+        assert_eq!(typed("42"), toml::Value::Integer(42));
+        assert_eq!(typed("010"), toml::Value::String(String::from("010")));
+        assert_eq!(typed("1.0"), toml::Value::String(String::from("1.0")));
+        assert_eq!(typed("True"), toml::Value::String(String::from("True")));
+    }
+}
@@ -14,16 +14,28 @@
     limitations under the License.
 */
 
+use crate::core::net::{retry_after, SOCKETS, THROTTLE};
+use crate::core::utils::constants::MAX_RETRIES;
 use crate::core::utils::package::NpmPackage;
 use isahc::http::StatusCode;
 use isahc::AsyncReadResponseExt;
+use isahc::{Request, RequestExt};
 use std::io;
 use thiserror::Error;
 
+/// `Accept` header for the abbreviated ("corgi") packument format.
+///
+/// Abbreviated documents only contain what's needed to install a package
+/// (dist-tags, versions, dependencies, dist), leaving out readmes and other bulky fields.
+const ABBREVIATED_ACCEPT: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+
 #[derive(Error, Debug)]
 pub enum GetPackageError {
     #[error("network request failed with registry")]
     Request(isahc::Error),
+    #[error("unable to build network request")]
+    Builder(isahc::http::Error),
     #[error("unable to read network response")]
     IO(io::Error),
     #[error("unable to deserialize network response: {0:?}")]
@@ -31,25 +43,115 @@ pub enum GetPackageError {
 }
 
 #[allow(dead_code)]
-/// Request a package from `registry.yarnpkg.com`
+/// Request a package from a registry (e.g. `registry.yarnpkg.com`)
 ///
-/// Uses `chttp` async implementation to send a `get` request for the package
+/// Requests the abbreviated packument, falling back to the full document
+/// if the registry doesn't support the abbreviated format.
 /// ## Arguments
-/// * `name` - Name of the package to request from `registry.yarnpkg.com`
+/// * `registry` - Base url of the registry
+/// * `name` - Name of the package to request from the registry
+/// * `headers` - Extra headers to send, see `App::request_headers`
 /// ## Examples
 /// ```
 /// // Await an async response
-/// get_package("react").await;
+/// get_package("https://registry.yarnpkg.com", "react", &app.request_headers()).await;
 /// ```
 /// ## Returns
 /// * `Result<Option<Package>, GetPackageError>`
-pub async fn get_package(name: &str) -> Result<Option<NpmPackage>, GetPackageError> {
-    let mut resp = isahc::get_async(format!("http://registry.yarnpkg.com/{}", name))
-        .await
-        .map_err(GetPackageError::Request)?;
+pub async fn get_package(
+    registry: &str,
+    name: &str,
+    headers: &[(String, String)],
+) -> Result<Option<NpmPackage>, GetPackageError> {
+    match request_package(registry, name, Some(ABBREVIATED_ACCEPT), headers).await? {
+        Some(package) => Ok(Some(package)),
+        None => get_package_full(registry, name, headers).await,
+    }
+}
+
+#[allow(dead_code)]
+/// Request the full packument for a package from a registry
+///
+/// Use this instead of [`get_package`] when fields only present in the full document
+/// (readme, time, maintainers) are needed.
+/// ## Arguments
+/// * `registry` - Base url of the registry
+/// * `name` - Name of the package to request from the registry
+/// * `headers` - Extra headers to send, see `App::request_headers`
+/// ## Returns
+/// * `Result<Option<Package>, GetPackageError>`
+pub async fn get_package_full(
+    registry: &str,
+    name: &str,
+    headers: &[(String, String)],
+) -> Result<Option<NpmPackage>, GetPackageError> {
+    request_package(registry, name, None, headers).await
+}
+
+/// Url of a package's packument. The `/` of a scoped name is escaped (`@babel%2fcore`),
+/// which every registry accepts and some private registries require.
+fn packument_url(registry: &str, name: &str) -> String {
+    let name = if name.starts_with('@') {
+        name.replacen('/', "%2f", 1)
+    } else {
+        name.to_string()
+    };
+
+    format!("{}/{}", registry.trim_end_matches('/'), name)
+}
+
+/// Sends the request for a packument, returning `Ok(None)` if the registry refused the `accept` type.
+async fn request_package(
+    registry: &str,
+    name: &str,
+    accept: Option<&str>,
+    headers: &[(String, String)],
+) -> Result<Option<NpmPackage>, GetPackageError> {
+    let mut retries = 0;
+
+    let (mut resp, _socket) = loop {
+        THROTTLE.wait().await;
+
+        let url = packument_url(registry, name);
+
+        // held until the body has been read
+        let socket = SOCKETS.acquire(&url).await;
+
+        let mut request = Request::get(url);
+
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        if let Some(accept) = accept {
+            request = request.header("Accept", accept);
+        }
+
+        let resp = request
+            .body(())
+            .map_err(GetPackageError::Builder)?
+            .send_async()
+            .await
+            .map_err(GetPackageError::Request)?;
+
+        // rate limited, back off together with every other request
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS && retries < MAX_RETRIES {
+            THROTTLE.back_off(retry_after(
+                resp.headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok()),
+            ));
+
+            retries += 1;
+            continue;
+        }
+
+        break (resp, socket);
+    };
 
     if !resp.status().is_success() {
         match resp.status() {
+            StatusCode::NOT_ACCEPTABLE if accept.is_some() => return Ok(None),
             StatusCode::NOT_FOUND => {}
             StatusCode::INTERNAL_SERVER_ERROR => {}
             StatusCode::METHOD_NOT_ALLOWED => {}
@@ -62,3 +164,20 @@ pub async fn get_package(name: &str) -> Result<Option<NpmPackage>, GetPackageErr
 
     Ok(Some(package))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_packument_urls_are_escaped() {
+        assert_eq!(
+            packument_url("https://registry.npmjs.org/", "@babel/core"),
+            "https://registry.npmjs.org/@babel%2fcore"
+        );
+        assert_eq!(
+            packument_url("https://registry.npmjs.org", "react"),
+            "https://registry.npmjs.org/react"
+        );
+    }
+}
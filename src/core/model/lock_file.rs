@@ -14,6 +14,9 @@
     limitations under the License.
 */
 
+use crate::commands::add::Package;
+use crate::core::utils::voltapi::{split_dependency, Funding, PeerDependencyMeta, VoltPackage};
+
 use std::collections::hash_map::DefaultHasher;
 
 use std::collections::{BTreeMap, HashMap};
@@ -22,6 +25,7 @@ use std::hash::{Hash, Hasher};
 use std::io;
 use std::path::{Path, PathBuf};
 
+use node_semver::{Range, Version};
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
@@ -32,8 +36,8 @@ pub enum LockFileError {
     #[error("unable to deserialize lock file")]
     #[allow(dead_code)]
     Decode(serde_json::Error),
-    // #[error("unable to serialize lock file")]
-    // Encode(serde_json::Error),
+    #[error("unable to serialize lock file")]
+    Encode(serde_json::Error),
 }
 
 /// The lock file is responsible for locking/pinning dependency versions in a given project.
@@ -96,14 +100,11 @@ impl<'de> de::Deserialize<'de> for DependencyID {
     where
         D: Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
-        let mut parts = s.split('@');
-        let name = parts
-            .next()
-            .ok_or_else(|| de::Error::custom("missing dependency name"))?;
-        let version = parts
-            .next()
-            .ok_or_else(|| de::Error::custom("missing dependency version"))?;
+        // an owned string, keys read from a file can't be borrowed
+        let s: String = Deserialize::deserialize(deserializer)?;
+        // @types/node@16.7.1 splits at its last @
+        let (name, version) = split_dependency(&s);
+        let version = version.ok_or_else(|| de::Error::custom("missing dependency version"))?;
         Ok(DependencyID(name.to_string(), version.to_string()))
     }
 }
@@ -142,6 +143,100 @@ pub struct DependencyLock {
     pub tarball: String,
     pub integrity: String,
     pub dependencies: Vec<String>,
+    /// Entries locked before volt kept the manifest don't have one, and are resolved again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<LockedManifest>,
+}
+
+/// The parts of a package's manifest installing it needs, locked so `volt install` can
+/// install the locked tree without resolving it again.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct LockedManifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optional_dependencies: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_dependencies: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_dependencies_meta: Option<BTreeMap<String, PeerDependencyMeta>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding: Option<Funding>,
+}
+
+impl DependencyLock {
+    /// Locks a resolved package, along with the manifest to install it again from the lock.
+    pub fn from_package(package: &VoltPackage) -> Self {
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            tarball: package.tarball.clone(),
+            integrity: package.integrity.clone(),
+            dependencies: package.dependencies.clone().unwrap_or_default(),
+            manifest: Some(LockedManifest {
+                // sorted, so the lock only changes when the package does
+                bin: package
+                    .bin
+                    .as_ref()
+                    .map(|bin| bin.clone().into_iter().collect()),
+                optional_dependencies: package.optional_dependencies.clone(),
+                peer_dependencies: package.peer_dependencies.clone(),
+                peer_dependencies_meta: package
+                    .peer_dependencies_meta
+                    .as_ref()
+                    .map(|meta| meta.clone().into_iter().collect()),
+                os: package.os.clone(),
+                cpu: package.cpu.clone(),
+                funding: package.funding.clone(),
+            }),
+        }
+    }
+
+    /// The package this entry installs, `None` when it was locked without a manifest.
+    pub fn to_package(&self) -> Option<VoltPackage> {
+        Some(self.package_with(self.manifest.clone()?))
+    }
+
+    /// The package this entry installs as far as the lock knows it, without bins, peers or
+    /// platforms when it was locked without a manifest. Enough to lay out `node_modules`.
+    pub fn to_partial_package(&self) -> VoltPackage {
+        self.package_with(self.manifest.clone().unwrap_or_default())
+    }
+
+    fn package_with(&self, manifest: LockedManifest) -> VoltPackage {
+        VoltPackage {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            tarball: self.tarball.clone(),
+            bin: manifest.bin.map(|bin| bin.into_iter().collect()),
+            integrity: self.integrity.clone(),
+            peer_dependencies: manifest.peer_dependencies,
+            peer_dependencies_meta: manifest
+                .peer_dependencies_meta
+                .map(|meta| meta.into_iter().collect()),
+            dependencies: Some(self.dependencies.clone()),
+            optional_dependencies: manifest.optional_dependencies,
+            os: manifest.os,
+            cpu: manifest.cpu,
+            funding: manifest.funding,
+            unpacked_size: None,
+        }
+    }
+}
+
+/// The locked entries reachable from some requested packages, see [`LockFile::reachable`].
+#[derive(Debug, Default)]
+pub struct LockedEntries<'a> {
+    /// The version locked for each requested package that has one inside its range.
+    pub versions: HashMap<String, String>,
+    /// Every entry reached, keyed by `name@version`.
+    pub entries: HashMap<String, &'a DependencyLock>,
+    /// Whether every requested package and every dependency edge was found in the lock.
+    pub complete: bool,
 }
 
 impl LockFile {
@@ -171,17 +266,305 @@ impl LockFile {
         })
     }
 
-    // Saves a lock file dumping pretty, formatted json
-    // pub fn save_pretty(&self) -> Result<(), LockFileError> {
-    //     let lock_file = File::create(&self.path).map_err(LockFileError::IO)?;
-    //     let writer = BufWriter::new(lock_file);
-    //     serde_json::to_writer_pretty(writer, &self.dependencies).map_err(LockFileError::Encode)
-    // }
+    /// The tree locked for `packages`, keyed by `name@version` like a resolved tree, and the
+    /// version locked for each of them.
+    ///
+    /// `None` when the lock can't stand in for resolving: a package isn't locked at a version
+    /// inside its range, an entry was locked without its manifest, or a dependency could be
+    /// more than one of the locked versions of it.
+    pub fn locked_tree(
+        &self,
+        packages: &[Package],
+    ) -> Option<(HashMap<String, String>, HashMap<String, VoltPackage>)> {
+        let reached = self.reachable(packages);
+
+        if !reached.complete {
+            return None;
+        }
+
+        let tree = reached
+            .entries
+            .into_iter()
+            .map(|(key, entry)| Some((key, entry.to_package()?)))
+            .collect::<Option<HashMap<_, _>>>()?;
+
+        Some((reached.versions, tree))
+    }
+
+    /// Follows the lock from `packages` down through every dependency edge.
+    ///
+    /// Each package starts at its highest locked version inside its range. Packages without
+    /// one, and edges that match no locked version or more than one, are skipped and leave
+    /// the result incomplete.
+    pub fn reachable(&self, packages: &[Package]) -> LockedEntries<'_> {
+        let mut reached = LockedEntries {
+            complete: true,
+            ..LockedEntries::default()
+        };
+
+        let mut stack = vec![];
+
+        for package in packages {
+            let range = package
+                .version
+                .as_ref()
+                .and_then(|v| v.parse::<Range>().ok());
+
+            match range.and_then(|range| self.find(&package.name, Some(&range))) {
+                Some(entry) => {
+                    reached
+                        .versions
+                        .insert(package.name.clone(), entry.version.clone());
+                    stack.push(entry);
+                }
+                None => reached.complete = false,
+            }
+        }
+
+        while let Some(entry) = stack.pop() {
+            let key = format!("{}@{}", entry.name, entry.version);
+
+            if reached.entries.contains_key(&key) {
+                continue;
+            }
+
+            // edges are written as react, react@17.0.2 or react@^17.0.0
+            for dependency in entry.dependencies.iter() {
+                let child = match split_dependency(dependency) {
+                    (name, Some(version)) => version
+                        .parse::<Range>()
+                        .ok()
+                        .and_then(|range| self.find(name, Some(&range))),
+                    (name, None) => self.find(name, None),
+                };
+
+                match child {
+                    Some(child) => stack.push(child),
+                    None => reached.complete = false,
+                }
+            }
+
+            reached.entries.insert(key, entry);
+        }
+
+        reached
+    }
+
+    /// The highest locked version of `name` inside `range`. Without a range the name has to
+    /// be locked at a single version, there'd be no telling which one was meant otherwise.
+    fn find(&self, name: &str, range: Option<&Range>) -> Option<&DependencyLock> {
+        let candidates: Vec<(Version, &DependencyLock)> = self
+            .dependencies
+            .values()
+            .filter(|entry| entry.name == name)
+            .filter_map(|entry| Some((entry.version.parse::<Version>().ok()?, entry)))
+            .filter(|(version, _)| range.map(|r| r.satisfies(version)).unwrap_or(true))
+            .collect();
+
+        if range.is_none() && candidates.len() > 1 {
+            return None;
+        }
+
+        candidates
+            .into_iter()
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, entry)| entry)
+    }
+
+    /// Finds a chain of dependents leading from a top-level dependency down to `name`.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// // ["express", "body-parser", "qs"]
+    /// lock_file.path_to("qs");
+    /// ```
+    pub fn path_to(&self, name: &str) -> Vec<String> {
+        let mut path = vec![name.to_string()];
+
+        while let Some(parent) = self.dependencies.values().find(|dependency| {
+            !path.contains(&dependency.name)
+                && dependency
+                    .dependencies
+                    .iter()
+                    .any(|child| child == path.last().unwrap())
+        }) {
+            path.push(parent.name.clone());
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Saves the lock file to the path it was loaded from, sorted so it diffs cleanly.
+    pub fn save(&self) -> Result<(), LockFileError> {
+        let ordered: BTreeMap<_, _> = self.dependencies.iter().collect();
+        let data = serde_json::to_string_pretty(&ordered).map_err(LockFileError::Encode)?;
+
+        std::fs::write(&self.path, data + "\n").map_err(LockFileError::IO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, version: &str, dependencies: &[&str]) -> DependencyLock {
+        DependencyLock {
+            name: name.to_string(),
+            version: version.to_string(),
+            tarball: format!(
+                "https://registry.npmjs.org/{0}/-/{0}-{1}.tgz",
+                name, version
+            ),
+            integrity: String::new(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            manifest: Some(LockedManifest::default()),
+        }
+    }
+
+    fn lock(entries: Vec<DependencyLock>) -> LockFile {
+        let mut lock_file = LockFile::new("volt.lock");
+
+        for entry in entries {
+            lock_file.dependencies.insert(
+                DependencyID(entry.name.clone(), entry.version.clone()),
+                entry,
+            );
+        }
+
+        lock_file
+    }
+
+    fn package(name: &str, range: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: Some(range.to_string()),
+        }
+    }
+
+    fn keys(reached: &LockedEntries) -> Vec<String> {
+        let mut keys: Vec<String> = reached.entries.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn follows_dependencies_from_the_requested_packages() {
+        let lock_file = lock(vec![
+            entry("express", "4.17.1", &["qs@6.7.0", "debug"]),
+            entry("qs", "6.7.0", &[]),
+            entry("debug", "2.6.9", &["ms@^2.0.0"]),
+            entry("ms", "2.0.0", &[]),
+            entry("unrelated", "1.0.0", &[]),
+        ]);
+
+        let reached = lock_file.reachable(&[package("express", "^4.17.0")]);
+
+        assert!(reached.complete);
+        assert_eq!(reached.versions["express"], "4.17.1");
+        assert_eq!(
+            keys(&reached),
+            vec!["debug@2.6.9", "express@4.17.1", "ms@2.0.0", "qs@6.7.0"]
+        );
+    }
+
+    #[test]
+    fn picks_the_highest_locked_version_in_range() {
+        let lock_file = lock(vec![
+            entry("react", "16.14.0", &[]),
+            entry("react", "17.0.1", &[]),
+            entry("react", "17.0.2", &[]),
+        ]);
 
-    // Saves a lock file to the same path it was opened from.
-    // pub fn save(&self) -> Result<(), LockFileError> {
-    //     let lock_file = File::create(&self.path).map_err(LockFileError::IO)?;
-    //     let writer = BufWriter::new(lock_file);
-    //     serde_json::to_writer(writer, &self.dependencies).map_err(LockFileError::Encode)
-    // }
+        let reached = lock_file.reachable(&[package("react", "^17.0.0")]);
+
+        assert_eq!(reached.versions["react"], "17.0.2");
+        assert_eq!(keys(&reached), vec!["react@17.0.2"]);
+    }
+
+    #[test]
+    fn unlocked_packages_leave_it_incomplete() {
+        let lock_file = lock(vec![entry("react", "16.14.0", &[])]);
+
+        let reached = lock_file.reachable(&[package("react", "^17.0.0")]);
+
+        assert!(!reached.complete);
+        assert!(reached.versions.is_empty());
+        assert!(lock_file
+            .locked_tree(&[package("react", "^17.0.0")])
+            .is_none());
+    }
+
+    #[test]
+    fn ambiguous_edges_leave_it_incomplete() {
+        let lock_file = lock(vec![
+            entry("a", "1.0.0", &["b"]),
+            entry("b", "1.0.0", &[]),
+            entry("b", "2.0.0", &[]),
+        ]);
+
+        assert!(!lock_file.reachable(&[package("a", "1.0.0")]).complete);
+    }
+
+    #[test]
+    fn entries_without_a_manifest_are_resolved_again() {
+        let mut old = entry("react", "17.0.2", &[]);
+        old.manifest = None;
+
+        let lock_file = lock(vec![old]);
+
+        assert!(lock_file.reachable(&[package("react", "^17.0.0")]).complete);
+        assert!(lock_file
+            .locked_tree(&[package("react", "^17.0.0")])
+            .is_none());
+    }
+
+    #[test]
+    fn locked_manifests_round_trip() {
+        let mut bin = HashMap::new();
+        bin.insert(String::from("tsc"), String::from("bin/tsc"));
+
+        let package = VoltPackage {
+            name: String::from("typescript"),
+            version: String::from("4.4.3"),
+            tarball: String::from("https://registry.npmjs.org/typescript/-/typescript-4.4.3.tgz"),
+            bin: Some(bin),
+            integrity: String::from("sha512-abc"),
+            peer_dependencies: None,
+            peer_dependencies_meta: None,
+            dependencies: Some(vec![]),
+            optional_dependencies: None,
+            os: Some(vec![String::from("!win32")]),
+            cpu: None,
+            funding: None,
+            unpacked_size: None,
+        };
+
+        let locked = DependencyLock::from_package(&package);
+        let data = serde_json::to_string(&locked).unwrap();
+        let read: DependencyLock = serde_json::from_str(&data).unwrap();
+
+        assert_eq!(read.to_package(), Some(package));
+    }
+
+    #[test]
+    fn saved_lock_files_load_again() {
+        let path = std::env::temp_dir().join(format!("volt-lock-{}.lock", std::process::id()));
+        let mut lock_file = lock(vec![
+            entry("@types/node", "16.7.1", &[]),
+            entry("react", "17.0.2", &["loose-envify@^1.1.0"]),
+        ]);
+        lock_file.path = path.clone();
+
+        lock_file.save().unwrap();
+        let loaded = LockFile::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.dependencies.len(), 2);
+        assert!(loaded.dependencies.contains_key(&DependencyID(
+            String::from("@types/node"),
+            String::from("16.7.1")
+        )));
+    }
 }
@@ -0,0 +1,207 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Settings read from npm's `.npmrc` files, so a project already set up for npm (registries,
+//! auth tokens, `save-prefix`...) works with volt unchanged.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use miette::Result;
+
+use crate::core::utils::errors::VoltError;
+
+/// Name of npm's configuration file in the project and home directories.
+pub const NPMRC_FILE_NAME: &str = ".npmrc";
+
+/// Reads an npm setting from the environment, where npm accepts either case.
+fn npm_env(name: &str) -> Option<String> {
+    env::var(format!("NPM_CONFIG_{}", name.to_uppercase()))
+        .or_else(|_| env::var(format!("npm_config_{}", name)))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// The global `npmrc`: `$NPM_CONFIG_GLOBALCONFIG`, otherwise `etc/npmrc` under npm's prefix.
+fn global_npmrc() -> Option<PathBuf> {
+    if let Some(path) = npm_env("globalconfig") {
+        return Some(PathBuf::from(path));
+    }
+
+    if let Some(prefix) = npm_env("prefix") {
+        return Some(Path::new(&prefix).join("etc").join("npmrc"));
+    }
+
+    if cfg!(windows) {
+        env::var_os("APPDATA")
+            .map(|appdata| Path::new(&appdata).join("npm").join("etc").join("npmrc"))
+    } else {
+        Some(PathBuf::from("/usr/local/etc/npmrc"))
+    }
+}
+
+/// The `.npmrc` files npm reads, lowest precedence first: global, user, then project.
+pub fn npmrc_paths(current_dir: &Path, home_dir: &Path) -> Vec<PathBuf> {
+    let user = npm_env("userconfig")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir.join(NPMRC_FILE_NAME));
+
+    global_npmrc()
+        .into_iter()
+        .chain(vec![user, current_dir.join(NPMRC_FILE_NAME)])
+        .collect()
+}
+
+/// Replaces `${VAR}` with the environment variable, the way npm expands tokens kept out of
+/// the file (`//registry.npmjs.org/:_authToken=${NPM_TOKEN}`). Unset variables are left as written.
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        expanded.push_str(&rest[..start]);
+
+        match env::var(&rest[start + 2..end]) {
+            Ok(variable) => expanded.push_str(&variable),
+            Err(_) => expanded.push_str(&rest[start..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Parses the ini-style contents of an `.npmrc`: `key=value` lines with `;` and `#`
+/// comments. A key without a value is `true`, like npm reads it. Sections and `key[]`
+/// arrays aren't settings volt uses and are skipped.
+pub fn parse(data: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+
+    for line in data.lines() {
+        let line = line.trim();
+
+        if line.is_empty()
+            || line.starts_with(';')
+            || line.starts_with('#')
+            || line.starts_with('[')
+        {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (line, "true"),
+        };
+
+        if key.is_empty() || key.ends_with("[]") {
+            continue;
+        }
+
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+
+        values.insert(expand_env(key), expand_env(value));
+    }
+
+    values
+}
+
+/// Reads and merges `.npmrc` files, each overriding the ones before it. Missing files are skipped.
+pub fn load(paths: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    let mut merged = BTreeMap::new();
+
+    for path in paths {
+        if !path.is_file() {
+            continue;
+        }
+
+        let data = read_to_string(path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?;
+
+        merged.extend(parse(&data));
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_parsed_like_npm() {
+        let values = parse(
+            "; comment\n# comment\n[section]\nregistry = https://r.example/\nsave-exact\nsave-prefix=\"~\"\nca[]=cert\n",
+        );
+
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["registry"], "https://r.example/");
+        assert_eq!(values["save-exact"], "true");
+        assert_eq!(values["save-prefix"], "~");
+    }
+
+    #[test]
+    fn set_variables_are_expanded() {
+        env::set_var("VOLT_NPMRC_TEST_TOKEN", "secret");
+
+        let values =
+            parse("//r.example/:_authToken=${VOLT_NPMRC_TEST_TOKEN}\nother=${VOLT_NPMRC_UNSET}\n");
+
+        assert_eq!(values["//r.example/:_authToken"], "secret");
+        assert_eq!(values["other"], "${VOLT_NPMRC_UNSET}");
+        assert_eq!(expand_env("a${VOLT_NPMRC_TEST_TOKEN}b${"), "asecretb${");
+    }
+
+    #[test]
+    fn later_files_override_earlier_ones() {
+        let dir = env::temp_dir().join(format!("volt-npmrc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let user = dir.join("user");
+        let project = dir.join("project");
+        std::fs::write(&user, "registry=https://user.example/\nsave-exact=true\n").unwrap();
+        std::fs::write(&project, "registry=https://project.example/\n").unwrap();
+
+        let values = load(&[user, dir.join("missing"), project]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(values["registry"], "https://project.example/");
+        assert_eq!(values["save-exact"], "true");
+    }
+
+    #[test]
+    fn the_project_npmrc_is_read_last() {
+        let paths = npmrc_paths(Path::new("/project"), Path::new("/home"));
+
+        assert_eq!(
+            paths.last().unwrap(),
+            &Path::new("/project").join(NPMRC_FILE_NAME)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Rate and connection limiting shared by every request an install makes.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::core::utils::constants::{
+    DEFAULT_RETRY_AFTER, INITIAL_CONCURRENCY, MAX_ADAPTIVE_CONCURRENCY, THROTTLE_JITTER,
+};
+
+lazy_static! {
+    /// The throttle every registry and tarball request goes through.
+    pub static ref THROTTLE: Throttle = Throttle::default();
+
+    /// The connection limit every registry and tarball request goes through.
+    pub static ref SOCKETS: Sockets = Sockets::default();
+
+    /// How many packages an install downloads at once with `--adaptive-concurrency`.
+    pub static ref CONCURRENCY: Concurrency = Concurrency::default();
+}
+
+/// A gate that pauses every request once the registry answers 429 (Too Many Requests).
+///
+/// Installs run many requests at once, and each backing off on its own would keep hitting a
+/// registry that's already rate limiting. Instead the first 429 closes the gate for as long
+/// as the registry's `Retry-After` asks, and requests waiting on it are let through over a
+/// short jittered window rather than all at once.
+#[derive(Default)]
+pub struct Throttle {
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Waits until the gate is open.
+    pub async fn wait(&self) {
+        let until = match *self.paused_until.lock().unwrap() {
+            Some(until) if until > Instant::now() => until,
+            _ => return,
+        };
+
+        // resume gradually instead of as a thundering herd
+        let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..THROTTLE_JITTER));
+
+        tokio::time::sleep_until((until + jitter).into()).await;
+    }
+
+    /// Closes the gate for `retry_after`, or longer if it's already closed for longer.
+    pub fn back_off(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        let mut paused_until = self.paused_until.lock().unwrap();
+
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+}
+
+/// Bounds how many requests are open to a single host at once, from the `max-sockets`
+/// setting.
+///
+/// Resolving and downloading share the limit, so a proxy or firewall that resets connections
+/// past a certain count sees at most `max-sockets` from volt however the install is going.
+/// Without the setting requests aren't limited here.
+#[derive(Default)]
+pub struct Sockets {
+    limit: AtomicUsize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl Sockets {
+    /// Sets the number of requests allowed per host, `None` lifts the limit.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.limit.store(limit.unwrap_or(0), Ordering::SeqCst);
+        self.hosts.lock().unwrap().clear();
+    }
+
+    /// Waits for a free slot to the host of `url`, which is held until the permit is dropped.
+    pub async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let limit = self.limit.load(Ordering::SeqCst);
+
+        if limit == 0 {
+            return None;
+        }
+
+        // requests to different ports of one host are still the same firewall's business
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+
+        let semaphore = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+/// Install concurrency that follows the connection, enabled by `--adaptive-concurrency`.
+///
+/// Like TCP congestion control it starts with a few downloads at once and doubles them while
+/// each window of downloads finishes with more throughput than the one before, then grows one
+/// download at a time. A 429 or a timed out download halves it. Until it's enabled the limit
+/// is left to the install.
+#[derive(Default)]
+pub struct Concurrency {
+    state: Mutex<Option<AdaptiveState>>,
+}
+
+struct AdaptiveState {
+    limit: usize,
+    slow_start: bool,
+    window_started: Instant,
+    window_bytes: u64,
+    window_downloads: usize,
+    last_throughput: f64,
+    metrics: ConcurrencyMetrics,
+}
+
+/// What adaptive concurrency did over an install, printed with `--verbose`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConcurrencyMetrics {
+    pub initial: usize,
+    pub peak: usize,
+    pub last: usize,
+    pub back_offs: usize,
+    /// Best throughput of a window, in bytes per second.
+    pub best_throughput: f64,
+}
+
+impl AdaptiveState {
+    fn new() -> Self {
+        Self {
+            limit: INITIAL_CONCURRENCY,
+            slow_start: true,
+            window_started: Instant::now(),
+            window_bytes: 0,
+            window_downloads: 0,
+            last_throughput: 0.0,
+            metrics: ConcurrencyMetrics {
+                initial: INITIAL_CONCURRENCY,
+                peak: INITIAL_CONCURRENCY,
+                last: INITIAL_CONCURRENCY,
+                ..ConcurrencyMetrics::default()
+            },
+        }
+    }
+
+    fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1).min(MAX_ADAPTIVE_CONCURRENCY);
+        self.metrics.peak = self.metrics.peak.max(self.limit);
+        self.metrics.last = self.limit;
+    }
+
+    fn reset_window(&mut self) {
+        self.window_started = Instant::now();
+        self.window_bytes = 0;
+        self.window_downloads = 0;
+    }
+}
+
+impl Concurrency {
+    /// Starts adapting, from a conservative number of downloads.
+    pub fn enable(&self) {
+        *self.state.lock().unwrap() = Some(AdaptiveState::new());
+    }
+
+    /// How many downloads may run at once, unlimited until enabled.
+    pub fn limit(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(usize::MAX, |state| state.limit)
+    }
+
+    /// Records a finished download of `bytes`, adjusting the limit once a window of
+    /// downloads as large as the limit has finished.
+    pub fn record(&self, bytes: u64) {
+        let mut guard = self.state.lock().unwrap();
+
+        let state = match guard.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+
+        state.window_bytes += bytes;
+        state.window_downloads += 1;
+
+        if state.window_downloads < state.limit {
+            return;
+        }
+
+        let elapsed = state.window_started.elapsed().as_secs_f64().max(0.001);
+        let throughput = state.window_bytes as f64 / elapsed;
+
+        if throughput > state.last_throughput {
+            let limit = if state.slow_start {
+                state.limit * 2
+            } else {
+                state.limit + 1
+            };
+
+            state.set_limit(limit);
+        } else {
+            // more downloads stopped paying off, only probe carefully from here
+            state.slow_start = false;
+        }
+
+        state.last_throughput = throughput;
+        state.metrics.best_throughput = state.metrics.best_throughput.max(throughput);
+        state.reset_window();
+    }
+
+    /// Halves the limit after a 429 or a timeout.
+    pub fn back_off(&self) {
+        let mut guard = self.state.lock().unwrap();
+
+        if let Some(state) = guard.as_mut() {
+            let limit = state.limit / 2;
+
+            state.set_limit(limit);
+            state.slow_start = false;
+            state.metrics.back_offs += 1;
+
+            // downloads started at the old limit would skew the next window
+            state.last_throughput = 0.0;
+            state.reset_window();
+        }
+    }
+
+    /// What happened so far, `None` unless enabled.
+    pub fn metrics(&self) -> Option<ConcurrencyMetrics> {
+        self.state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|state| state.metrics)
+    }
+}
+
+/// How long to back off for, from a `Retry-After` header in seconds.
+///
+/// The http-date form isn't supported, it and a missing header wait for the default.
+pub fn retry_after(header: Option<&str>) -> Duration {
+    let seconds = header
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_RETRY_AFTER);
+
+    Duration::from_secs(seconds)
+}
@@ -227,3 +227,58 @@ impl Select {
         input.interact()
     }
 }
+
+/// Prompt that allows the user to pick any number of items from a list
+#[derive(Debug, StructOpt)]
+pub struct MultiSelect {
+    /// Message for the prompt
+    #[structopt(short, long)]
+    pub message: String,
+
+    /// Enables paging. Uses your terminal size
+    #[structopt(short, long)]
+    pub paged: bool,
+
+    /// Items that can be selected
+    pub items: Vec<String>,
+}
+
+impl MultiSelect {
+    /// Returns the indices of the picked items.
+    pub fn run(&self) -> Result<Vec<usize>> {
+        if self.items.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let theme = ColorfulTheme {
+            defaults_style: console::Style::new(),
+            prompt_style: console::Style::new().bold(),
+            prompt_prefix: console::style(String::from("?")).yellow().bright(),
+            prompt_suffix: console::style(String::from(">")).blue().dim(),
+            success_prefix: console::style(String::from("√")).green().bright(),
+            success_suffix: console::style(String::from("·")).blue().dim(),
+            error_prefix: console::style(String::from("❌")).bright().red(),
+            error_style: console::Style::new(),
+            hint_style: console::Style::new().bold(),
+            values_style: console::Style::new(),
+            active_item_style: console::Style::new().cyan(),
+            inactive_item_style: console::Style::new(),
+            active_item_prefix: console::style(String::from(">")).bright().green(),
+            inactive_item_prefix: console::style(String::from(" ")),
+            checked_item_prefix: console::style(String::from("[x]")).green(),
+            unchecked_item_prefix: console::style(String::from("[ ]")),
+            picked_item_prefix: console::style(String::from("")),
+            unpicked_item_prefix: console::style(String::from("")),
+            inline_selections: false,
+        };
+
+        let mut input = dialoguer::MultiSelect::with_theme(&theme);
+
+        input
+            .with_prompt(&self.message)
+            .paged(self.paged)
+            .items(&self.items);
+
+        input.interact()
+    }
+}
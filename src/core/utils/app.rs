@@ -1,16 +1,97 @@
 use crate::{
     commands::add::Package,
-    core::utils::{enable_ansi_support, errors::VoltError},
+    core::model::config::VoltConfig,
+    core::net::SOCKETS,
+    core::utils::{
+        constants::{
+            DEFAULT_MAX_TARBALL_SIZE, DEFAULT_REGISTRY, STALE_TMP_SECS, VOLT_CDN, VOLT_HOME,
+            VOLT_USER_AGENT,
+        },
+        enable_ansi_support,
+        errors::VoltError,
+        link_strategy::LinkStrategy,
+        linker::{NodeLinker, WindowsLinkType},
+        platform::Platform,
+    },
+    core::VERSION,
 };
 use clap::ArgMatches;
 use dirs::home_dir;
 use miette::Result;
-use sha1::Digest;
-use sha2::Sha512;
-use ssri::{Algorithm, Integrity};
-use std::{env, path::PathBuf};
+use ssri::Algorithm;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::hasher::digest;
+use super::npm::{dedupe_packages, parse_versions};
+use super::voltapi::package_scope;
+
+/// The `max-sockets` setting from `.voltrc`, `None` when it isn't set.
+fn max_sockets(config: &VoltConfig) -> Result<Option<usize>> {
+    match config.get("max-sockets") {
+        Some(value) => match value.trim().parse() {
+            Ok(limit) if limit > 0 => Ok(Some(limit)),
+            _ => Err(VoltError::InvalidMaxSockets {
+                value: value.to_string(),
+            })?,
+        },
+        None => Ok(None),
+    }
+}
+
+/// Creates the store directory if needed and checks that volt can write to it.
+fn check_store_writable(volt_dir: &Path) -> Result<()> {
+    let not_writable = |e| VoltError::StoreNotWritable {
+        source: e,
+        path: volt_dir.to_string_lossy().to_string(),
+    };
+
+    std::fs::create_dir_all(volt_dir).map_err(not_writable)?;
+
+    // a read-only mount or a directory owned by someone else only fails on write
+    let probe = volt_dir.join(format!(".volt-write-{}", std::process::id()));
+
+    std::fs::write(&probe, b"").map_err(not_writable)?;
+    std::fs::remove_file(&probe).ok();
+
+    Ok(())
+}
+
+/// Directory in `~/.volt` for files that are written before being moved into place.
+///
+/// It's on the same filesystem as the store, so moving out of it is a rename rather than
+/// the copy `std::env::temp_dir()` would need when the store is on another volume.
+fn tmp_dir(volt_dir: &Path) -> PathBuf {
+    volt_dir.join(".tmp")
+}
 
-use super::npm::parse_versions;
+/// Removes what earlier runs left in the temp directory, skipping anything recent enough
+/// to belong to an install still running.
+fn clean_tmp_dir(volt_dir: &Path) {
+    let stale = Duration::from_secs(STALE_TMP_SECS);
+    let entries = std::fs::read_dir(tmp_dir(volt_dir));
+
+    for entry in entries.into_iter().flatten().flatten() {
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+        if age.is_some_and(|age| age > stale) {
+            let path = entry.path();
+
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path).ok();
+            } else {
+                std::fs::remove_file(&path).ok();
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct App {
@@ -19,6 +100,7 @@ pub struct App {
     pub node_modules_dir: PathBuf,
     pub volt_dir: PathBuf,
     pub lock_file_path: PathBuf,
+    pub config: VoltConfig,
     pub args: ArgMatches,
 }
 
@@ -38,37 +120,55 @@ impl App {
         // node_modules/
         let node_modules_directory = current_directory.join("node_modules");
 
-        // Volt Global Directory: /username/.volt or C:\Users\username\.volt
-        let volt_dir = home_directory.join(".volt");
-
-        // Create volt directory if it doesn't exist
-        std::fs::create_dir_all(&volt_dir).map_err(VoltError::CreateDirError)?;
-
         // ./volt.lock
         let lock_file_path = current_directory.join("volt.lock");
 
+        // ./.voltrc merged over ~/.voltrc
+        let config = VoltConfig::load(&current_directory, &home_directory)?;
+
+        // Volt Global Directory: $VOLT_HOME, the store-dir config key, or /username/.volt
+        let volt_dir = env::var_os(VOLT_HOME)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| config.get("store-dir").map(PathBuf::from))
+            .map(|dir| current_directory.join(dir))
+            .unwrap_or_else(|| home_directory.join(".volt"));
+
+        check_store_writable(&volt_dir)?;
+        clean_tmp_dir(&volt_dir);
+
+        SOCKETS.set_limit(max_sockets(&config)?);
+
         Ok(App {
             current_dir: current_directory,
             home_dir: home_directory,
             node_modules_dir: node_modules_directory,
             volt_dir,
             lock_file_path,
+            config,
             args: args.to_owned(),
         })
     }
 
     /// Retrieve packages passed in
     pub fn get_packages(&self) -> Result<Vec<Package>> {
-        let mut args = self
+        let args = self
             .args
             .values_of("package-names")
             .unwrap()
             .map(|v| v.to_string())
             .collect::<Vec<String>>();
 
-        args.dedup();
+        dedupe_packages(parse_versions(&args)?)
+    }
+
+    /// Temp directory under the store for atomic writes, created if needed.
+    pub fn tmp_dir(&self) -> Result<PathBuf> {
+        let dir = tmp_dir(&self.volt_dir);
 
-        Ok(parse_versions(&args)?)
+        std::fs::create_dir_all(&dir).map_err(VoltError::CreateDirError)?;
+
+        Ok(dir)
     }
 
     /// Check if the app arguments contain the flags specified
@@ -76,7 +176,175 @@ impl App {
         self.args.is_present(flag)
     }
 
-    /// Calculate the hash of a tarball
+    /// Check if a boolean setting is enabled, either by its flag or by `key = true` in `.voltrc`.
+    pub fn has_setting(&self, flag: &str, key: &str) -> bool {
+        self.has_flag(flag)
+            || self
+                .config
+                .get(key)
+                .map(|value| value == "true")
+                .unwrap_or(false)
+    }
+
+    /// The largest tarball volt downloads or decompresses, in bytes, from `--max-tarball-size`
+    /// or `max-tarball-size` in `.voltrc`.
+    pub fn max_tarball_size(&self) -> Result<u64> {
+        let value = self.args.value_of("max-tarball-size").or_else(|| {
+            self.config
+                .get("max-tarball-size")
+                .map(|value| value.as_str())
+        });
+
+        match value {
+            Some(value) => match value.trim().parse() {
+                Ok(limit) if limit > 0 => Ok(limit),
+                _ => Err(VoltError::InvalidMaxTarballSize {
+                    value: value.to_string(),
+                })?,
+            },
+            None => Ok(DEFAULT_MAX_TARBALL_SIZE),
+        }
+    }
+
+    /// Whether tarballs may be fetched from plain http urls, from `--allow-insecure-tarballs`,
+    /// `allow-insecure-tarballs` in `.voltrc` or npm's `strict-ssl = false`.
+    pub fn allow_insecure_tarballs(&self) -> bool {
+        self.has_setting("allow-insecure-tarballs", "allow-insecure-tarballs")
+            || self
+                .config
+                .get("strict-ssl")
+                .map(|value| value == "false")
+                .unwrap_or(false)
+    }
+
+    /// How packages are materialized into node_modules, from `--node-linker` or `node-linker` in `.voltrc`.
+    pub fn node_linker(&self) -> Result<NodeLinker> {
+        let linker = self
+            .args
+            .value_of("node-linker")
+            .or_else(|| self.config.get("node-linker").map(|value| value.as_str()));
+
+        match linker {
+            Some(linker) => NodeLinker::parse(linker),
+            None => Ok(NodeLinker::Copy),
+        }
+    }
+
+    /// How the tree is laid out in node_modules, from `--link-strategy` or `link-strategy`
+    /// in `.voltrc`.
+    pub fn link_strategy(&self) -> Result<LinkStrategy> {
+        let strategy = self
+            .args
+            .value_of("link-strategy")
+            .or_else(|| self.config.get("link-strategy").map(|value| value.as_str()));
+
+        match strategy {
+            Some(strategy) => LinkStrategy::parse(strategy),
+            None => Ok(LinkStrategy::Hoisted),
+        }
+    }
+
+    /// How `volt link` links directories on windows, from `--windows-link-type` or
+    /// `windows-link-type` in `.voltrc`.
+    pub fn windows_link_type(&self) -> Result<WindowsLinkType> {
+        let link_type = self.args.value_of("windows-link-type").or_else(|| {
+            self.config
+                .get("windows-link-type")
+                .map(|value| value.as_str())
+        });
+
+        match link_type {
+            Some(link_type) => WindowsLinkType::parse(link_type),
+            None => Ok(WindowsLinkType::Auto),
+        }
+    }
+
+    /// The registry to fetch `package` from.
+    ///
+    /// `--registry` wins for every package, then `@scope:registry` and `registry` from `.voltrc`.
+    pub fn registry_for(&self, package: &str) -> String {
+        let scoped = package_scope(package)
+            .and_then(|scope| self.config.get(&format!("{}:registry", scope)));
+
+        self.args
+            .value_of("registry")
+            .map(String::from)
+            .or_else(|| scoped.cloned())
+            .or_else(|| self.config.get("registry").cloned())
+            .unwrap_or_else(|| DEFAULT_REGISTRY.to_string())
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// Where the pre-flattened dependency trees are fetched from, `volt-cdn` in `.voltrc` for
+    /// a mirror of the volt CDN.
+    pub fn volt_cdn(&self) -> String {
+        self.config
+            .get("volt-cdn")
+            .map(String::as_str)
+            .unwrap_or(VOLT_CDN)
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// Headers sent with every registry request.
+    ///
+    /// The user agent is `$VOLT_USER_AGENT`, then `user-agent` from `.voltrc`, then
+    /// `volt/<version> <os> <arch>`. Every `header.<name>` key in `.voltrc` is sent as well,
+    /// e.g. `"header.npm-auth-type" = "legacy"` for registries expecting npm's headers.
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        let user_agent = env::var(VOLT_USER_AGENT)
+            .ok()
+            .filter(|user_agent| !user_agent.is_empty())
+            .or_else(|| self.config.get("user-agent").cloned())
+            .unwrap_or_else(|| {
+                let platform = Platform::host();
+                format!("volt/{} {} {}", VERSION, platform.os, platform.cpu)
+            });
+
+        let mut headers = vec![(String::from("user-agent"), user_agent)];
+
+        headers.extend(self.config.list().into_iter().filter_map(|(key, value)| {
+            key.strip_prefix("header.")
+                .map(|name| name.to_lowercase())
+                .filter(|name| name != "user-agent")
+                .map(|name| (name, value))
+        }));
+
+        headers
+    }
+
+    /// Headers for a metadata request to `registry`: the [`request_headers`](App::request_headers),
+    /// plus the registry's auth token with `always-auth` for registries that refuse anonymous reads.
+    pub fn registry_headers(&self, registry: &str) -> Vec<(String, String)> {
+        let mut headers = self.request_headers();
+
+        if self.has_setting("always-auth", "always-auth") {
+            if let Some(token) = self.config.auth_token(&format!("{}/", registry)) {
+                headers.push((String::from("authorization"), format!("Bearer {}", token)));
+            }
+        }
+
+        headers
+    }
+
+    /// Routes a `dist.tarball` url through the configured registry when it points at the
+    /// default npm/yarn registry, leaving tarballs hosted anywhere else untouched.
+    pub fn tarball_url(&self, package: &str, tarball: &str) -> String {
+        let registry = self.registry_for(package);
+
+        for default in &["https://registry.npmjs.org", "https://registry.yarnpkg.com"] {
+            if let Some(path) = tarball.strip_prefix(default) {
+                if registry != DEFAULT_REGISTRY {
+                    return format!("{}{}", registry, path);
+                }
+            }
+        }
+
+        tarball.to_string()
+    }
+
+    /// Calculate the hash of a tarball, formatted as `<algorithm>-<hex digest>`
     ///
     /// ## Examples
     /// ```rs
@@ -85,39 +353,90 @@ impl App {
     /// ## Returns
     /// * Result<String>
     pub fn calc_hash(data: &bytes::Bytes, algorithm: Algorithm) -> Result<String> {
-        match algorithm {
-            Algorithm::Sha1 => {
-                let mut hasher = sha1::Sha1::new();
-                std::io::copy(&mut &**data, &mut hasher).map_err(VoltError::HasherCopyError)?;
-
-                let integrity: Integrity = format!(
-                    "sha1-{}",
-                    base64::encode(format!("{:x}", hasher.clone().finalize()))
-                )
-                .parse()
-                .map_err(|_| VoltError::HashParseError {
-                    hash: format!(
-                        "sha1-{}",
-                        base64::encode(format!("{:x}", hasher.clone().finalize()))
-                    ),
-                })?;
-
-                let hash = integrity
-                    .hashes
-                    .into_iter()
-                    .find(|h| h.algorithm == algorithm)
-                    .map(|h| Integrity { hashes: vec![h] })
-                    .map(|i| i.to_hex().1)
-                    .unwrap();
-
-                return Ok(format!("sha1-{}", hash));
-            }
-            Algorithm::Sha512 => {
-                let mut hasher = Sha512::new();
-                std::io::copy(&mut &**data, &mut hasher).map_err(VoltError::HasherCopyError)?;
-                return Ok(format!("sha512-{:x}", hasher.finalize()));
-            }
-            _ => Ok(String::new()),
+        let hash: String = digest(std::iter::once(&data[..]), algorithm)?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(format!("{}-{}", algorithm, hash))
+    }
+}
+
+#[cfg(test)]
+impl App {
+    /// An app for a project at `/project`, without touching the environment or the store.
+    pub fn for_tests(args: ArgMatches, config: VoltConfig) -> App {
+        Self::for_tests_in(Path::new("/"), args, config)
+    }
+
+    /// An app for a project at `<root>/project`, with its home directory at `<root>/home`.
+    pub fn for_tests_in(root: &Path, args: ArgMatches, config: VoltConfig) -> App {
+        App {
+            current_dir: root.join("project"),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("project").join("node_modules"),
+            volt_dir: root.join("home").join(".volt"),
+            lock_file_path: root.join("project").join("volt.lock"),
+            config,
+            args,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn app(args: &[&str], settings: &[(&str, &str)]) -> App {
+        let args = clap::App::new("volt")
+            .arg(Arg::new("registry").long("registry").takes_value(true))
+            .get_matches_from(std::iter::once("volt").chain(args.iter().copied()));
+
+        let mut config = VoltConfig::default();
+
+        for (key, value) in settings {
+            config.set(key, value, false);
         }
+
+        App::for_tests(args, config)
+    }
+
+    #[test]
+    fn the_registry_flag_wins_over_config() {
+        let settings = [
+            ("registry", "https://mirror.corp.com"),
+            ("@corp:registry", "https://npm.corp.com/"),
+        ];
+
+        let configured = app(&[], &settings);
+
+        assert_eq!(configured.registry_for("react"), "https://mirror.corp.com");
+        assert_eq!(configured.registry_for("@corp/ui"), "https://npm.corp.com");
+
+        let flagged = app(&["--registry", "https://staging.corp.com/"], &settings);
+
+        assert_eq!(flagged.registry_for("react"), "https://staging.corp.com");
+        assert_eq!(flagged.registry_for("@corp/ui"), "https://staging.corp.com");
+    }
+
+    #[test]
+    fn tarballs_follow_the_registry_override() {
+        let tarball = "https://registry.npmjs.org/react/-/react-17.0.2.tgz";
+
+        assert_eq!(app(&[], &[]).tarball_url("react", tarball), tarball);
+
+        assert_eq!(
+            app(&["--registry", "https://staging.corp.com"], &[]).tarball_url("react", tarball),
+            "https://staging.corp.com/react/-/react-17.0.2.tgz"
+        );
+
+        // hosted somewhere other than the default registry, left alone
+        let cdn = "https://cdn.corp.com/react/-/react-17.0.2.tgz";
+
+        assert_eq!(
+            app(&["--registry", "https://staging.corp.com"], &[]).tarball_url("react", cdn),
+            cdn
+        );
     }
 }
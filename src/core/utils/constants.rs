@@ -1,2 +1,19 @@
 pub static PROGRESS_CHARS: &str = "=> ";
 pub static MAX_RETRIES: u8 = 4;
+pub static MAX_DEPTH: usize = 256;
+pub static NODE_DIST_URL: &str = "https://nodejs.org/dist";
+pub static MAX_REDIRECTS: usize = 5;
+pub static POOL_IDLE_TIMEOUT: u64 = 90;
+pub static VOLT_CDN: &str = "https://cdn.jsdelivr.net/npm/@voltpkg";
+pub static DEFAULT_REGISTRY: &str = "https://registry.yarnpkg.com";
+pub static LAYOUT_VERSION: u32 = 1;
+pub static LOW_MEMORY_CONCURRENCY: usize = 8;
+pub static VOLT_HOME: &str = "VOLT_HOME";
+pub static VOLT_USER_AGENT: &str = "VOLT_USER_AGENT";
+pub static DEFAULT_RETRY_AFTER: u64 = 5;
+pub static THROTTLE_JITTER: u64 = 1000;
+pub static INITIAL_CONCURRENCY: usize = 4;
+pub static MAX_ADAPTIVE_CONCURRENCY: usize = 256;
+pub static STORE_STAGING_PREFIX: &str = ".volt-staging-";
+pub static STALE_TMP_SECS: u64 = 24 * 60 * 60;
+pub static DEFAULT_MAX_TARBALL_SIZE: u64 = 512 * 1024 * 1024;
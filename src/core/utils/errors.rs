@@ -55,37 +55,30 @@ pub enum VoltError {
     #[diagnostic(code(volt::integrity::parse))]
     HashParseError { hash: String },
 
-    #[error("failed to copy bytes to hasher.")]
-    #[diagnostic(code(volt::hasher::copy))]
-    HasherCopyError(#[source] std::io::Error),
-
     #[error("failed to verify tarball checksum")]
     #[diagnostic(code(volt::integrity::verify))]
     ChecksumVerificationError,
 
-    #[error("failed to convert integrity into hex")]
-    #[diagnostic(code(volt::integrity::convert))]
-    IntegrityConversionError,
-
     #[error("failed to deserialize slice to `JSONVoltResponse`")]
     #[diagnostic(code(volt::integrity::convert))]
     DeserializeError,
 
-    // #[error("failed to build request client")]
-    // #[diagnostic(code(volt::network::builder))]
-    // RequestBuilderError(#[source] isahc::http::Error),
+    #[error("failed to build request client")]
+    #[diagnostic(code(volt::network::builder))]
+    RequestBuilderError(#[source] isahc::http::Error),
 
-    // #[error("failed to build recieve response text")]
-    // #[diagnostic(code(volt::io::rec::text))]
-    // IoTextRecError(#[source] std::io::Error),
+    #[error("failed to build recieve response text")]
+    #[diagnostic(code(volt::io::rec::text))]
+    IoTextRecError(#[source] std::io::Error),
 
     // #[error("failed to find a hash that matches the specified version requirement: {version}")]
     // #[diagnostic(code(volt::io::rec::text))]
     // HashLookupError { version: String },
 
-    // #[error("failed to find a version that matches the specified version requirement for {name}")]
-    // #[diagnostic(code(volt::io::rec::text))]
-    // VersionLookupError { name: String },
+    #[error("failed to find a version that matches the specified version requirement for {name}")]
+    #[diagnostic(code(volt::registry::version_lookup))]
+    VersionLookupError { name: String },
+
     #[error("failed to read `{name}`")]
     #[diagnostic(code(volt::io::file::read))]
     ReadFileError {
@@ -99,6 +92,424 @@ pub enum VoltError {
         source: std::io::Error,
         name: String,
     },
+
+    #[error("failed to parse config file `{path}`")]
+    #[diagnostic(code(volt::config::parse))]
+    ConfigParseError {
+        source: toml::de::Error,
+        path: String,
+    },
+
+    #[error("failed to serialize config file `{path}`")]
+    #[diagnostic(code(volt::config::serialize))]
+    ConfigSerializeError { path: String },
+
+    #[error("dependency cycle detected: {path}")]
+    #[diagnostic(code(volt::resolve::cycle))]
+    DependencyCycle { path: String },
+
+    #[error("dependency tree is deeper than the maximum depth of {max_depth}: {path}")]
+    #[diagnostic(
+        code(volt::resolve::max_depth),
+        help("raise the limit with `volt config set max-depth <depth>`")
+    )]
+    DependencyTooDeep { path: String, max_depth: usize },
+
+    #[error("failed to remove `{name}`")]
+    #[diagnostic(code(volt::io::remove_dir))]
+    RemoveDirError {
+        source: std::io::Error,
+        name: String,
+    },
+
+    #[error("invalid platform `{platform}`")]
+    #[diagnostic(
+        code(volt::platform::parse),
+        help("platforms are written as <os>-<arch>, e.g. linux-x64 or darwin-arm64")
+    )]
+    InvalidPlatform { platform: String },
+
+    #[error("found {count} vulnerabilities at or above the `{level}` audit level")]
+    #[diagnostic(code(volt::audit::failed))]
+    AuditFailed { count: usize, level: String },
+
+    #[error("invalid audit level `{level}`")]
+    #[diagnostic(
+        code(volt::audit::level),
+        help("valid levels are info, low, moderate, high and critical")
+    )]
+    InvalidAuditLevel { level: String },
+
+    #[error("`{script}` failed for {package} (exit code {code})")]
+    #[diagnostic(code(volt::scripts::lifecycle))]
+    LifecycleScriptError {
+        package: String,
+        script: String,
+        code: String,
+    },
+
+    #[error("{name} has no `{tag}` dist-tag (available tags: {available})")]
+    #[diagnostic(
+        code(volt::registry::tag_not_found),
+        help("packages added without a version follow `latest`, which isn't always the highest version; use --newest for that")
+    )]
+    TagNotFound {
+        name: String,
+        tag: String,
+        available: String,
+    },
+
+    #[error("invalid node linker `{linker}`")]
+    #[diagnostic(
+        code(volt::config::node_linker),
+        help("valid node linkers are copy and hardlink")
+    )]
+    InvalidNodeLinker { linker: String },
+
+    #[error("invalid windows link type `{link_type}`")]
+    #[diagnostic(
+        code(volt::config::windows_link_type),
+        help("valid windows link types are junction, symlink and auto")
+    )]
+    InvalidWindowsLinkType { link_type: String },
+
+    #[error("failed to link `{name}` into node_modules")]
+    #[diagnostic(code(volt::io::link))]
+    LinkError {
+        source: std::io::Error,
+        name: String,
+    },
+
+    #[error("{package} has no `{missing_field}` in the registry")]
+    #[diagnostic(code(volt::registry::malformed_entry))]
+    MalformedRegistryEntry {
+        package: String,
+        missing_field: String,
+    },
+
+    #[error("{name} is not linked")]
+    #[diagnostic(
+        code(volt::link::not_found),
+        help("run `volt link` in the package's directory first")
+    )]
+    PackageNotLinked { name: String },
+
+    #[error("failed to download node {version}")]
+    #[diagnostic(code(volt::node::download))]
+    NodeDownloadError {
+        source: reqwest::Error,
+        version: String,
+    },
+
+    #[error("no node release matches `{version}`")]
+    #[diagnostic(code(volt::node::version))]
+    NodeVersionNotFound { version: String },
+
+    #[error("no node version is pinned for this project")]
+    #[diagnostic(
+        code(volt::node::not_pinned),
+        help("pass a version to `volt use` or add a .nvmrc file")
+    )]
+    NodeVersionNotPinned,
+
+    #[error("missing script: `{name}`")]
+    #[diagnostic(code(volt::scripts::not_found), help("{}", hint))]
+    ScriptNotFound { name: String, hint: String },
+
+    #[error("failed to extract node {version}")]
+    #[diagnostic(code(volt::node::extract))]
+    NodeExtractError {
+        source: std::io::Error,
+        version: String,
+    },
+
+    #[error("failed to lock `{path}`")]
+    #[diagnostic(code(volt::io::lock))]
+    LockError {
+        source: std::io::Error,
+        path: String,
+    },
+
+    #[error("installation cancelled")]
+    #[diagnostic(code(volt::interrupted))]
+    Interrupted,
+
+    #[error("node_modules uses an outdated layout (version {found}, expected {expected})")]
+    #[diagnostic(
+        code(volt::layout::outdated),
+        help("remove node_modules and run `volt install` again")
+    )]
+    OutdatedLayout { found: u32, expected: u32 },
+
+    #[error("node_modules uses a newer layout (version {found}, from volt {volt_version})")]
+    #[diagnostic(
+        code(volt::layout::newer),
+        help("upgrade volt, or remove node_modules and run `volt install` again")
+    )]
+    NewerLayout { found: u32, volt_version: String },
+
+    #[error("failed to download the tarball for {name}")]
+    #[diagnostic(code(volt::registry::tarball))]
+    TarballDownloadError {
+        source: reqwest::Error,
+        name: String,
+    },
+
+    #[error("failed to read the prompt")]
+    #[diagnostic(code(volt::io::prompt))]
+    PromptError(#[source] std::io::Error),
+
+    #[error("{name} was requested more than once, as `{first}` and `{second}`")]
+    #[diagnostic(
+        code(volt::args::conflicting_specs),
+        help("pass {} once with the version you want", name)
+    )]
+    ConflictingSpecs {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("unsupported integrity algorithm {algorithm}")]
+    #[diagnostic(code(volt::integrity::unsupported_algorithm))]
+    UnsupportedHashAlgorithm { algorithm: String },
+
+    #[error("the volt store at {path} is not writable")]
+    #[diagnostic(
+        code(volt::io::store_not_writable),
+        help("point VOLT_HOME or the store-dir config key at a writable directory")
+    )]
+    StoreNotWritable {
+        source: std::io::Error,
+        path: String,
+    },
+
+    #[error("failed to import {file}: {reason}")]
+    #[diagnostic(code(volt::import))]
+    ImportError { file: String, reason: String },
+
+    #[error("no lockfile to import")]
+    #[diagnostic(
+        code(volt::import::not_found),
+        help("run `volt import` next to a package-lock.json")
+    )]
+    NoLockfileToImport,
+
+    #[error("failed to save volt.lock")]
+    #[diagnostic(code(volt::io::lock_file::save))]
+    SaveLockFileError(#[source] crate::core::model::lock_file::LockFileError),
+
+    #[error("the tarball for {package} is neither gzip nor tar")]
+    #[diagnostic(code(volt::tarball::format))]
+    UnsupportedTarballFormat { package: String },
+
+    #[error("the registry sent {package} with an unsupported content encoding `{encoding}`")]
+    #[diagnostic(code(volt::tarball::encoding))]
+    UnsupportedContentEncoding { encoding: String, package: String },
+
+    #[error("failed to decode the tarball for {package}")]
+    #[diagnostic(code(volt::tarball::decode))]
+    ContentDecodeError {
+        source: std::io::Error,
+        package: String,
+    },
+
+    #[error("the tarball for {package} is truncated or corrupt")]
+    #[diagnostic(
+        code(volt::tarball::corrupt),
+        help("run the install again, the download may have been cut short")
+    )]
+    CorruptTarball { package: String },
+
+    #[error("no volt.lock in this project")]
+    #[diagnostic(
+        code(volt::lock_file::not_found),
+        help("run `volt install` to create one")
+    )]
+    LockFileNotFound,
+
+    #[error("node_modules differs from volt.lock in {count} places")]
+    #[diagnostic(
+        code(volt::install::check),
+        help("run `volt install` to bring node_modules up to date")
+    )]
+    NodeModulesOutOfDate { count: usize },
+
+    #[error("{count} packages failed to install")]
+    #[diagnostic(
+        code(volt::install::failed),
+        help("the failures above are grouped by cause")
+    )]
+    InstallFailed { count: usize },
+
+    #[error("package.json has no workspaces")]
+    #[diagnostic(
+        code(volt::workspace::not_a_workspace),
+        help("run this next to the package.json with a `workspaces` field")
+    )]
+    NotAWorkspace,
+
+    #[error("no workspace member named `{name}`")]
+    #[diagnostic(
+        code(volt::workspace::member_not_found),
+        help("workspace members: {}", members)
+    )]
+    WorkspaceMemberNotFound { name: String, members: String },
+
+    #[error("`{version}` is not a valid version")]
+    #[diagnostic(
+        code(volt::why_not::invalid_version),
+        help("pass an exact version, e.g. `volt why-not react@16.14.0`")
+    )]
+    InvalidVersion { version: String },
+
+    #[error("invalid max-sockets `{value}`")]
+    #[diagnostic(
+        code(volt::config::max_sockets),
+        help("max-sockets must be a whole number of connections greater than 0")
+    )]
+    InvalidMaxSockets { value: String },
+
+    #[error("invalid package name `{name}`")]
+    #[diagnostic(
+        code(volt::args::package_name),
+        help("scoped packages are written @scope/name, e.g. @types/node")
+    )]
+    InvalidPackageName { name: String },
+
+    #[error("{dependent} requires {dependency}, which isn't available on this platform")]
+    #[diagnostic(
+        code(volt::resolve::missing_dependency),
+        help("installing it without the dependency would fail when it's required at runtime")
+    )]
+    MissingDependency {
+        dependent: String,
+        dependency: String,
+    },
+
+    #[error("this project requires node {required}, but node {current} is installed")]
+    #[diagnostic(
+        code(volt::engine::unsupported),
+        help("engine-strict is set, switch node versions with volt use or unset engine-strict")
+    )]
+    UnsupportedEngine { required: String, current: String },
+
+    #[error("invalid depth `{depth}`")]
+    #[diagnostic(
+        code(volt::args::depth),
+        help("the depth must be a whole number, e.g. --depth 1")
+    )]
+    InvalidDepth { depth: String },
+
+    #[error("{path} has no package.json with a name and version")]
+    #[diagnostic(
+        code(volt::store::manifest),
+        help("only package tarballs like the ones npm pack creates can be added to the store")
+    )]
+    MissingTarballManifest { path: String },
+
+    #[error("conflicting peer dependencies:\n  {conflicts}")]
+    #[diagnostic(
+        code(volt::resolve::peer_conflict),
+        help("install versions that satisfy them, or pass --legacy-peer-deps to install anyway")
+    )]
+    PeerDependencyConflict { conflicts: String },
+
+    #[error("invalid max-tarball-size `{value}`")]
+    #[diagnostic(
+        code(volt::config::max_tarball_size),
+        help("max-tarball-size must be a whole number of bytes greater than 0")
+    )]
+    InvalidMaxTarballSize { value: String },
+
+    #[error("the tarball for {package} is {size} bytes, over the limit of {limit} bytes")]
+    #[diagnostic(
+        code(volt::tarball::too_large),
+        help("raise it with --max-tarball-size or max-tarball-size in .voltrc if you trust the package")
+    )]
+    TarballTooLarge {
+        package: String,
+        size: u64,
+        limit: u64,
+    },
+
+    #[error("invalid link strategy `{strategy}`")]
+    #[diagnostic(
+        code(volt::config::link_strategy),
+        help("valid link strategies are hoisted, isolated and pnpm-strict")
+    )]
+    InvalidLinkStrategy { strategy: String },
+
+    #[error("node_modules was installed with the {found} link strategy, not {expected}")]
+    #[diagnostic(
+        code(volt::layout::link_strategy),
+        help(
+            "remove node_modules to switch, or install with --link-strategy {}",
+            found
+        )
+    )]
+    LinkStrategyChanged { found: String, expected: String },
+
+    #[error("{package}@{requested} resolved to {resolved}, outside the requested range")]
+    #[diagnostic(
+        code(volt::resolve::unsatisfied_range),
+        help("this is a registry or resolver bug, nothing was installed")
+    )]
+    UnsatisfiedRange {
+        package: String,
+        requested: String,
+        resolved: String,
+    },
+
+    #[error("failed to fetch the signing keys of {registry}")]
+    #[diagnostic(code(volt::signatures::keys))]
+    RegistryKeysError { registry: String },
+
+    #[error("refusing to fetch the signing keys of {registry} over plain http")]
+    #[diagnostic(
+        code(volt::signatures::insecure),
+        help("pass --allow-insecure-tarballs or set strict-ssl = false to trust this registry")
+    )]
+    InsecureRegistryKeys { registry: String },
+
+    #[error("{package} is signed with key {keyid}, which expired at {expires}")]
+    #[diagnostic(
+        code(volt::signatures::expired),
+        help("check the registry or mirror the package came from")
+    )]
+    ExpiredRegistryKey {
+        package: String,
+        keyid: String,
+        expires: String,
+    },
+
+    #[error("{package} has no registry signature")]
+    #[diagnostic(
+        code(volt::signatures::missing),
+        help("install without --verify-signatures to accept unsigned packages")
+    )]
+    MissingSignature { package: String },
+
+    #[error("the registry signature of {package} (key {keyid}) doesn't match its integrity")]
+    #[diagnostic(
+        code(volt::signatures::invalid),
+        help("the package may have been tampered with, check the registry or mirror it came from")
+    )]
+    InvalidSignature { package: String, keyid: String },
+
+    #[error("the registry serves {package} over plain http ({url})")]
+    #[diagnostic(
+        code(volt::tarball::insecure),
+        help("pass --allow-insecure-tarballs or set strict-ssl = false if this mirror is trusted")
+    )]
+    InsecureTarballUrl { package: String, url: String },
+
+    #[error("failed to read volt.lock")]
+    #[diagnostic(
+        code(volt::lock_file::read),
+        help("fix or delete volt.lock, then run `volt install` to write it again")
+    )]
+    ReadLockFileError(#[source] crate::core::model::lock_file::LockFileError),
     // #[error("an unknown error occured.")]
     // #[diagnostic(code(volt::unknown))]
     // UnknownError,
@@ -0,0 +1,230 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Events reported while resolving and installing packages.
+//!
+//! The install code reports what it's doing to a [`Reporter`] instead of drawing progress
+//! bars itself. The CLI renders events with [`TerminalReporter`] (or [`NdjsonReporter`] for
+//! `--progress ndjson`), and embedders (editors, GUIs) can pass their own reporter to
+//! `install_packages_with` to drive their own UI.
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::constants::PROGRESS_CHARS;
+use super::print_elapsed;
+
+/// Something that happened during an install, reported in the order it happened.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum InstallEvent {
+    /// Fetching the dependency trees of this many requested packages.
+    ResolutionStarted {
+        packages: usize,
+    },
+    /// The dependency tree of one requested package arrived.
+    PackageResolved,
+    /// The tree is resolved and flattened to this many packages.
+    ResolutionFinished {
+        packages: usize,
+        elapsed: f32,
+    },
+    /// Installing this many packages.
+    InstallStarted {
+        packages: usize,
+    },
+    /// A package isn't in the store and is being downloaded.
+    Downloading {
+        name: String,
+        version: String,
+    },
+    /// A downloaded package was extracted into the store.
+    Extracted {
+        name: String,
+        version: String,
+    },
+    /// A package is in place in every `node_modules` it goes in.
+    Linked {
+        name: String,
+        version: String,
+    },
+    /// A package failed to install, the error is returned once the install finishes.
+    PackageFailed {
+        name: String,
+        version: String,
+    },
+    /// Worth knowing, e.g. packages skipped on this platform.
+    Info {
+        message: String,
+    },
+    /// Something may be wrong, e.g. an unmet peer dependency.
+    Warning {
+        message: String,
+    },
+    InstallFinished,
+    InstallFailed,
+    InstallCancelled,
+}
+
+/// Receives the events of an install. Reporters are called from concurrent installs, so
+/// they must be cheap and thread safe.
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: InstallEvent);
+}
+
+/// Ignores every event, for output that must stay machine readable (`volt resolve`).
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {
+    fn report(&self, _event: InstallEvent) {}
+}
+
+/// Writes each event to stderr as a line of json as it happens, for CI dashboards and build
+/// tools rendering their own live progress, e.g. `{"event":"linked","name":"react","version":"17.0.2"}`.
+pub struct NdjsonReporter;
+
+impl Reporter for NdjsonReporter {
+    fn report(&self, event: InstallEvent) {
+        if let Ok(line) = serde_json::to_string(&event) {
+            // one write per line so concurrent installs can't interleave them
+            writeln!(std::io::stderr().lock(), "{}", line).ok();
+        }
+    }
+}
+
+/// Records the order packages are fetched, extracted, linked or fail in to a file for
+/// `--record-order`, passing every event on to another reporter.
+///
+/// Each step is a line like `fetch react@17.0.2`, written as it happens so the file shows
+/// how far an install got even when it crashes or is interrupted.
+pub struct OrderRecorder<'a> {
+    inner: &'a dyn Reporter,
+    file: Mutex<File>,
+}
+
+impl<'a> OrderRecorder<'a> {
+    pub fn create(path: &Path, inner: &'a dyn Reporter) -> std::io::Result<Self> {
+        Ok(Self {
+            inner,
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+}
+
+impl Reporter for OrderRecorder<'_> {
+    fn report(&self, event: InstallEvent) {
+        let step = match &event {
+            InstallEvent::Downloading { name, version } => Some(("fetch", name, version)),
+            InstallEvent::Extracted { name, version } => Some(("extract", name, version)),
+            InstallEvent::Linked { name, version } => Some(("link", name, version)),
+            InstallEvent::PackageFailed { name, version } => Some(("fail", name, version)),
+            _ => None,
+        };
+
+        if let Some((step, name, version)) = step {
+            writeln!(self.file.lock().unwrap(), "{} {}@{}", step, name, version).ok();
+        }
+
+        self.inner.report(event);
+    }
+}
+
+/// The reporter for a `--progress` style, the progress bars unless it's `ndjson`.
+pub fn reporter_for(progress: Option<&str>) -> Box<dyn Reporter> {
+    match progress {
+        Some("ndjson") => Box::new(NdjsonReporter),
+        _ => Box::new(TerminalReporter::new()),
+    }
+}
+
+/// Renders events as the CLI's progress bars.
+pub struct TerminalReporter {
+    bar: Mutex<ProgressBar>,
+}
+
+impl TerminalReporter {
+    pub fn new() -> Self {
+        Self {
+            bar: Mutex::new(ProgressBar::hidden()),
+        }
+    }
+
+    fn styled_bar(length: usize, label: &str) -> ProgressBar {
+        let bar = ProgressBar::new(length as u64);
+
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .progress_chars(PROGRESS_CHARS)
+                .template(&format!(
+                    "{} [{{bar:40.magenta/blue}}] {{msg:.blue}}",
+                    label.bright_blue()
+                )),
+        );
+
+        bar
+    }
+}
+
+impl Default for TerminalReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reporter for TerminalReporter {
+    fn report(&self, event: InstallEvent) {
+        let mut bar = self.bar.lock().unwrap();
+
+        match event {
+            InstallEvent::ResolutionStarted { packages } => {
+                *bar = Self::styled_bar(packages, "Resolving Dependencies");
+            }
+            InstallEvent::PackageResolved => bar.inc(1),
+            InstallEvent::ResolutionFinished { packages, elapsed } => {
+                bar.finish_with_message("[OK]".bright_green().to_string());
+                print_elapsed(packages, elapsed);
+            }
+            InstallEvent::InstallStarted { packages } => {
+                *bar = Self::styled_bar(packages, "Installing Packages");
+            }
+            InstallEvent::Downloading { .. } | InstallEvent::Extracted { .. } => {}
+            InstallEvent::Linked { .. } | InstallEvent::PackageFailed { .. } => bar.inc(1),
+            InstallEvent::Info { message } => {
+                bar.println(format!("{}: {}", "info".bright_purple(), message));
+            }
+            InstallEvent::Warning { message } => {
+                bar.println(format!(
+                    "{}{} {}",
+                    " warn ".black().bright_yellow(),
+                    ":",
+                    message
+                ));
+            }
+            InstallEvent::InstallFinished => bar.finish(),
+            InstallEvent::InstallFailed => {
+                bar.abandon_with_message("failed".bright_red().to_string());
+            }
+            InstallEvent::InstallCancelled => {
+                bar.abandon_with_message("cancelled".bright_red().to_string());
+            }
+        }
+    }
+}
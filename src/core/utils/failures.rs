@@ -0,0 +1,121 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Collect the packages that failed to install and report them grouped by cause.
+
+use colored::Colorize;
+use miette::Report;
+use std::error::Error;
+
+use super::errors::VoltError;
+
+/// Why a package failed to install, used to group the failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailureCause {
+    Network,
+    Checksum,
+    Extraction,
+    Platform,
+    Other,
+}
+
+impl FailureCause {
+    /// Classifies an install error by the `VoltError` behind it.
+    pub fn of(error: &Report) -> Self {
+        match error.downcast_ref::<VoltError>() {
+            Some(VoltError::NetworkError(_))
+            | Some(VoltError::TarballDownloadError { .. })
+            | Some(VoltError::NetworkUnknownError { .. })
+            | Some(VoltError::TooManyRequests { .. })
+            | Some(VoltError::BadRequest { .. })
+            | Some(VoltError::PackageNotFound { .. }) => FailureCause::Network,
+            Some(VoltError::ChecksumVerificationError)
+            | Some(VoltError::HashParseError { .. })
+            | Some(VoltError::UnsupportedHashAlgorithm { .. }) => FailureCause::Checksum,
+            Some(VoltError::CorruptTarball { .. })
+            | Some(VoltError::UnsupportedTarballFormat { .. })
+            | Some(VoltError::UnsupportedContentEncoding { .. })
+            | Some(VoltError::ContentDecodeError { .. })
+            | Some(VoltError::CreateDirError(_))
+            | Some(VoltError::WriteFileError { .. })
+            | Some(VoltError::LinkError { .. }) => FailureCause::Extraction,
+            Some(VoltError::InvalidPlatform { .. }) => FailureCause::Platform,
+            _ => FailureCause::Other,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FailureCause::Network => "network",
+            FailureCause::Checksum => "checksum",
+            FailureCause::Extraction => "extraction",
+            FailureCause::Platform => "platform",
+            FailureCause::Other => "other",
+        }
+    }
+}
+
+/// Packages that failed during an install, so one failure doesn't hide the rest.
+#[derive(Default)]
+pub struct InstallFailures {
+    failures: Vec<(FailureCause, String, Report)>,
+}
+
+impl InstallFailures {
+    pub fn push(&mut self, package: String, error: Report) {
+        self.failures
+            .push((FailureCause::of(&error), package, error));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.failures.len()
+    }
+
+    /// Prints the failures grouped by cause, with the packages affected under each.
+    pub fn print(&mut self) {
+        self.failures
+            .sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+        let mut current: Option<FailureCause> = None;
+
+        for (cause, package, error) in self.failures.iter() {
+            if current != Some(*cause) {
+                let count = self.failures.iter().filter(|f| f.0 == *cause).count();
+
+                println!(
+                    "\n{}",
+                    format!("{} ({})", cause.label(), count).bright_red().bold()
+                );
+
+                current = Some(*cause);
+            }
+
+            // the underlying io or http error says what actually went wrong
+            let reason = match error.downcast_ref::<VoltError>().and_then(|e| e.source()) {
+                Some(source) => format!("{}: {}", error, source),
+                None => error.to_string(),
+            };
+
+            println!("  {} {}", package.bright_cyan(), reason.bright_black());
+        }
+
+        println!();
+    }
+}
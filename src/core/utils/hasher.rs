@@ -0,0 +1,127 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Integrity hashing, the one place that maps an algorithm to its implementation.
+
+use miette::Result;
+use sha1::Digest;
+use ssri::{Algorithm, Integrity};
+
+use super::errors::VoltError;
+
+/// Incrementally computes the digest of some data for one integrity algorithm.
+pub trait Hasher: Send {
+    /// Feeds the next chunk of data into the hash.
+    fn update(&mut self, data: &[u8]);
+
+    /// Finishes hashing and returns the raw digest.
+    fn finish(self: Box<Self>) -> Vec<u8>;
+}
+
+impl<D: Digest + Send> Hasher for D {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        Digest::finalize(*self).to_vec()
+    }
+}
+
+/// Creates a hasher for an integrity algorithm.
+///
+/// Swapping an implementation (e.g. for a faster sha512) only needs a change here.
+pub fn hasher_for(algorithm: Algorithm) -> Result<Box<dyn Hasher>> {
+    match algorithm {
+        Algorithm::Sha1 => Ok(Box::new(sha1::Sha1::new())),
+        Algorithm::Sha256 => Ok(Box::new(sha2::Sha256::new())),
+        Algorithm::Sha512 => Ok(Box::new(sha2::Sha512::new())),
+        algorithm => Err(VoltError::UnsupportedHashAlgorithm {
+            algorithm: algorithm.to_string(),
+        })?,
+    }
+}
+
+/// Hashes data chunk by chunk, returning the raw digest.
+pub fn digest<I, C>(chunks: I, algorithm: Algorithm) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = C>,
+    C: AsRef<[u8]>,
+{
+    let mut hasher = hasher_for(algorithm)?;
+
+    for chunk in chunks {
+        hasher.update(chunk.as_ref());
+    }
+
+    Ok(hasher.finish())
+}
+
+/// Parses an SRI integrity string, e.g. `sha512-...` or several space separated hashes.
+pub fn parse_integrity(integrity: &str) -> Result<Integrity> {
+    Ok(integrity.parse().map_err(|_| VoltError::HashParseError {
+        hash: integrity.to_string(),
+    })?)
+}
+
+/// Converts a legacy hex `shasum` (always sha1) into an integrity.
+pub fn integrity_from_shasum(shasum: &str) -> Result<Integrity> {
+    let invalid = || VoltError::HashParseError {
+        hash: shasum.to_string(),
+    };
+
+    if shasum.len() != 40 || !shasum.is_ascii() {
+        return Err(invalid().into());
+    }
+
+    let digest = (0..shasum.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&shasum[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| invalid())?;
+
+    parse_integrity(&format!("sha1-{}", base64::encode(digest)))
+}
+
+/// Checks data against an integrity using the strongest algorithm it contains.
+pub fn check_integrity(integrity: &Integrity, data: &[u8]) -> Result<Algorithm> {
+    let algorithm = integrity.pick_algorithm();
+    let actual = base64::encode(digest(std::iter::once(data), algorithm)?);
+
+    if integrity
+        .hashes
+        .iter()
+        .any(|hash| hash.algorithm == algorithm && hash.digest == actual)
+    {
+        Ok(algorithm)
+    } else {
+        Err(VoltError::ChecksumVerificationError)?
+    }
+}
+
+/// Computes the SRI integrity of some data, e.g. `sha512-<base64 digest>`.
+pub fn integrity_of(data: &[u8], algorithm: Algorithm) -> Result<Integrity> {
+    let digest = base64::encode(digest(std::iter::once(data), algorithm)?);
+
+    parse_integrity(&format!("{}-{}", algorithm, digest))
+}
+
+/// Checks whether sha1 is the strongest hash an integrity string has.
+pub fn is_sha1_only(integrity: &str) -> bool {
+    parse_integrity(integrity)
+        .map(|integrity| integrity.pick_algorithm() == Algorithm::Sha1)
+        .unwrap_or(false)
+}
@@ -0,0 +1,72 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use colored::Colorize;
+use fs2::FileExt;
+use miette::Result;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+use super::errors::VoltError;
+
+/// An advisory lock held for the duration of an install, so a second volt process
+/// waits instead of writing to the same directory at the same time.
+///
+/// The lock is released when the guard is dropped. If volt panics or is killed with
+/// Ctrl-C, the operating system releases it when the process exits.
+pub struct InstallLock {
+    file: File,
+}
+
+impl InstallLock {
+    /// Locks `path` (creating it if needed), waiting for any other volt process holding it.
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let lock_error = |e| VoltError::LockError {
+            source: e,
+            path: path.to_string_lossy().to_string(),
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(VoltError::CreateDirError)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(lock_error)?;
+
+        if file.try_lock_exclusive().is_err() {
+            println!(
+                "{}: waiting for another volt process to finish ({})",
+                "info".bright_purple(),
+                path.display()
+            );
+
+            file.lock_exclusive().map_err(lock_error)?;
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        self.file.unlock().ok();
+    }
+}
@@ -0,0 +1,138 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Json printed for tooling by `--json` flags.
+
+use crate::core::utils::errors::VoltError;
+
+use miette::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Version of the `--json` output format, bumped when a field is renamed, removed or changes
+/// meaning. Added fields don't bump it.
+pub static SCHEMA_VERSION: u32 = 1;
+
+/// Serializes `output` for a `--json` flag: a `schemaVersion`, then the fields of `output`.
+///
+/// The keys of every object are sorted, so the same tree always prints the same bytes however
+/// the maps it was built from iterate.
+pub fn to_json<T: Serialize>(output: &T) -> Result<String> {
+    let value = serde_json::to_value(output).map_err(|_| VoltError::DeserializeError)?;
+
+    let mut object = Map::new();
+    object.insert(String::from("schemaVersion"), Value::from(SCHEMA_VERSION));
+
+    match sorted(value) {
+        Value::Object(fields) => object.extend(fields),
+        value => {
+            object.insert(String::from("data"), value);
+        }
+    }
+
+    Ok(serde_json::to_string_pretty(&Value::Object(object))
+        .map_err(|_| VoltError::DeserializeError)?)
+}
+
+/// `value` with the keys of every object in it sorted.
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<(String, Value)> = fields.into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sorted).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Node {
+        version: String,
+        dependencies: HashMap<String, Node>,
+    }
+
+    fn node(version: &str, dependencies: Vec<(&str, Node)>) -> Node {
+        Node {
+            version: version.to_string(),
+            dependencies: dependencies
+                .into_iter()
+                .map(|(name, node)| (name.to_string(), node))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn output_is_byte_stable() {
+        let tree = node(
+            "1.0.0",
+            vec![
+                ("zod", node("3.0.0", vec![])),
+                ("react", node("17.0.2", vec![])),
+                ("@types/node", node("16.7.1", vec![])),
+                ("lodash", node("4.17.21", vec![])),
+            ],
+        );
+
+        let expected = r#"{
+  "schemaVersion": 1,
+  "dependencies": {
+    "@types/node": {
+      "dependencies": {},
+      "version": "16.7.1"
+    },
+    "lodash": {
+      "dependencies": {},
+      "version": "4.17.21"
+    },
+    "react": {
+      "dependencies": {},
+      "version": "17.0.2"
+    },
+    "zod": {
+      "dependencies": {},
+      "version": "3.0.0"
+    }
+  },
+  "version": "1.0.0"
+}"#;
+
+        for _ in 0..8 {
+            assert_eq!(to_json(&tree).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn values_that_are_not_objects_go_under_data() {
+        assert_eq!(
+            to_json(&vec!["a", "b"]).unwrap(),
+            "{\n  \"schemaVersion\": 1,\n  \"data\": [\n    \"a\",\n    \"b\"\n  ]\n}"
+        );
+    }
+}
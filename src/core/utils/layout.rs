@@ -0,0 +1,117 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! The `node_modules/.volt-state` marker recording which layout a tree was installed with.
+
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::constants::LAYOUT_VERSION;
+use super::errors::VoltError;
+use super::link_strategy::LinkStrategy;
+use crate::core::VERSION;
+
+/// Contents of `node_modules/.volt-state`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LayoutState {
+    pub layout_version: u32,
+    /// The volt version that last installed into this `node_modules`.
+    pub volt_version: String,
+    /// Trees installed before link strategies existed are hoisted.
+    #[serde(default = "hoisted")]
+    pub link_strategy: String,
+}
+
+fn hoisted() -> String {
+    LinkStrategy::Hoisted.as_str().to_string()
+}
+
+impl LayoutState {
+    /// The state written by the running volt installing with `link_strategy`.
+    pub fn current(link_strategy: LinkStrategy) -> Self {
+        Self {
+            layout_version: LAYOUT_VERSION,
+            volt_version: VERSION.to_string(),
+            link_strategy: link_strategy.as_str().to_string(),
+        }
+    }
+
+    pub fn path(node_modules_dir: &Path) -> PathBuf {
+        node_modules_dir.join(".volt-state")
+    }
+
+    /// Reads the marker, `None` when `node_modules` has none or it can't be parsed.
+    pub fn load(node_modules_dir: &Path) -> Option<Self> {
+        std::fs::read_to_string(Self::path(node_modules_dir))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    pub fn save(&self, node_modules_dir: &Path) -> Result<()> {
+        let path = Self::path(node_modules_dir);
+
+        std::fs::create_dir_all(node_modules_dir).map_err(VoltError::CreateDirError)?;
+
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap()).map_err(|e| {
+            VoltError::WriteFileError {
+                source: e,
+                name: path.to_string_lossy().to_string(),
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Makes sure `node_modules` can be installed into without mixing two layouts.
+///
+/// A tree without a marker (fresh, or installed before markers existed) is accepted and
+/// gets one after the install. Layout 1 is the first, so there is nothing to migrate
+/// from yet: an older layout asks for a reinstall, as does a tree from a newer volt or one
+/// laid out with a different link strategy.
+pub fn check_layout(node_modules_dir: &Path, link_strategy: LinkStrategy) -> Result<()> {
+    let state = match LayoutState::load(node_modules_dir) {
+        Some(state) => state,
+        None => return Ok(()),
+    };
+
+    if state.layout_version < LAYOUT_VERSION {
+        return Err(VoltError::OutdatedLayout {
+            found: state.layout_version,
+            expected: LAYOUT_VERSION,
+        }
+        .into());
+    }
+
+    if state.layout_version > LAYOUT_VERSION {
+        return Err(VoltError::NewerLayout {
+            found: state.layout_version,
+            volt_version: state.volt_version,
+        }
+        .into());
+    }
+
+    if state.link_strategy != link_strategy.as_str() {
+        return Err(VoltError::LinkStrategyChanged {
+            found: state.link_strategy,
+            expected: link_strategy.as_str().to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! How a resolved tree is laid out in `node_modules`, from `--link-strategy` or
+//! `link-strategy` in `.voltrc`.
+//!
+//! Each strategy turns the tree into a [`LinkPlan`]; the install itself only extracts
+//! packages where the plan places them and creates the links it asks for, so a new layout
+//! doesn't touch the resolver or the install loop.
+
+use miette::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::app::App;
+use super::errors::VoltError;
+use super::linker::{remove_dir_without_following, symlink_dir};
+use super::voltapi::VoltPackage;
+use super::{find_dependency_key, find_root_key, plan_node_modules};
+use crate::commands::add::Package;
+
+/// How the packages of a tree are arranged in `node_modules`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkStrategy {
+    /// npm's flat tree: one version of each name at the top level, the rest nested.
+    Hoisted,
+    /// Every package in `node_modules/.volt/<name>@<version>/node_modules`, linked to its
+    /// dependencies, with only the requested packages linked at the top level. Packages
+    /// can still reach undeclared dependencies through `node_modules/.volt/node_modules`.
+    Isolated,
+    /// The isolated layout without `node_modules/.volt/node_modules`, so a package can only
+    /// require what it declares, like pnpm's strict mode.
+    PnpmStrict,
+}
+
+impl LinkStrategy {
+    pub fn parse(strategy: &str) -> Result<Self> {
+        match strategy {
+            "hoisted" => Ok(Self::Hoisted),
+            "isolated" => Ok(Self::Isolated),
+            "pnpm-strict" => Ok(Self::PnpmStrict),
+            _ => Err(VoltError::InvalidLinkStrategy {
+                strategy: strategy.to_string(),
+            })?,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hoisted => "hoisted",
+            Self::Isolated => "isolated",
+            Self::PnpmStrict => "pnpm-strict",
+        }
+    }
+
+    /// The layout implementing this strategy.
+    pub fn layout(&self) -> Box<dyn NodeModulesLayout> {
+        match self {
+            Self::Hoisted => Box::new(HoistedLayout),
+            Self::Isolated => Box::new(IsolatedLayout { strict: false }),
+            Self::PnpmStrict => Box::new(IsolatedLayout { strict: true }),
+        }
+    }
+}
+
+/// Where each package of a tree is extracted, and what's linked once they all are.
+#[derive(Debug, Default)]
+pub struct LinkPlan {
+    /// The `node_modules` directories of each package, keyed by `name@version`. Packages
+    /// left out go in the project's `node_modules`.
+    pub placements: HashMap<String, Vec<PathBuf>>,
+    /// Directory links to create after the install, as `(link, target)`.
+    pub links: Vec<(PathBuf, PathBuf)>,
+    /// Packages linked at the top level whose bins go in `node_modules/.bin`, for layouts
+    /// that don't extract them there.
+    pub bins: Vec<VoltPackage>,
+}
+
+/// Lays out a resolved tree in `node_modules`.
+pub trait NodeModulesLayout {
+    /// Plans where the packages of `tree` go, `roots` being the packages that were asked for.
+    fn plan(&self, app: &App, roots: &[Package], tree: &HashMap<String, VoltPackage>) -> LinkPlan;
+}
+
+struct HoistedLayout;
+
+impl NodeModulesLayout for HoistedLayout {
+    fn plan(&self, app: &App, roots: &[Package], tree: &HashMap<String, VoltPackage>) -> LinkPlan {
+        LinkPlan {
+            placements: plan_node_modules(&app.node_modules_dir, roots, tree),
+            ..LinkPlan::default()
+        }
+    }
+}
+
+struct IsolatedLayout {
+    strict: bool,
+}
+
+impl IsolatedLayout {
+    /// `node_modules/.volt/<name>@<version>/node_modules`, with a scope's `/` as `+`.
+    fn package_node_modules(app: &App, key: &str) -> PathBuf {
+        app.node_modules_dir
+            .join(".volt")
+            .join(key.replace('/', "+"))
+            .join("node_modules")
+    }
+}
+
+impl NodeModulesLayout for IsolatedLayout {
+    fn plan(&self, app: &App, roots: &[Package], tree: &HashMap<String, VoltPackage>) -> LinkPlan {
+        let keys: HashMap<&str, &str> = tree
+            .iter()
+            .map(|(key, package)| (package.name.as_str(), key.as_str()))
+            .collect();
+
+        let mut plan = LinkPlan::default();
+
+        for (key, package) in tree.iter() {
+            let node_modules = Self::package_node_modules(app, key);
+
+            for dependency in package.dependencies.as_deref().unwrap_or(&[]) {
+                if let Some(child) = find_dependency_key(tree, &keys, dependency) {
+                    let name = &tree[child].name;
+
+                    plan.links.push((
+                        node_modules.join(name),
+                        Self::package_node_modules(app, child).join(name),
+                    ));
+                }
+            }
+
+            plan.placements.insert(key.clone(), vec![node_modules]);
+        }
+
+        // the first version of each name is what an undeclared require finds
+        if !self.strict {
+            let mut sorted: Vec<&String> = tree.keys().collect();
+            sorted.sort();
+
+            let hidden = app.node_modules_dir.join(".volt").join("node_modules");
+            let mut linked: Vec<&str> = vec![];
+
+            for key in sorted {
+                let name = tree[key].name.as_str();
+
+                if !linked.contains(&name) {
+                    plan.links.push((
+                        hidden.join(name),
+                        Self::package_node_modules(app, key).join(name),
+                    ));
+                    linked.push(name);
+                }
+            }
+        }
+
+        for root in roots {
+            if let Some(key) = find_root_key(tree, root) {
+                let package = &tree[key];
+
+                plan.links.push((
+                    app.node_modules_dir.join(&package.name),
+                    Self::package_node_modules(app, key).join(&package.name),
+                ));
+                plan.bins.push(package.clone());
+            }
+        }
+
+        plan
+    }
+}
+
+/// Creates the links of a plan, replacing whatever is already at each link's path.
+pub fn create_links(app: &App, links: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let link_type = app.windows_link_type()?;
+
+    for (link, target) in links {
+        if std::fs::symlink_metadata(link).is_ok() {
+            remove_dir_without_following(link).map_err(|e| VoltError::RemoveDirError {
+                source: e,
+                name: link.to_string_lossy().to_string(),
+            })?;
+        }
+
+        symlink_dir(target, link, link_type).map_err(|e| VoltError::LinkError {
+            source: e,
+            name: link.to_string_lossy().to_string(),
+        })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,396 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use miette::Result;
+use std::path::{Path, PathBuf};
+
+use super::errors::VoltError;
+
+/// How an installed package is materialized into `node_modules`.
+///
+/// Both modes produce real files (never symlinks or junctions), so the tree works
+/// on filesystems and Windows containers that can't follow links.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeLinker {
+    /// Extract the tarball a second time straight into `node_modules`, or copy it from `~/.volt` once cached.
+    Copy,
+    /// Hard link the files extracted into `~/.volt`, copying when a link isn't possible.
+    Hardlink,
+}
+
+impl NodeLinker {
+    pub fn parse(linker: &str) -> Result<Self> {
+        match linker {
+            "copy" => Ok(Self::Copy),
+            "hardlink" => Ok(Self::Hardlink),
+            _ => Err(VoltError::InvalidNodeLinker {
+                linker: linker.to_string(),
+            })?,
+        }
+    }
+}
+
+/// How `volt link` links directories on windows, from `--windows-link-type` or
+/// `windows-link-type` in `.voltrc`. Other platforms always use symlinks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowsLinkType {
+    /// Needs no privilege, but can't point at a network share and some tools don't follow it.
+    Junction,
+    /// Needs Developer Mode (windows 10 and later) or an administrator.
+    Symlink,
+    /// A symlink when this process may create one, a junction otherwise.
+    Auto,
+}
+
+impl WindowsLinkType {
+    pub fn parse(link_type: &str) -> Result<Self> {
+        match link_type {
+            "junction" => Ok(Self::Junction),
+            "symlink" => Ok(Self::Symlink),
+            "auto" => Ok(Self::Auto),
+            _ => Err(VoltError::InvalidWindowsLinkType {
+                link_type: link_type.to_string(),
+            })?,
+        }
+    }
+}
+
+/// Hard links every file under `source` into `destination`, mirroring its directory layout.
+///
+/// Falls back to copying a file when it can't be linked (e.g. `~/.volt` is on another drive).
+/// A file already at the target is replaced, it may belong to another version.
+pub fn link_package_files(source: &Path, destination: &Path) -> std::io::Result<()> {
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).unwrap();
+        let target = destination.join(relative);
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        if std::fs::symlink_metadata(&target).is_ok() {
+            remove_dir_without_following(&target)?;
+        }
+
+        if std::fs::hard_link(entry.path(), &target).is_err() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies every file under `source` into `destination`, mirroring its directory layout.
+pub fn copy_package_files(source: &Path, destination: &Path) -> std::io::Result<()> {
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).unwrap();
+        let target = destination.join(relative);
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies a local package under development into `destination`, leaving out its own
+/// `node_modules` and `.git` the way a published tarball would.
+pub fn copy_local_package(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let entries = walkdir::WalkDir::new(source)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() != 1
+                || !matches!(
+                    entry.file_name().to_str(),
+                    Some("node_modules") | Some(".git")
+                )
+        });
+
+    for entry in entries {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).unwrap();
+        let target = destination.join(relative);
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Directory in `~/.volt` where `volt link` registers local packages, keyed by package name.
+pub fn links_dir(volt_dir: &Path) -> PathBuf {
+    volt_dir.join(".links")
+}
+
+/// Creates a symlink at `link` pointing to the directory `original`, or a junction on windows
+/// when `link_type` asks for one.
+pub fn symlink_dir(
+    original: &Path,
+    link: &Path,
+    link_type: WindowsLinkType,
+) -> std::io::Result<()> {
+    if let Some(parent) = link.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    create_symlink(original, link, link_type)
+}
+
+#[cfg(unix)]
+fn create_symlink(
+    original: &Path,
+    link: &Path,
+    _link_type: WindowsLinkType,
+) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(original: &Path, link: &Path, link_type: WindowsLinkType) -> std::io::Result<()> {
+    let symlink = match link_type {
+        WindowsLinkType::Symlink => true,
+        WindowsLinkType::Junction => false,
+        WindowsLinkType::Auto => can_symlink(),
+    };
+
+    if symlink {
+        std::os::windows::fs::symlink_dir(original, link)
+    } else {
+        // junctions only hold absolute targets
+        junction::create(std::env::current_dir()?.join(original), link)
+    }
+}
+
+/// Whether this process may create symlinks, checked once by creating one in the temp
+/// directory. Developer Mode allows it without being an administrator.
+#[cfg(windows)]
+fn can_symlink() -> bool {
+    lazy_static::lazy_static! {
+        static ref CAN_SYMLINK: bool = {
+            let temp = std::env::temp_dir();
+            let probe = temp.join(format!("volt-symlink-probe-{}", std::process::id()));
+
+            let created = std::os::windows::fs::symlink_dir(&temp, &probe).is_ok();
+            std::fs::remove_dir(&probe).ok();
+
+            created
+        };
+    }
+
+    *CAN_SYMLINK
+}
+
+/// Removes a symlink created by [`symlink_dir`], leaving its target untouched.
+pub fn remove_symlink_dir(link: &Path) -> std::io::Result<()> {
+    // directory symlinks are removed with remove_dir on windows
+    std::fs::remove_file(link).or_else(|_| std::fs::remove_dir(link))
+}
+
+/// Checks whether `path` is a symlink, or on windows a junction, without following it.
+pub fn is_symlink(path: &Path) -> bool {
+    std::fs::symlink_metadata(path)
+        .map(|metadata| is_link(&metadata))
+        .unwrap_or(false)
+}
+
+/// Whether an entry points somewhere else: a symlink, or on windows any reparse point such
+/// as a junction.
+#[cfg(unix)]
+fn is_link(metadata: &std::fs::Metadata) -> bool {
+    metadata.file_type().is_symlink()
+}
+
+#[cfg(windows)]
+fn is_link(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::winnt::FILE_ATTRIBUTE_REPARSE_POINT;
+
+    metadata.file_type().is_symlink()
+        || metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+}
+
+/// Removes a file, clearing its read-only flag first if that's what stops it.
+#[cfg(windows)]
+fn remove_file(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path).or_else(|e| {
+        let mut permissions = std::fs::symlink_metadata(path)?.permissions();
+
+        if !permissions.readonly() {
+            return Err(e);
+        }
+
+        permissions.set_readonly(false);
+        std::fs::set_permissions(path, permissions)?;
+        std::fs::remove_file(path)
+    })
+}
+
+/// Removes a file. Unlike windows, a read-only file can be removed as it is.
+#[cfg(not(windows))]
+fn remove_file(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)
+}
+
+/// Empties the directory a package is installed in before another version goes there, so
+/// none of the old version's files are left behind.
+///
+/// Its `node_modules` is kept, nested dependencies are placed in it separately and may
+/// already be installed. A link in the package's place is removed, not what it points to.
+pub fn clear_package_dir(path: &Path) -> std::io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+
+    if is_link(&metadata) || !metadata.is_dir() {
+        return remove_dir_without_following(path);
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+
+        if entry.file_name() != "node_modules" {
+            remove_dir_without_following(&entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a directory tree without following the links inside it.
+///
+/// Symlinks and junctions are removed themselves, never the content they point into, so a
+/// `node_modules` with linked packages can be deleted without touching what they link to.
+pub fn remove_dir_without_following(path: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+
+    if is_link(&metadata) {
+        return remove_symlink_dir(path);
+    }
+
+    if !metadata.is_dir() {
+        return remove_file(path);
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        remove_dir_without_following(&entry?.path())?;
+    }
+
+    std::fs::remove_dir(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("volt-linker-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn upgrading_in_place_leaves_no_old_files() {
+        let dir = scratch("upgrade");
+        let (old, new, installed) = (dir.join("16"), dir.join("17"), dir.join("react"));
+
+        fs::create_dir_all(old.join("cjs")).unwrap();
+        fs::write(old.join("package.json"), "16").unwrap();
+        fs::write(old.join("cjs/react.development.js"), "16").unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(new.join("package.json"), "17").unwrap();
+
+        link_package_files(&old, &installed).unwrap();
+        fs::create_dir_all(installed.join("node_modules/loose-envify")).unwrap();
+
+        clear_package_dir(&installed).unwrap();
+        link_package_files(&new, &installed).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(installed.join("package.json")).unwrap(),
+            "17"
+        );
+        assert!(!installed.join("cjs").exists());
+        assert!(installed.join("node_modules/loose-envify").exists());
+
+        // the store's copy of the old version is untouched
+        assert_eq!(fs::read_to_string(old.join("package.json")).unwrap(), "16");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn linking_replaces_existing_files() {
+        let dir = scratch("replace");
+        let (source, installed) = (dir.join("store"), dir.join("installed"));
+
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(source.join("index.js"), "new").unwrap();
+        fs::write(installed.join("index.js"), "old").unwrap();
+
+        link_package_files(&source, &installed).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(installed.join("index.js")).unwrap(),
+            "new"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn clearing_a_missing_directory_does_nothing() {
+        let dir = scratch("missing");
+
+        assert!(clear_package_dir(&dir.join("react")).is_ok());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scoped_packages_are_linked_under_their_scope() {
+        let dir = scratch("symlink");
+        let (project, link) = (dir.join("types-node"), dir.join("node_modules/@types/node"));
+
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("package.json"), "{}").unwrap();
+
+        symlink_dir(&project, &link, WindowsLinkType::Auto).unwrap();
+
+        assert!(is_symlink(&link));
+        assert!(link.join("package.json").is_file());
+
+        remove_symlink_dir(&link).unwrap();
+
+        assert!(!link.exists());
+        assert!(project.join("package.json").is_file());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
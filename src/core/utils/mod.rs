@@ -1,115 +1,262 @@
 pub mod app;
 pub mod constants;
 pub mod errors;
+pub mod events;
+pub mod failures;
+pub mod hasher;
 pub mod helper;
+pub mod install_lock;
+pub mod json_output;
+pub mod layout;
+pub mod link_strategy;
+pub mod linker;
+pub mod node;
 pub mod npm;
 pub mod package;
+pub mod platform;
 pub mod scripts;
+pub mod signatures;
+pub mod snapshot;
+pub mod spill;
+pub mod stats;
+pub mod store_index;
+pub mod tarball;
+#[cfg(test)]
+pub mod testing;
 pub mod voltapi;
+pub mod workspace;
 
 use crate::commands::add::Package;
-use crate::core::utils::voltapi::{VoltPackage, VoltResponse};
+use crate::core::utils::voltapi::{
+    dependency_name, normalize_name, package_scope, split_dependency, VoltPackage, VoltResponse,
+};
 use crate::Instant;
 use app::App;
 use colored::Colorize;
 use errors::VoltError;
-use flate2::read::GzDecoder;
-use futures_util::{stream::FuturesUnordered, StreamExt};
+use events::{InstallEvent, Reporter};
+use futures_util::{stream::FuturesOrdered, StreamExt};
 use git_config::{file::GitConfig, parser::Parser};
-use indicatif::ProgressBar;
-use isahc::AsyncReadResponseExt;
+use hasher::{check_integrity, integrity_from_shasum, integrity_of, is_sha1_only, parse_integrity};
+use isahc::{AsyncReadResponseExt, RequestExt};
+use linker::{clear_package_dir, copy_package_files, link_package_files, NodeLinker};
 use miette::Result;
+use node_semver::{Range, Version};
+use npm::{is_valid_name, VersionRange};
 use package::NpmPackage;
+use platform::Platform;
 use reqwest::StatusCode;
-use ssri::{Algorithm, Integrity};
+use signatures::verify_signatures;
+use ssri::{Algorithm, Integrity, IntegrityOpts};
+use stats::{directory_size, InstallStats};
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     convert::TryFrom,
     env::temp_dir,
-    ffi::OsStr,
     fs::{read_to_string, File},
-    io::Write,
+    io::{Read, Seek, SeekFrom, Write},
     path::{Component, Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
+use store_index::{check_entry, write_index, EntryState};
+use tarball::{decode_content, TarballFormat};
 
 use jwalk::WalkDir;
+use lazy_static::lazy_static;
 use tokio::fs::hard_link;
 
 use tar::Archive;
 use tokio::fs::create_dir_all;
 
-use crate::core::utils::constants::MAX_RETRIES;
+use crate::core::model::http_manager::get_package;
+use crate::core::model::lock_file::LockFile;
+use crate::core::net::{retry_after, CONCURRENCY, SOCKETS, THROTTLE};
+use crate::core::utils::constants::{
+    MAX_DEPTH, MAX_REDIRECTS, MAX_RETRIES, POOL_IDLE_TIMEOUT, STORE_STAGING_PREFIX,
+};
 use crate::core::utils::voltapi::JSONVoltResponse;
 
-/// convert a JSONVoltResponse -> VoltResponse
-pub fn convert(deserialized: JSONVoltResponse) -> Result<VoltResponse> {
-    // initialize a hashmap to store the converted versions
-    let mut converted_versions: HashMap<String, VoltPackage> = HashMap::new();
+lazy_static! {
+    /// Client shared by every tarball download. Registries often redirect tarballs to a
+    /// storage backend, so a bounded number of redirects is followed; reqwest drops the
+    /// `Authorization` header whenever a redirect leaves the original host.
+    ///
+    /// Sharing one client keeps its connection pool warm across an install. Over https the
+    /// registry negotiates HTTP/2 through ALPN (the `native-tls-alpn` feature), so
+    /// concurrent downloads are multiplexed over a single connection per host.
+    static ref TARBALL_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT))
+        .tcp_keepalive(Duration::from_secs(POOL_IDLE_TIMEOUT))
+        .build()
+        .unwrap();
 
-    // iterate through all listed dependencies of the latest version of the response
-    for version in deserialized.versions.get(&deserialized.latest).unwrap() {
-        // access data in the hashmap, not name@version
-        let data = version.1;
+    /// Threads that decompress and extract tarballs, one per cpu by default. They're kept
+    /// apart from tokio's blocking pool so a burst of downloads finishing at once can't
+    /// starve other blocking work, or the other way around.
+    static ref EXTRACT_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|index| format!("volt-extract-{}", index))
+        .build()
+        .unwrap();
+}
 
-        // @codemirror/state -> state
-        let split = version
-            .0
-            .split("@")
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<&str>>();
+/// Runs CPU-bound extraction work on the extraction pool, returning a future that
+/// resolves once it's done. The work starts right away, not when the future is polled.
+fn spawn_extract<F>(package: &str, work: F) -> impl std::future::Future<Output = Result<()>>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    let package = package.to_string();
+
+    EXTRACT_POOL.spawn(move || {
+        sender.send(work()).ok();
+    });
+
+    // the sender is only dropped without sending if the work panicked
+    async move {
+        receiver
+            .await
+            .unwrap_or_else(|_| Err(VoltError::CorruptTarball { package }.into()))
+    }
+}
 
-        let mut package_name = String::new();
+/// Unpacks a tarball into `destination/<name>`, the way packages sit in the store and in
+/// node_modules. Entries that can't be written (access denied on windows) are skipped.
+fn unpack_tarball(
+    data: &[u8],
+    format: TarballFormat,
+    destination: &Path,
+    name: &str,
+    bin: &Option<HashMap<String, String>>,
+) -> Result<()> {
+    let corrupt = |_| VoltError::CorruptTarball {
+        package: name.to_string(),
+    };
+
+    let mut archive = Archive::new(format.reader(data));
+
+    // meta packages can be nothing but a package.json, and the directory is linked
+    // and committed to the store whatever the tarball holds
+    std::fs::create_dir_all(destination.join(name)).map_err(VoltError::CreateDirError)?;
+
+    for entry in archive.entries().map_err(corrupt)? {
+        let mut entry = entry.map_err(corrupt)?;
+        let mode = entry.header().mode().unwrap_or(0o644);
+        let is_dir = entry.header().entry_type().is_dir();
+        let target = destination.join(entry_destination(&entry.path().map_err(corrupt)?, name));
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(VoltError::CreateDirError)?;
+        }
 
-        if split.len() == 2 {
-            package_name = split[0].to_string();
-            if package_name.contains("/") {
-                package_name = format!("@{}", package_name);
-            }
+        if entry.unpack(&target).is_ok() {
+            apply_entry_mode(&target, mode, is_dir);
         }
+    }
 
-        // @codemirror/state@1.2.3 -> 1.2.3
-        let package_version = version.0.split("@").last().unwrap();
+    mark_bins_executable(&destination.join(name), bin);
 
-        let integrity: Integrity =
-            data.integrity
-                .clone()
-                .parse()
-                .map_err(|_| VoltError::HashParseError {
-                    hash: data.integrity.clone(),
-                })?;
+    Ok(())
+}
 
-        let algo = integrity.pick_algorithm();
+/// Picks the version of a response that best matches a requested version or range.
+///
+/// An exact match wins, otherwise the highest version satisfying the range is used.
+pub fn select_version(deserialized: &JSONVoltResponse, requested: &str) -> Option<String> {
+    if deserialized.versions.contains_key(requested) {
+        return Some(requested.to_string());
+    }
 
-        let mut hash = integrity
-            .hashes
-            .into_iter()
-            .find(|h| h.algorithm == algo)
-            .map(|h| Integrity { hashes: vec![h] })
-            .map(|i| i.to_hex().1)
-            .ok_or(VoltError::IntegrityConversionError)?;
+    let range: VersionRange = requested.parse().ok()?;
 
-        match algo {
-            Algorithm::Sha1 => {
-                hash = format!("sha1-{}", hash);
-            }
-            Algorithm::Sha512 => {
-                hash = format!("sha512-{}", hash);
+    deserialized
+        .versions
+        .keys()
+        .filter_map(|version| version.parse::<Version>().ok())
+        .filter(|version| range.satisfies(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
+/// convert a JSONVoltResponse -> VoltResponse
+pub fn convert(
+    name: &str,
+    version: String,
+    deserialized: JSONVoltResponse,
+) -> Result<VoltResponse> {
+    // initialize a hashmap to store the converted versions
+    let mut converted_versions: HashMap<String, VoltPackage> = HashMap::new();
+
+    // `latest` can name a version the response doesn't have
+    let missing = || VoltError::MalformedRegistryEntry {
+        package: name.to_string(),
+        missing_field: version.clone(),
+    };
+    let packages = deserialized.versions.get(&version).ok_or_else(missing)?;
+
+    // iterate through all listed dependencies of the selected version of the response
+    for version in packages {
+        // access data in the hashmap, not name@version
+        let data = version.1;
+
+        // @codemirror/state@1.2.3 -> @codemirror/state, 1.2.3
+        let (package_name, package_version) = match split_dependency(version.0) {
+            (name, Some(version)) => (name.to_string(), version),
+            (name, None) => (name.to_string(), ""),
+        };
+
+        if data.tarball.is_empty() {
+            return Err(VoltError::MalformedRegistryEntry {
+                package: version.0.to_string(),
+                missing_field: String::from("tarball"),
             }
-            _ => {}
+            .into());
         }
 
+        // keep every hash in the integrity string, verification picks the strongest one
+        let integrity: Integrity = match (data.integrity.is_empty(), &data.shasum) {
+            (false, _) => parse_integrity(&data.integrity)?,
+            // legacy packages only publish a sha1 shasum
+            (true, Some(shasum)) => integrity_from_shasum(shasum)?,
+            (true, None) => {
+                return Err(VoltError::MalformedRegistryEntry {
+                    package: version.0.to_string(),
+                    missing_field: String::from("integrity"),
+                }
+                .into());
+            }
+        };
+
         converted_versions.insert(
             version.0.to_string(), // name@version
             VoltPackage {
                 name: package_name.to_string(),
                 version: package_version.to_string(),
                 tarball: data.tarball.clone(),
-                bin: data.bin.clone(),
-                integrity: hash,
+                bin: data.bin.as_ref().map(|bin| {
+                    bin.iter()
+                        .map(|(command, path)| match command.as_str() {
+                            // the string form of bin, named after the package without its scope
+                            "" => {
+                                let command = package_name.rsplit('/').next().unwrap();
+                                (command.to_string(), path.clone())
+                            }
+                            _ => (command.clone(), path.clone()),
+                        })
+                        .collect()
+                }),
+                integrity: integrity.to_string(),
                 peer_dependencies: data.peer_dependencies.clone(),
+                peer_dependencies_meta: data.peer_dependencies_meta.clone(),
                 dependencies: data.dependencies.clone(),
+                optional_dependencies: data.optional_dependencies.clone(),
+                os: data.os.clone(),
+                cpu: data.cpu.clone(),
+                funding: data.funding.clone(),
+                unpacked_size: data.unpacked_size,
             },
         );
     }
@@ -118,29 +265,46 @@ pub fn convert(deserialized: JSONVoltResponse) -> Result<VoltResponse> {
 
     let mut final_res: HashMap<String, HashMap<String, VoltPackage>> = HashMap::new();
 
-    final_res.insert(deserialized.latest.to_string(), converted_versions);
+    final_res.insert(version.to_string(), converted_versions);
 
     Ok(VoltResponse {
-        version: deserialized.latest,
+        version,
         versions: final_res,
     })
 }
 
 // Get response from volt CDN
-pub async fn get_volt_response(package: Package) -> Result<VoltResponse> {
+pub async fn get_volt_response(
+    package: Package,
+    cdn: &str,
+    headers: &[(String, String)],
+) -> Result<VoltResponse> {
     // number of retries
     let mut retries = 0;
 
     // loop until MAX_RETRIES reached.
     loop {
+        THROTTLE.wait().await;
+
         let package_name = package.name.clone();
+        let url = format!("{}/{}/data.json", cdn, package_name);
+
+        // held until the body has been read
+        let _socket = SOCKETS.acquire(&url).await;
+
         // get a response
-        let mut response = isahc::get_async(format!(
-            "https://cdn.jsdelivr.net/npm/@voltpkg/{}/data.json",
-            package_name
-        ))
-        .await
-        .map_err(VoltError::NetworkError)?;
+        let mut request = isahc::Request::get(url);
+
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let mut response = request
+            .body(())
+            .map_err(VoltError::RequestBuilderError)?
+            .send_async()
+            .await
+            .map_err(VoltError::NetworkError)?;
 
         // check the status of the response
         match response.status() {
@@ -150,15 +314,35 @@ pub async fn get_volt_response(package: Package) -> Result<VoltResponse> {
                     serde_json::from_str(response.text().await.unwrap().as_str())
                         .map_err(|_| VoltError::DeserializeError)?;
 
-                let converted = convert(deserialized)?;
+                let version = match &package.version {
+                    Some(requested) => select_version(&deserialized, requested).ok_or(
+                        VoltError::VersionLookupError {
+                            name: package_name.to_string(),
+                        },
+                    )?,
+                    None => deserialized.latest.clone(),
+                };
+
+                let converted = convert(&package_name, version, deserialized)?;
 
                 return Ok(converted);
             }
-            // 429 (TOO_MANY_REQUESTS)
-            StatusCode::TOO_MANY_REQUESTS => Err(VoltError::TooManyRequests {
-                url: format!("http://registry.voltpkg.com/{}", package_name),
-                package_name: package_name.to_string(),
-            })?,
+            // 429 (TOO_MANY_REQUESTS), every request waits before trying again
+            StatusCode::TOO_MANY_REQUESTS => {
+                if retries == MAX_RETRIES {
+                    Err(VoltError::TooManyRequests {
+                        url: format!("http://registry.voltpkg.com/{}", package_name),
+                        package_name: package_name.to_string(),
+                    })?
+                }
+
+                THROTTLE.back_off(retry_after(
+                    response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| value.to_str().ok()),
+                ));
+            }
             // 400 (BAD_REQUEST)
             StatusCode::BAD_REQUEST => Err(VoltError::BadRequest {
                 url: format!("http://registry.voltpkg.com/{}", package_name),
@@ -191,15 +375,114 @@ pub async fn get_volt_response(package: Package) -> Result<VoltResponse> {
     }
 }
 
+/// What a package requested without a version resolves to.
+pub enum DefaultVersion<'a> {
+    /// The version behind a dist-tag, `latest` unless `--tag` says otherwise.
+    Tag(&'a str),
+    /// The highest published version, whatever the dist-tags point at (`--newest`).
+    Newest,
+}
+
+/// The highest stable version in a packument, or the highest prerelease if there's no stable one.
+fn newest_version(packument: &NpmPackage) -> Option<String> {
+    let versions = packument
+        .versions
+        .keys()
+        .filter_map(|version| version.parse::<Version>().ok());
+
+    let (stable, prerelease): (Vec<Version>, Vec<Version>) =
+        versions.partition(|version| version.pre_release.is_empty());
+
+    stable
+        .into_iter()
+        .max()
+        .or_else(|| prerelease.into_iter().max())
+        .map(|version| version.to_string())
+}
+
+/// Resolves dist-tags (`react@next`, `--tag beta`) to the versions they point at.
+///
+/// Packages requested with a version or range are left untouched, packages requested
+/// without one resolve to `default`. Maintainers sometimes point `latest` at an older
+/// release line, so `DefaultVersion::Tag("latest")` and `DefaultVersion::Newest` can differ.
+pub async fn resolve_tags(
+    app: &App,
+    packages: Vec<Package>,
+    default: DefaultVersion<'_>,
+) -> Result<Vec<Package>> {
+    let mut resolved = Vec::with_capacity(packages.len());
+
+    for mut package in packages {
+        let tag = match (&package.version, &default) {
+            // anything that isn't a version range is a tag
+            (Some(version), _) if version.parse::<Range>().is_err() => Some(version.clone()),
+            (Some(_), _) => {
+                resolved.push(package);
+                continue;
+            }
+            (None, DefaultVersion::Tag(tag)) => Some(tag.to_string()),
+            (None, DefaultVersion::Newest) => None,
+        };
+
+        let registry = app.registry_for(&package.name);
+        let headers = app.registry_headers(&registry);
+
+        let packument = get_package(&registry, &package.name, &headers)
+            .await
+            .map_err(|_| VoltError::VersionLookupError {
+                name: package.name.clone(),
+            })?
+            .ok_or(VoltError::VersionLookupError {
+                name: package.name.clone(),
+            })?;
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                package.version = Some(newest_version(&packument).ok_or(
+                    VoltError::VersionLookupError {
+                        name: package.name.clone(),
+                    },
+                )?);
+
+                resolved.push(package);
+                continue;
+            }
+        };
+
+        match packument.dist_tags.get(&tag) {
+            Some(version) => package.version = Some(version.clone()),
+            None => {
+                let mut available = packument.dist_tags.keys().cloned().collect::<Vec<String>>();
+                available.sort();
+
+                return Err(VoltError::TagNotFound {
+                    name: package.name,
+                    tag,
+                    available: available.join(", "),
+                }
+                .into());
+            }
+        }
+
+        resolved.push(package);
+    }
+
+    Ok(resolved)
+}
+
+/// Responses are in the order `packages` were passed, so callers can pair them up by index.
 pub async fn get_volt_response_multi(
     packages: Vec<Package>,
-    pb: &ProgressBar,
+    cdn: &str,
+    headers: &[(String, String)],
+    reporter: &dyn Reporter,
 ) -> Vec<Result<VoltResponse>> {
     packages
         .into_iter()
-        .map(|name| get_volt_response(name))
-        .collect::<FuturesUnordered<_>>()
-        .inspect(|_| pb.inc(1))
+        .map(|name| get_volt_response(name, cdn, headers))
+        .collect::<FuturesOrdered<_>>()
+        .inspect(|_| reporter.report(InstallEvent::PackageResolved))
         .collect::<Vec<Result<VoltResponse>>>()
         .await
 }
@@ -353,346 +636,1055 @@ pub async fn get_volt_response_multi(
 //     }
 // }
 
-/// downloads tarball file from package
-pub async fn download_tarball(app: &App, package: &VoltPackage, secure: bool) -> Result<()> {
-    let package_instance = package.clone();
+/// Verifies `data` against an SRI integrity string.
+///
+/// The integrity may contain several hashes (e.g. both sha1 and sha512),
+/// in which case the strongest algorithm present is used for verification.
+pub fn verify_checksum(integrity: &str, data: &[u8]) -> Result<Algorithm> {
+    check_integrity(&parse_integrity(integrity)?, data)
+}
 
-    // @types/eslint
-    if package_instance.name.starts_with('@') && package_instance.name.contains("/") {
-        let package_directory_location = app
-            .volt_dir
-            .join(&package.name.split("/").collect::<Vec<&str>>()[0]);
+/// Checks that an extracted package in the volt directory has a readable `package.json`.
+fn is_valid_cache_entry(path: &Path) -> bool {
+    read_to_string(path.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+        .is_some()
+}
 
-        if !Path::new(&package_directory_location).exists() {
-            create_dir_all(&package_directory_location)
-                .await
-                .map_err(VoltError::CreateDirError)?;
-        }
-    }
+/// Checks whether a volt directory entry holds at least one readable `package.json`.
+fn has_package_manifest(path: &Path) -> bool {
+    walkdir::WalkDir::new(path)
+        .max_depth(5)
+        .into_iter()
+        .flatten()
+        .any(|entry| {
+            entry.file_name() == "package.json"
+                && is_valid_cache_entry(entry.path().parent().unwrap())
+        })
+}
 
-    // location of extracted package
-    let loc = app.volt_dir.join(&package.name);
+/// Directory a package's tarball is extracted to in the volt store.
+///
+/// `~/.volt/react-17.0.2`, or `~/.volt/@types/@types/node-16.7.1` for scoped packages.
+pub fn store_directory(app: &App, package: &VoltPackage) -> PathBuf {
+    store_directory_for(app, &package.name, &package.version)
+}
+
+/// Same as `store_directory`, for a package known only by its name and version.
+pub fn store_directory_for(app: &App, name: &str, version: &str) -> PathBuf {
+    let mut directory = app.volt_dir.clone();
+
+    // ~/.volt/@types
+    if let Some(scope) = package_scope(name) {
+        directory = directory.join(scope);
+    }
 
-    // if package is not already installed
-    if !Path::new(&loc).exists() {
-        // Url to download tarball code files from
-        let mut url = package_instance.tarball;
-        // let registries = vec!["yarnpkg.com"];
-        // let random_registry = registries.choose(&mut rand::thread_rng()).unwrap();
+    directory.join(format!("{}-{}", name, version))
+}
 
-        // url = url.replace("npmjs.org", random_registry);
+/// Where a package is extracted before it's moved into `store_directory` in one rename.
+///
+/// It sits next to its destination so the rename never crosses filesystems, and is named
+/// per process so concurrent installs never write to the same one.
+fn staging_directory(store_directory: &Path) -> PathBuf {
+    let name = store_directory
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+
+    store_directory.with_file_name(format!(
+        "{}{}-{}",
+        STORE_STAGING_PREFIX,
+        name,
+        std::process::id()
+    ))
+}
 
-        if !secure {
-            url = url.replace("https", "http")
+/// Moves a fully extracted package from `staging` into the store, so the store only ever
+/// holds complete packages and an install failing partway leaves nothing behind to retry on.
+///
+/// If another install committed the same package first, its copy is kept.
+fn commit_store_directory(staging: &Path, store_directory: &Path) -> Result<()> {
+    match std::fs::rename(staging, store_directory) {
+        Ok(()) => Ok(()),
+        Err(_) if has_package_manifest(store_directory) => {
+            std::fs::remove_dir_all(staging).ok();
+            Ok(())
         }
+        Err(e) => Err(VoltError::WriteFileError {
+            source: e,
+            name: store_directory.to_string_lossy().to_string(),
+        })?,
+    }
+}
 
-        // Get Tarball File
-        let res = reqwest::get(url).await.unwrap();
+/// A tarball added to the store by `volt store add`.
+pub struct StoredTarball {
+    pub name: String,
+    pub version: String,
+    /// sha512 of the tarball, as an install would lock it.
+    pub integrity: Integrity,
+    /// `false` when the store already had this version.
+    pub added: bool,
+}
 
-        // Tarball bytes response
-        let bytes: bytes::Bytes = res.bytes().await.unwrap();
+/// Extracts a local package tarball into the store, where installs needing that name and
+/// version find it instead of downloading it.
+///
+/// The name and version are read from the package.json inside the tarball. With `expected`
+/// the tarball must match that integrity before anything is written.
+pub fn add_tarball_to_store(
+    app: &App,
+    data: &[u8],
+    path: &str,
+    expected: Option<&str>,
+) -> Result<StoredTarball> {
+    if let Some(expected) = expected {
+        verify_checksum(expected, data)?;
+    }
 
-        let algorithm;
+    let format = TarballFormat::detect(data, path)?;
+    let manifest = read_tarball_manifest(data, format, path)?;
 
-        // there are only 2 supported algorithms
-        // sha1 and sha512
-        // so we can be sure that if it doesn't start with sha1, it's going to have to be sha512
-        if package.integrity.starts_with("sha1") {
-            algorithm = Algorithm::Sha1;
-        } else {
-            algorithm = Algorithm::Sha512;
-        }
+    let missing_manifest = || VoltError::MissingTarballManifest {
+        path: path.to_string(),
+    };
 
-        // Verify If Bytes == (Sha 512 | Sha 1) of Tarball
-        if package.integrity == App::calc_hash(&bytes, algorithm).unwrap() {
-            // Create node_modules
-            create_dir_all(&app.node_modules_dir).await.unwrap();
+    let name = manifest["name"].as_str().ok_or_else(missing_manifest)?;
+    let version = manifest["version"].as_str().ok_or_else(missing_manifest)?;
 
-            // Delete package from node_modules
-            let node_modules_dep_path = app.node_modules_dir.join(&package.name);
+    // both end up in a path under ~/.volt
+    if !is_valid_name(name)
+        || name.split('/').any(|part| part.starts_with('.'))
+        || Version::parse(version).is_err()
+    {
+        return Err(missing_manifest().into());
+    }
 
-            // TODO: fix this
-            // if node_modules_dep_path.exists() {
-            //     remove_dir_all(&node_modules_dep_path).unwrap();
-            // }
+    let integrity = integrity_of(data, Algorithm::Sha512)?;
+    let destination = store_directory_for(app, name, version);
 
-            // Directory to extract tarball to
-            let mut extract_directory = PathBuf::from(&app.volt_dir);
+    if is_valid_cache_entry(&destination.join(name)) {
+        return Ok(StoredTarball {
+            name: name.to_string(),
+            version: version.to_string(),
+            integrity,
+            added: false,
+        });
+    }
 
-            // @types/eslint
-            if package.clone().name.starts_with('@') && package.clone().name.contains("/") {
-                if cfg!(target_os = "windows") {
-                    let name = package.clone().name.replace(r"/", r"\");
+    // an unreadable entry from an older volt is replaced
+    if destination.exists() {
+        remove_volt_entry(&destination)?;
+    }
 
-                    let split = name.split(r"\").collect::<Vec<&str>>();
+    let parent = destination.parent().unwrap();
 
-                    // C:\Users\xtrem\.volt\@types
-                    extract_directory = extract_directory.join(split[0]);
-                } else {
-                    let name = package.clone().name;
-                    let split = name.split('/').collect::<Vec<&str>>();
+    std::fs::create_dir_all(parent).map_err(VoltError::CreateDirError)?;
 
-                    // ~/.volt/@types
-                    extract_directory = extract_directory.join(split[0]);
-                }
-            }
+    let staging = staging_directory(&destination);
 
-            extract_directory = extract_directory.join(format!(
-                "{}-{}",
-                package.clone().name,
-                package.clone().version
-            ));
+    std::fs::remove_dir_all(&staging).ok();
 
-            // Initialize tarfile decoder while directly passing in bytes
+    let corrupt = |_| VoltError::CorruptTarball {
+        package: path.to_string(),
+    };
 
-            let bytes = Arc::new(bytes);
+    let mut archive = Archive::new(format.reader(data));
 
-            let bytes_ref = bytes.clone();
+    // meta packages can be nothing but a package.json
+    std::fs::create_dir_all(staging.join(name)).map_err(VoltError::CreateDirError)?;
 
-            let extract_directory_instance = extract_directory.clone();
+    for entry in archive.entries().map_err(corrupt)? {
+        let mut entry = entry.map_err(corrupt)?;
+        let mode = entry.header().mode().unwrap_or(0o644);
+        let is_dir = entry.header().entry_type().is_dir();
+        let target = staging.join(entry_destination(&entry.path().map_err(corrupt)?, name));
 
-            let node_modules_dep_path_instance = app.clone().node_modules_dir.clone();
-            let pkg_name = package.clone().name;
-            let pkg_name_instance = package.clone().name;
+        std::fs::create_dir_all(target.parent().unwrap()).map_err(VoltError::CreateDirError)?;
 
-            futures::try_join!(
-                tokio::task::spawn_blocking(move || {
-                    // Extract the data into extract_directory
+        entry
+            .unpack(&target)
+            .map_err(|e| VoltError::WriteFileError {
+                source: e,
+                name: target.to_string_lossy().to_string(),
+            })?;
 
-                    let node_gz_decoder = GzDecoder::new(&**bytes_ref);
+        apply_entry_mode(&target, mode, is_dir);
+    }
 
-                    let mut node_archive = Archive::new(node_gz_decoder);
+    let bin = match &manifest["bin"] {
+        // named after the package without its scope
+        serde_json::Value::String(file) => {
+            let mut bin = HashMap::new();
+            bin.insert(name.rsplit('/').next().unwrap().to_string(), file.clone());
+            Some(bin)
+        }
+        serde_json::Value::Object(commands) => Some(
+            commands
+                .iter()
+                .filter_map(|(command, file)| Some((command.clone(), file.as_str()?.to_string())))
+                .collect(),
+        ),
+        _ => None,
+    };
 
-                    for entry in node_archive.entries().unwrap() {
-                        let mut entry = entry.unwrap();
-                        let path = entry.path().unwrap();
-                        let mut new_path = PathBuf::new();
+    mark_bins_executable(&staging.join(name), &bin);
 
-                        for component in path.components() {
-                            if component.as_os_str() == "package" {
-                                new_path.push(Component::Normal(OsStr::new(&pkg_name)));
-                            } else {
-                                new_path.push(component)
-                            }
-                        }
-
-                        std::fs::create_dir_all(
-                            node_modules_dep_path_instance
-                                .to_path_buf()
-                                .join(&new_path)
-                                .parent()
-                                .unwrap(),
-                        )
-                        .unwrap();
-
-                        match entry
-                            .unpack(node_modules_dep_path_instance.to_path_buf().join(&new_path))
-                        {
-                            Ok(_v) => {}
-                            Err(_err) => {}
-                        }
-                    }
-                }),
-                tokio::task::spawn_blocking(move || {
-                    let gz_decoder = GzDecoder::new(&**bytes);
+    write_index(&staging)?;
+    commit_store_directory(&staging, &destination)?;
 
-                    let mut archive = Archive::new(gz_decoder);
+    Ok(StoredTarball {
+        name: name.to_string(),
+        version: version.to_string(),
+        integrity,
+        added: true,
+    })
+}
 
-                    for entry in archive.entries().unwrap() {
-                        let mut entry = entry.unwrap();
-                        let path = entry.path().unwrap();
-                        let mut new_path = PathBuf::new();
+/// Reads the top-level package.json of a tarball, `package/package.json` in npm's layout.
+fn read_tarball_manifest(
+    data: &[u8],
+    format: TarballFormat,
+    path: &str,
+) -> Result<serde_json::Value> {
+    let corrupt = |_| VoltError::CorruptTarball {
+        package: path.to_string(),
+    };
+
+    let mut archive = Archive::new(format.reader(data));
+
+    for entry in archive.entries().map_err(corrupt)? {
+        let mut entry = entry.map_err(corrupt)?;
+        let entry_path = entry.path().map_err(corrupt)?.into_owned();
+
+        let components = entry_path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect::<Vec<_>>();
+
+        if components.len() != 2 || components[1].as_os_str() != "package.json" {
+            continue;
+        }
 
-                        for component in path.components() {
-                            if component.as_os_str() == "package" {
-                                new_path.push(Component::Normal(OsStr::new(&pkg_name_instance)));
-                            } else {
-                                new_path.push(component)
-                            }
-                        }
-
-                        std::fs::create_dir_all(
-                            extract_directory_instance
-                                .to_path_buf()
-                                .join(&new_path)
-                                .parent()
-                                .unwrap(),
-                        )
-                        .unwrap();
-
-                        match entry.unpack(extract_directory_instance.to_path_buf().join(&new_path))
-                        {
-                            Ok(_v) => {}
-                            Err(err) => {
-                                let code = err.raw_os_error().unwrap();
-
-                                if code == 5 {
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                })
-            )
-            .unwrap();
-        } else {
-            return Err(VoltError::ChecksumVerificationError)?;
+        let mut manifest = String::new();
+        entry.read_to_string(&mut manifest).map_err(corrupt)?;
+
+        if let Ok(manifest) = serde_json::from_str(&manifest) {
+            return Ok(manifest);
         }
     }
 
-    Ok(())
+    Err(VoltError::MissingTarballManifest {
+        path: path.to_string(),
+    })?
 }
 
-pub async fn download_tarball_create(
-    _app: &App,
-    package: &NpmPackage,
-    name: &str,
-) -> Result<String> {
-    let file_name = format!("{}-{}.tgz", name, package.dist_tags.get("latest").unwrap());
-    let temp_dir = temp_dir();
-
-    if !Path::new(&temp_dir.join("volt")).exists() {
-        std::fs::create_dir(Path::new(&temp_dir.join("volt")))
-            .map_err(VoltError::CreateDirError)?;
-    }
+/// File next to an extracted package in the store holding the sha512 volt computed for a
+/// package whose registry only publishes a sha1.
+fn upgraded_integrity_path(store_directory: &Path) -> PathBuf {
+    store_directory.join(".volt-integrity")
+}
 
-    if name.starts_with('@') && name.contains("__") {
-        let package_dir_loc;
+/// Adds the sha512 computed at download time to locked packages that only have a sha1, so
+/// later installs verify them with the stronger hash.
+///
+/// Returns the packages whose registry still only publishes sha1.
+pub fn upgrade_sha1_integrities(app: &App, lock_file: &mut LockFile) -> Vec<String> {
+    let mut sha1_only = vec![];
 
-        if cfg!(target_os = "windows") {
-            // Check if C:\Users\username\.volt exists
-            package_dir_loc = format!(
-                r"{}\.volt\{}",
-                std::env::var("USERPROFILE").unwrap(),
-                name.split("__").collect::<Vec<&str>>()[0]
-            );
-        } else {
-            // Check if ~/.volt\packagename exists
-            package_dir_loc = format!(
-                r"{}\.volt\{}",
-                std::env::var("HOME").unwrap(),
-                name.split("__").collect::<Vec<&str>>()[0]
-            );
+    for dependency in lock_file.dependencies.values_mut() {
+        if !is_sha1_only(&dependency.integrity) {
+            continue;
         }
 
-        if !Path::new(&package_dir_loc).exists() {
-            create_dir_all(&package_dir_loc).await.unwrap();
+        let directory = store_directory_for(app, &dependency.name, &dependency.version);
+
+        if let Ok(strong) = read_to_string(upgraded_integrity_path(&directory)) {
+            // sha512-... sha1-..., verification picks the strongest
+            dependency.integrity = format!("{} {}", strong.trim(), dependency.integrity);
         }
+
+        sha1_only.push(format!("{}@{}", dependency.name, dependency.version));
     }
 
-    let path;
+    sha1_only.sort();
+    sha1_only
+}
 
-    if cfg!(target_os = "windows") {
-        path = temp_dir.join(format!(r"volt\{}", file_name));
-    } else {
-        path = temp_dir.join(format!(r"volt/{}", file_name));
+/// Warns about packages whose registry only publishes a sha1 integrity.
+pub fn print_sha1_warning(packages: &[String]) {
+    if packages.is_empty() {
+        return;
     }
 
-    let path_str = path.to_string_lossy().to_string();
-    let package_version = package
-        .versions
-        .get(package.dist_tags.get("latest").unwrap())
-        .unwrap();
-
-    let bytes = std::fs::read(path_str.clone()).unwrap();
+    println!(
+        "{}: {} packages only publish a sha1 integrity, volt locked a sha512 it computed: {}",
+        " warn ".black().bright_yellow(),
+        packages.len(),
+        packages.join(", ").bright_black()
+    );
+}
 
-    // Corrupt tar files may cause issues
-    // if let Ok(hash) = App::calc_hash(&bytes::Bytes::from(bytes)) {
-    //     // File exists, make sure it's not corrupted
-    //     if hash
-    //         == package
-    //             .versions
-    //             .get(package.dist_tags.get("latest").unwrap())
-    //             .unwrap()
-    //             .dist
-    //             .shasum
-    //     {
-    //         return Ok(path_str);
-    //     }
-    // }
+/// Removes what an interrupted install left behind for packages that never finished
+/// installing: their directories in each `node_modules` they go in, and their copies in the
+/// volt store.
+///
+/// This is best effort, anything it misses is caught by [`sweep_volt_dir`] on the next install.
+pub fn clean_up_partial_installs<'a>(
+    app: &App,
+    packages: impl Iterator<Item = (&'a VoltPackage, &'a [PathBuf])>,
+) {
+    for (package, node_modules_dirs) in packages {
+        for node_modules_dir in node_modules_dirs {
+            std::fs::remove_dir_all(node_modules_dir.join(&package.name)).ok();
+        }
 
-    let tarball = package_version.dist.tarball.replace("https", "http");
+        std::fs::remove_dir_all(store_directory(app, package)).ok();
+    }
+}
 
-    let res = reqwest::get(tarball).await.unwrap();
+/// Removes entries in the volt directory left behind by an interrupted install.
+///
+/// An entry is incomplete when it's empty or has no readable `package.json`, and scope
+/// directories (`~/.volt/@types`) are removed once they no longer hold any packages.
+/// Staging directories of extractions that never finished are removed as well; other
+/// dot-prefixed entries (`~/.volt/.links`) belong to volt itself and are left alone.
+/// ## Returns
+/// * `Result<usize>` - the number of entries removed
+pub fn sweep_volt_dir(volt_dir: &Path) -> Result<usize> {
+    let mut removed = 0;
+
+    let entries = match std::fs::read_dir(volt_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if !path.is_dir() {
+            continue;
+        }
 
-    let bytes = res.bytes().await.unwrap();
+        if name.starts_with(STORE_STAGING_PREFIX) {
+            remove_volt_entry(&path)?;
+            removed += 1;
+        } else if name.starts_with('.') {
+            continue;
+        } else if name.starts_with('@') {
+            removed += sweep_volt_dir(&path)?;
+
+            // the scope directory itself is empty once all of its packages are gone
+            if std::fs::read_dir(&path)
+                .map(|mut scoped| scoped.next().is_none())
+                .unwrap_or(false)
+            {
+                remove_volt_entry(&path)?;
+            }
+        } else if !has_package_manifest(&path) {
+            remove_volt_entry(&path)?;
+            removed += 1;
+        }
+    }
 
-    // App::calc_hash(&bytes)?;
+    Ok(removed)
+}
 
-    Ok(path_str)
+fn remove_volt_entry(path: &Path) -> Result<()> {
+    Ok(
+        std::fs::remove_dir_all(path).map_err(|e| VoltError::RemoveDirError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?,
+    )
 }
 
-pub fn get_basename(path: &'_ str) -> Cow<'_, str> {
-    let sep: char;
-    if cfg!(target_os = "windows") {
-        sep = '\\';
+/// Applies a tarball entry's mode the way npm normalizes it, so extracted files don't depend
+/// on the archive's owner bits or the user's umask: directories and files with any executable
+/// bit become `755`, everything else `644`.
+#[cfg(unix)]
+fn apply_entry_mode(path: &Path, mode: u32, is_dir: bool) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = if is_dir || mode & 0o111 != 0 {
+        0o755
     } else {
-        sep = '/';
-    }
-    let mut pieces = path.rsplit(sep);
+        0o644
+    };
 
-    match pieces.next() {
-        Some(p) => p.into(),
-        None => path.into(),
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).ok();
+}
+
+#[cfg(windows)]
+fn apply_entry_mode(_path: &Path, _mode: u32, _is_dir: bool) {}
+
+/// Marks the files listed in a package's `bin` as executable, since many tarballs ship them as `644`.
+#[cfg(unix)]
+fn mark_bins_executable(package_directory: &Path, bin: &Option<HashMap<String, String>>) {
+    use std::os::unix::fs::PermissionsExt;
+
+    for file in bin.iter().flat_map(|bin| bin.values()) {
+        std::fs::set_permissions(
+            package_directory.join(file),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .ok();
     }
 }
 
-/// Gets a config key from git using the git cli.
-/// Uses `gitoxide` to read from your git configuration.
-pub fn get_git_config(app: &App, key: &str) -> Option<String> {
-    match key {
-        "user.name" => {
-            let config_path = app.home_dir.join(".gitconfig");
+#[cfg(windows)]
+fn mark_bins_executable(_package_directory: &Path, _bin: &Option<HashMap<String, String>>) {}
+
+/// Where a tarball entry is extracted to, relative to the extraction directory.
+///
+/// npm tarballs put everything under `package/`, but some (DefinitelyTyped's `@types/*`
+/// among them) use another top-level directory, so the first component is replaced with the
+/// package name whatever it's called: `node/index.d.ts` -> `@types/node/index.d.ts`. A
+/// directory named `package` deeper in the tarball is left alone, and `..` is dropped.
+fn entry_destination(path: &Path, package_name: &str) -> PathBuf {
+    let mut destination = PathBuf::from(package_name);
+
+    destination.extend(
+        path.components()
+            .skip(1)
+            .filter(|component| matches!(component, Component::Normal(_))),
+    );
+
+    destination
+}
 
-            if !config_path.exists() {
-                return None;
-            } else {
-                let data = read_to_string(config_path).ok()?;
+/// downloads tarball file from package
+/// Downloads a tarball through a partial file in the store's temp directory.
+///
+/// When the connection drops mid-download and the server advertises `Accept-Ranges: bytes`,
+/// the download resumes from the last byte received with a `Range` request; otherwise it
+/// starts over. Each chunk is hashed as it arrives, and the tarball is only returned once it
+/// matches the package's integrity, decoded first when the server compressed it again.
+///
+/// Tarballs over `limit` bytes are refused from their `Content-Length` before anything is
+/// read, or as soon as the body passes the limit when the server doesn't send one.
+async fn fetch_tarball(
+    url: &str,
+    token: Option<&str>,
+    headers: &[(String, String)],
+    package: &VoltPackage,
+    tmp_dir: &Path,
+    limit: u64,
+) -> Result<(bytes::Bytes, reqwest::Version)> {
+    let integrity = parse_integrity(&package.integrity)?;
+    let algorithm = integrity.pick_algorithm();
+
+    let partial = tmp_dir.join(format!(
+        "volt-{}-{}.tgz.part",
+        package.name.replace('/', "+"),
+        package.version
+    ));
+
+    let write_error = |e| VoltError::WriteFileError {
+        source: e,
+        name: partial.to_string_lossy().to_string(),
+    };
+
+    let too_large = |size| {
+        std::fs::remove_file(&partial).ok();
+
+        VoltError::TarballTooLarge {
+            package: package.name.clone(),
+            size,
+            limit,
+        }
+    };
 
-                let config = GitConfig::from(Parser::try_from(data.as_str()).ok()?);
-                let value = config.get_raw_value("user", None, "name").ok()?;
+    let mut file = File::create(&partial).map_err(write_error)?;
+    let mut hasher = IntegrityOpts::new().algorithm(algorithm);
+    let mut received: u64 = 0;
+    let mut retries = 0;
 
-                return Some(String::from_utf8_lossy(&value).to_owned().to_string());
-            }
+    let (http_version, encoding) = loop {
+        THROTTLE.wait().await;
+
+        // held until the body has been read
+        let _socket = SOCKETS.acquire(url).await;
+
+        let mut request = TARBALL_CLIENT.get(url);
+
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
         }
-        "user.email" => {
-            let config_path = app.home_dir.join(".gitconfig");
 
-            if !config_path.exists() {
-                return None;
-            } else {
-                let data = read_to_string(config_path).ok()?;
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
 
-                let config = GitConfig::from(Parser::try_from(data.as_str()).ok()?);
-                let value = config.get_raw_value("user", None, "email").ok()?;
+        if received > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", received));
+        }
 
-                return Some(String::from_utf8_lossy(&value).to_owned().to_string());
+        let response = request.send().await;
+
+        // rate limited, back off together with every other request
+        if let Ok(response) = &response {
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && retries < MAX_RETRIES {
+                THROTTLE.back_off(retry_after(
+                    response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok()),
+                ));
+                CONCURRENCY.back_off();
+
+                retries += 1;
+                continue;
             }
         }
-        "repository.url" => {
-            let remote_config_path = app.current_dir.join(".git").join("config");
 
-            if !remote_config_path.exists() {
-                let data = read_to_string(remote_config_path).ok()?;
+        let mut response = match response.and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            Err(e) if retries < MAX_RETRIES => {
+                if e.is_timeout() {
+                    CONCURRENCY.back_off();
+                }
 
-                let config = GitConfig::from(Parser::try_from(data.as_str()).ok()?);
-                let value = config.get_raw_value("remote", Some("origin"), "url").ok()?;
+                retries += 1;
+                continue;
+            }
+            Err(e) => {
+                return Err(VoltError::TarballDownloadError {
+                    source: e,
+                    name: package.name.clone(),
+                }
+                .into())
+            }
+        };
+
+        // the server ignored the range and sent the whole tarball again
+        if received > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            file.set_len(0).map_err(write_error)?;
+            file.seek(SeekFrom::Start(0)).map_err(write_error)?;
+            hasher = IntegrityOpts::new().algorithm(algorithm);
+            received = 0;
+        }
 
-                return Some(String::from_utf8_lossy(&value).to_owned().to_string());
-            } else {
-                return None;
+        // a range response's length only covers what's left
+        if let Some(length) = response.content_length() {
+            if received + length > limit {
+                return Err(too_large(received + length).into());
             }
         }
-        _ => None,
-    }
-}
 
-// Windows Function
-/// Enable ansi support and colors
-#[cfg(windows)]
-pub fn enable_ansi_support() -> Result<(), u32> {
-    // ref: https://docs.microsoft.com/en-us/windows/console/console-virtual-terminal-sequences#EXAMPLE_OF_ENABLING_VIRTUAL_TERMINAL_PROCESSING @@ https://archive.is/L7wRJ#76%
+        let resumable = response
+            .headers()
+            .get(reqwest::header::ACCEPT_RANGES)
+            .map(|ranges| ranges == "bytes")
+            .unwrap_or(false);
+
+        let encoding = response
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .and_then(|encoding| encoding.to_str().ok())
+            .map(String::from);
+
+        let finished = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    received += chunk.len() as u64;
+
+                    if received > limit {
+                        return Err(too_large(received).into());
+                    }
 
+                    hasher.input(&chunk);
+                    file.write_all(&chunk).map_err(write_error)?;
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        match finished {
+            Ok(()) => break (response.version(), encoding),
+            Err(e) if retries < MAX_RETRIES => {
+                if e.is_timeout() {
+                    CONCURRENCY.back_off();
+                }
+
+                retries += 1;
+
+                if !resumable {
+                    file.set_len(0).map_err(write_error)?;
+                    file.seek(SeekFrom::Start(0)).map_err(write_error)?;
+                    hasher = IntegrityOpts::new().algorithm(algorithm);
+                    received = 0;
+                }
+            }
+            Err(e) => {
+                return Err(VoltError::TarballDownloadError {
+                    source: e,
+                    name: package.name.clone(),
+                }
+                .into())
+            }
+        }
+    };
+
+    drop(file);
+
+    CONCURRENCY.record(received);
+
+    let verified = integrity.matches(&hasher.result()).is_some();
+
+    // Some servers label a .tgz as `Content-Encoding: gzip` without compressing it
+    // again, so the body is only decoded when it doesn't match as it is
+    if !verified && encoding.is_none() {
+        std::fs::remove_file(&partial).ok();
+
+        return Err(VoltError::ChecksumVerificationError.into());
+    }
+
+    let bytes = std::fs::read(&partial).map_err(|e| VoltError::ReadFileError {
+        source: e,
+        name: partial.to_string_lossy().to_string(),
+    });
+
+    std::fs::remove_file(&partial).ok();
+
+    let bytes = bytes?;
+
+    match encoding {
+        Some(encoding) if !verified => {
+            let decoded = decode_content(&bytes, &encoding, &package.name, limit)?;
+            check_integrity(&integrity, &decoded)?;
+
+            Ok((bytes::Bytes::from(decoded), http_version))
+        }
+        _ => Ok((bytes::Bytes::from(bytes), http_version)),
+    }
+}
+
+/// Whether `url` is fetched over https.
+pub fn is_https(url: &str) -> bool {
+    url.get(..8)
+        .map(|scheme| scheme.eq_ignore_ascii_case("https://"))
+        .unwrap_or(false)
+}
+
+/// Refuses a tarball that isn't served over https unless `allow_insecure` is set.
+///
+/// Anyone between volt and a plain http mirror could swap the tarball for another one along
+/// with the integrity it's checked against, so http has to be allowed explicitly.
+pub fn check_tarball_url(package: &str, url: &str, allow_insecure: bool) -> Result<()> {
+    if !is_https(url) && !allow_insecure {
+        return Err(VoltError::InsecureTarballUrl {
+            package: package.to_string(),
+            url: url.to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Downloads a package's tarball and verifies it against the package's integrity, under the
+/// same url, credential and size rules an install uses. Nothing is written to the store.
+pub async fn fetch_verified_tarball(
+    app: &App,
+    package: &VoltPackage,
+    reporter: &dyn Reporter,
+) -> Result<(bytes::Bytes, reqwest::Version)> {
+    // dist.tarball is used as-is (it may point at a different host than the registry),
+    // unless a registry override replaces the default registry's host
+    let url = app.tarball_url(&package.name, &package.tarball);
+
+    check_tarball_url(&package.name, &url, app.allow_insecure_tarballs())?;
+
+    if !is_https(&url) {
+        reporter.report(InstallEvent::Warning {
+            message: format!("fetching {} over plain http", url),
+        });
+    }
+
+    // None over plain http, credentials are never sent without tls
+    let token = app.config.auth_token(&url);
+
+    // Get Tarball File, verified against the strongest hash in its integrity before
+    // anything is written, so a corrupt tarball never ends up in ~/.volt
+    fetch_tarball(
+        &url,
+        token.as_deref(),
+        &app.request_headers(),
+        package,
+        &app.tmp_dir()?,
+        app.max_tarball_size()?,
+    )
+    .await
+}
+
+pub async fn download_tarball(
+    app: &App,
+    package: &VoltPackage,
+    stats: &InstallStats,
+    node_modules_dir: &Path,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let package_instance = package.clone();
+    let linker = app.node_linker()?;
+
+    // --fetch-only fills the store and leaves node_modules alone
+    let fetch_only = app.has_flag("fetch-only");
+
+    // @types/eslint
+    if let Some(scope) = package_scope(&package_instance.name) {
+        let package_directory_location = app.volt_dir.join(scope);
+
+        if !Path::new(&package_directory_location).exists() {
+            create_dir_all(&package_directory_location)
+                .await
+                .map_err(VoltError::CreateDirError)?;
+        }
+    }
+
+    // location of extracted package
+    let loc = store_directory(app, package).join(&package.name);
+
+    // a cached package with an unreadable manifest (from an older volt version) is
+    // treated as a cache miss and extracted again, as is one whose files changed since it
+    // was extracted when --verify-store asks to check
+    if store_directory(app, package).exists()
+        && (!is_valid_cache_entry(&loc)
+            || (app.has_setting("verify-store", "verify-store")
+                && matches!(
+                    check_entry(&store_directory(app, package)),
+                    EntryState::Corrupt(_)
+                )))
+    {
+        remove_volt_entry(&store_directory(app, package))?;
+    }
+
+    let node_modules_dep_path = node_modules_dir.join(&package.name);
+
+    // whatever version was installed here before (react 16 upgraded to 17) mustn't leave
+    // files the new one doesn't have, or keep its own versions of files both have
+    if !fetch_only {
+        clear_package_dir(&node_modules_dep_path).map_err(|e| VoltError::LinkError {
+            source: e,
+            name: package.name.clone(),
+        })?;
+    }
+
+    // already extracted by an earlier install, materialize it from the store
+    if loc.exists() {
+        let source = loc.clone();
+        let destination = node_modules_dep_path.clone();
+
+        if !fetch_only {
+            tokio::task::spawn_blocking(move || match linker {
+                NodeLinker::Copy => copy_package_files(&source, &destination),
+                NodeLinker::Hardlink => link_package_files(&source, &destination),
+            })
+            .await
+            .unwrap()
+            .map_err(|e| VoltError::LinkError {
+                source: e,
+                name: package.name.clone(),
+            })?;
+        }
+
+        stats.cache_hit(directory_size(&loc));
+    } else {
+        reporter.report(InstallEvent::Downloading {
+            name: package.name.clone(),
+            version: package.version.clone(),
+        });
+
+        let (bytes, http_version) = fetch_verified_tarball(app, package, reporter).await?;
+
+        let http2 = http_version == reqwest::Version::HTTP_2;
+
+        // .tgz almost always, but some registries serve a plain .tar
+        let format = TarballFormat::detect(&bytes, &package.name)?;
+
+        // the registry only vouches for this tarball with sha1, keep a sha512 of it as well
+        let upgraded_integrity = if is_sha1_only(&package.integrity) {
+            Some(integrity_of(&bytes, Algorithm::Sha512)?)
+        } else {
+            None
+        };
+
+        // Create node_modules
+        if !fetch_only {
+            create_dir_all(node_modules_dir).await.unwrap();
+        }
+
+        // Directory to extract tarball to, committed to the store once it's complete
+        let extract_directory = store_directory(app, package);
+        let staging = staging_directory(&extract_directory);
+
+        // left by an earlier attempt of this process
+        std::fs::remove_dir_all(&staging).ok();
+
+        // Initialize tarfile decoder while directly passing in bytes
+
+        let downloaded = bytes.len() as u64;
+
+        let bytes = Arc::new(bytes);
+
+        let bytes_ref = bytes.clone();
+
+        let extract_directory_instance = staging.clone();
+
+        let node_modules_dep_path_instance = node_modules_dir.to_path_buf();
+        let pkg_name = package.clone().name;
+        let pkg_name_instance = package.clone().name;
+        let bin = package.bin.clone();
+        let bin_instance = package.bin.clone();
+
+        // Extract the data into the volt directory
+        let store_extraction = spawn_extract(&package.name, move || {
+            unpack_tarball(
+                &bytes,
+                format,
+                &extract_directory_instance,
+                &pkg_name_instance,
+                &bin_instance,
+            )
+        });
+
+        match linker {
+            _ if fetch_only => store_extraction.await?,
+            NodeLinker::Copy => {
+                // Extract a second copy straight into node_modules
+                let node_modules_extraction = spawn_extract(&package.name, move || {
+                    unpack_tarball(
+                        &bytes_ref,
+                        format,
+                        &node_modules_dep_path_instance,
+                        &pkg_name,
+                        &bin,
+                    )
+                });
+
+                let (node_modules, store) =
+                    futures::join!(node_modules_extraction, store_extraction);
+
+                node_modules?;
+                store?;
+            }
+            NodeLinker::Hardlink => {
+                store_extraction.await?;
+
+                // hard links stay valid when the staging directory is renamed
+                let source = staging.join(&package.name);
+
+                tokio::task::spawn_blocking(move || {
+                    link_package_files(&source, &node_modules_dep_path)
+                })
+                .await
+                .unwrap()
+                .map_err(|e| VoltError::LinkError {
+                    source: e,
+                    name: package.name.clone(),
+                })?;
+            }
+        }
+
+        if let Some(integrity) = upgraded_integrity {
+            let path = upgraded_integrity_path(&staging);
+
+            std::fs::write(&path, integrity.to_string()).map_err(|e| {
+                VoltError::WriteFileError {
+                    source: e,
+                    name: path.to_string_lossy().to_string(),
+                }
+            })?;
+        }
+
+        let index_directory = staging.clone();
+
+        tokio::task::spawn_blocking(move || write_index(&index_directory))
+            .await
+            .unwrap()?;
+
+        commit_store_directory(&staging, &extract_directory)?;
+
+        reporter.report(InstallEvent::Extracted {
+            name: package.name.clone(),
+            version: package.version.clone(),
+        });
+
+        stats.cache_miss(downloaded, directory_size(&extract_directory), http2);
+    }
+
+    Ok(())
+}
+
+pub async fn download_tarball_create(
+    _app: &App,
+    package: &NpmPackage,
+    name: &str,
+) -> Result<String> {
+    let file_name = format!("{}-{}.tgz", name, package.dist_tags.get("latest").unwrap());
+    let temp_dir = temp_dir();
+
+    std::fs::create_dir_all(temp_dir.join("volt")).map_err(VoltError::CreateDirError)?;
+
+    if name.starts_with('@') && name.contains("__") {
+        let package_dir_loc;
+
+        if cfg!(target_os = "windows") {
+            // Check if C:\Users\username\.volt exists
+            package_dir_loc = format!(
+                r"{}\.volt\{}",
+                std::env::var("USERPROFILE").unwrap(),
+                name.split("__").collect::<Vec<&str>>()[0]
+            );
+        } else {
+            // Check if ~/.volt\packagename exists
+            package_dir_loc = format!(
+                r"{}\.volt\{}",
+                std::env::var("HOME").unwrap(),
+                name.split("__").collect::<Vec<&str>>()[0]
+            );
+        }
+
+        if !Path::new(&package_dir_loc).exists() {
+            create_dir_all(&package_dir_loc).await.unwrap();
+        }
+    }
+
+    let path;
+
+    if cfg!(target_os = "windows") {
+        path = temp_dir.join(format!(r"volt\{}", file_name));
+    } else {
+        path = temp_dir.join(format!(r"volt/{}", file_name));
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    let package_version = package
+        .versions
+        .get(package.dist_tags.get("latest").unwrap())
+        .unwrap();
+
+    let bytes = std::fs::read(path_str.clone()).unwrap();
+
+    // Corrupt tar files may cause issues
+    // if let Ok(hash) = App::calc_hash(&bytes::Bytes::from(bytes)) {
+    //     // File exists, make sure it's not corrupted
+    //     if hash
+    //         == package
+    //             .versions
+    //             .get(package.dist_tags.get("latest").unwrap())
+    //             .unwrap()
+    //             .dist
+    //             .shasum
+    //     {
+    //         return Ok(path_str);
+    //     }
+    // }
+
+    let tarball = package_version.dist.tarball.replace("https", "http");
+
+    let res = reqwest::get(tarball).await.unwrap();
+
+    let bytes = res.bytes().await.unwrap();
+
+    // App::calc_hash(&bytes)?;
+
+    Ok(path_str)
+}
+
+pub fn get_basename(path: &'_ str) -> Cow<'_, str> {
+    let sep: char;
+    if cfg!(target_os = "windows") {
+        sep = '\\';
+    } else {
+        sep = '/';
+    }
+    let mut pieces = path.rsplit(sep);
+
+    match pieces.next() {
+        Some(p) => p.into(),
+        None => path.into(),
+    }
+}
+
+/// Reads `section[.subsection].key` from a git config file.
+///
+/// Git metadata only fills in defaults, so a file that's missing, unreadable or invalid is
+/// skipped rather than failing the command; `--verbose` says why.
+fn read_git_config(
+    app: &App,
+    path: &Path,
+    section: &str,
+    subsection: Option<&str>,
+    key: &str,
+) -> Option<String> {
+    let skip = |reason: String| {
+        if app.has_flag("verbose") {
+            println!(
+                "{}: skipping {}: {}",
+                "info".bright_purple(),
+                path.display(),
+                reason
+            );
+        }
+    };
+
+    let data = match read_to_string(path) {
+        Ok(data) => data,
+        // no global config or not a git repository, nothing worth mentioning
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            skip(e.to_string());
+            return None;
+        }
+    };
+
+    let config = match Parser::try_from(data.as_str()) {
+        Ok(parser) => GitConfig::from(parser),
+        Err(_) => {
+            skip(String::from("not a valid git config"));
+            return None;
+        }
+    };
+
+    let value = config.get_raw_value(section, subsection, key).ok()?;
+
+    Some(String::from_utf8_lossy(&value).to_string())
+}
+
+/// Gets a config key from git using the git cli.
+/// Uses `gitoxide` to read from your git configuration.
+///
+/// `user.name` and `user.email` come from `~/.gitconfig`, `remote.origin.url` from the
+/// project's `.git/config`. Anything that can't be read is `None`.
+pub fn get_git_config(app: &App, key: &str) -> Option<String> {
+    match key {
+        "user.name" => read_git_config(app, &app.home_dir.join(".gitconfig"), "user", None, "name"),
+        "user.email" => {
+            read_git_config(app, &app.home_dir.join(".gitconfig"), "user", None, "email")
+        }
+        "remote.origin.url" => read_git_config(
+            app,
+            &app.current_dir.join(".git").join("config"),
+            "remote",
+            Some("origin"),
+            "url",
+        ),
+        _ => None,
+    }
+}
+
+// Windows Function
+/// Enable ansi support and colors
+#[cfg(windows)]
+pub fn enable_ansi_support() -> Result<(), u32> {
+    // ref: https://docs.microsoft.com/en-us/windows/console/console-virtual-terminal-sequences#EXAMPLE_OF_ENABLING_VIRTUAL_TERMINAL_PROCESSING @@ https://archive.is/L7wRJ#76%
+
+    use std::ffi::OsStr;
     use std::iter::once;
     use std::os::windows::ffi::OsStrExt;
     use std::ptr::null_mut;
@@ -743,71 +1735,98 @@ pub fn enable_ansi_support() -> Result<(), u32> {
     Ok(())
 }
 
+/// The `node_modules/.bin` entries of a package: each command name with the file it runs,
+/// relative to `node_modules/.bin`.
+///
+/// The file is found from where the package is installed and the path its `bin` declares, so
+/// `@angular/cli`'s `ng` runs `../@angular/cli/bin/ng.js`. Entries that would point outside
+/// the package are skipped.
+fn bin_targets(package: &VoltPackage) -> Vec<(String, PathBuf)> {
+    let mut targets = vec![];
+
+    for (command, file) in package.bin.iter().flatten() {
+        // npm drops any scope from command names
+        let command = command.rsplit('/').next().unwrap_or_default();
+        let file = Path::new(file);
+
+        let escapes = file
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+
+        if command.is_empty() || command.starts_with('.') || escapes {
+            continue;
+        }
+
+        let mut target = PathBuf::from("..");
+        target.push(&package.name);
+        target.extend(
+            file.components()
+                .filter(|component| matches!(component, Component::Normal(_))),
+        );
+
+        targets.push((command.to_string(), target));
+    }
+
+    targets
+}
+
+/// Creates a `.cmd` shim in `node_modules/.bin` for each binary of the package.
 #[cfg(windows)]
-/// Generates the binary and other required scripts for the package
-pub fn generate_script(app: &Arc<App>, package: &VoltPackage) {
-    // Create node_modules/scripts if it doesn't exist
-    if !Path::new("node_modules/.bin").exists() {
-        // Create the binary directory
-        std::fs::create_dir_all("node_modules/.bin").unwrap();
-    }
-
-    // Create binary scripts for the package if they exist.
-    if package.bin.is_some() {
-        let bin = package.bin.as_ref().unwrap();
-
-        let k = bin.keys().next().unwrap();
-        let v = bin.values().next().unwrap();
-
-        let command = format!(
-            r#"
-            @IF EXIST "%~dp0\node.exe" (
-                "%~dp0\node.exe"  "%~dp0\..\{}\{}" %*
-                ) ELSE (
-                    @SETLOCAL
-                    @SET PATHEXT=%PATHEXT:;.JS;=;%
-                    node  "%~dp0\..\{}\{}" %*
-                    )"#,
-            k, v, k, v
-        )
-        .replace(r"%~dp0\..", format!("{}", app.volt_dir.display()).as_str());
+pub fn generate_script(app: &App, package: &VoltPackage) -> Result<()> {
+    let bin_directory = app.node_modules_dir.join(".bin");
+
+    for (command, target) in bin_targets(package) {
+        std::fs::create_dir_all(&bin_directory).map_err(VoltError::CreateDirError)?;
+
+        // %~dp0 is the directory of the shim, so the project can be moved
+        let target = target.to_string_lossy().replace('/', r"\");
+
+        let shim = format!(
+            r#"@IF EXIST "%~dp0\node.exe" (
+  "%~dp0\node.exe" "%~dp0\{target}" %*
+) ELSE (
+  @SETLOCAL
+  @SET PATHEXT=%PATHEXT:;.JS;=;%
+  node "%~dp0\{target}" %*
+)
+"#,
+            target = target
+        );
 
-        let mut f = File::create(format!(r"node_modules/.bin/{}.cmd", k)).unwrap();
-        f.write_all(command.as_bytes()).unwrap();
+        std::fs::write(bin_directory.join(format!("{}.cmd", command)), shim).map_err(|e| {
+            VoltError::LinkError {
+                source: e,
+                name: package.name.clone(),
+            }
+        })?;
     }
+
+    Ok(())
 }
 
+/// Symlinks each binary of the package into `node_modules/.bin`.
+///
+/// The links are relative, so the project can be moved. The files they point to were
+/// already marked executable when the package was extracted.
 #[cfg(unix)]
-pub fn generate_script(app: &Arc<App>, package: &VoltPackage) {
-    // Create node_modules/scripts if it doesn't exist
-    if !Path::new("node_modules/scripts").exists() {
-        std::fs::create_dir_all("node_modules/scripts").unwrap();
-    }
-
-    // If the package has binary scripts, create them
-    if package.bin.is_some() {
-        let bin = package.bin.as_ref().unwrap();
-
-        let k = bin.keys().next().unwrap();
-        let v = bin.values().next().unwrap();
-
-        let command = format!(
-            r#"
-            node  "{}/.volt/{}/{}" %*
-            "#,
-            app.volt_dir.to_string_lossy(),
-            k,
-            v,
-        );
-        // .replace(r"%~dp0\..", format!("{}", app.volt_dir.display()).as_str());
-        let p = format!(r"node_modules/scripts/{}.sh", k);
-        let mut f = File::create(p.clone()).unwrap();
-        std::process::Command::new("chmod")
-            .args(&["+x", &p])
-            .spawn()
-            .unwrap();
-        f.write_all(command.as_bytes()).unwrap();
+pub fn generate_script(app: &App, package: &VoltPackage) -> Result<()> {
+    let bin_directory = app.node_modules_dir.join(".bin");
+
+    for (command, target) in bin_targets(package) {
+        std::fs::create_dir_all(&bin_directory).map_err(VoltError::CreateDirError)?;
+
+        let shim = bin_directory.join(&command);
+
+        // replaces the link of a version installed earlier
+        std::fs::remove_file(&shim).ok();
+
+        std::os::unix::fs::symlink(&target, &shim).map_err(|e| VoltError::LinkError {
+            source: e,
+            name: package.name.clone(),
+        })?;
     }
+
+    Ok(())
 }
 
 // Unix functions
@@ -816,25 +1835,93 @@ pub fn enable_ansi_support() -> Result<(), u32> {
     Ok(())
 }
 
-pub fn check_peer_dependency(_package_name: &str) -> bool {
-    false
+/// How a peer dependency stands against the tree being installed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerState {
+    Met,
+    /// Neither in the tree nor in node_modules.
+    Missing,
+    /// Only versions outside the peer's range are there.
+    Conflict(Vec<String>),
+}
+
+/// Checks a peer dependency (`react` or `react@^17.0.0`) against the tree being installed,
+/// then against what's already in node_modules for packages the tree doesn't include.
+pub fn check_peer_dependency(
+    app: &App,
+    tree: &HashMap<String, VoltPackage>,
+    peer: &str,
+) -> PeerState {
+    let (name, range) = split_dependency(peer);
+
+    let mut found: Vec<String> = tree
+        .values()
+        .filter(|package| package.name == name)
+        .map(|package| package.version.clone())
+        .collect();
+
+    if found.is_empty() {
+        let installed = read_to_string(app.node_modules_dir.join(name).join("package.json"))
+            .ok()
+            .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+            .and_then(|manifest| manifest["version"].as_str().map(String::from));
+
+        found.extend(installed);
+    }
+
+    if found.is_empty() {
+        return PeerState::Missing;
+    }
+
+    // no range or one volt can't read, any version will do
+    let range = match range.and_then(|range| range.parse::<VersionRange>().ok()) {
+        Some(range) => range,
+        None => return PeerState::Met,
+    };
+
+    let satisfied = found.iter().any(|version| {
+        version
+            .parse::<Version>()
+            .map(|version| range.satisfies(&version))
+            .unwrap_or(false)
+    });
+
+    if satisfied {
+        PeerState::Met
+    } else {
+        found.sort();
+        PeerState::Conflict(found)
+    }
 }
 
 /// package all steps for installation into 1 convinient function.
-pub async fn install_extract_package(app: &Arc<App>, package: &VoltPackage) -> Result<()> {
-    // if there's an error (most likely a checksum verification error) while using insecure http, retry.
-    if download_tarball(&app, &package, false).await.is_err() {
-        // use https instead
-        download_tarball(&app, &package, true)
-            .await
-            .unwrap_or_else(|_| {
-                println!("failed to download tarball");
-                std::process::exit(1);
-            });
+///
+/// The package is installed into each of `node_modules_dirs` in turn, after the first one
+/// it's materialized from the store.
+pub async fn install_extract_package(
+    app: &Arc<App>,
+    package: &VoltPackage,
+    stats: &InstallStats,
+    node_modules_dirs: &[PathBuf],
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    if app.has_setting("verify-signatures", "verify-signatures") {
+        verify_signatures(app, package).await?;
+    }
+
+    for node_modules_dir in node_modules_dirs {
+        download_tarball(app, package, stats, node_modules_dir, reporter).await?;
+    }
+
+    // only the hoisted version's bins go in node_modules/.bin
+    if node_modules_dirs.contains(&app.node_modules_dir) && !app.has_flag("fetch-only") {
+        generate_script(app, package)?;
     }
 
-    // generate the package's script
-    generate_script(&app, package);
+    reporter.report(InstallEvent::Linked {
+        name: package.name.clone(),
+        version: package.version.clone(),
+    });
 
     // let directory = &app
     //     .volt_dir
@@ -850,12 +1937,20 @@ pub async fn install_extract_package(app: &Arc<App>, package: &VoltPackage) -> R
 
 pub async fn fetch_dep_tree(
     packages: &Vec<Package>,
-    progress_bar: &ProgressBar,
+    cdn: &str,
+    headers: &[(String, String)],
+    reporter: &dyn Reporter,
 ) -> Result<(Vec<VoltResponse>, f32)> {
     let start = Instant::now();
+
+    // nothing to resolve, e.g. volt install in a project without dependencies
+    if packages.is_empty() {
+        return Ok((vec![], start.elapsed().as_secs_f32()));
+    }
+
     if packages.len() > 1 {
         Ok((
-            get_volt_response_multi(packages.clone(), progress_bar)
+            get_volt_response_multi(packages.clone(), cdn, headers, reporter)
                 .await
                 .into_iter()
                 .collect::<Result<Vec<_>>>()?,
@@ -863,12 +1958,441 @@ pub async fn fetch_dep_tree(
         ))
     } else {
         Ok((
-            vec![get_volt_response(packages[0].clone()).await?],
+            vec![get_volt_response(packages[0].clone(), cdn, headers).await?],
             start.elapsed().as_secs_f32(),
         ))
     }
 }
 
+/// Resolves packages into a single flattened dependency tree, keyed by `name@version`.
+///
+/// Packages that don't support the host (or `--force-platform`) platform are dropped, as are
+/// packages only needed as optional dependencies with `--no-optional`, and the tree is
+/// checked for cycles and excessive depth before it's returned.
+pub async fn resolve_dependencies(
+    app: &App,
+    packages: &Vec<Package>,
+    reporter: &dyn Reporter,
+) -> Result<(Vec<VoltResponse>, HashMap<String, VoltPackage>, f32)> {
+    reporter.report(InstallEvent::ResolutionStarted {
+        packages: packages.len(),
+    });
+
+    // Fetch pre-flattened dependency trees from the registry
+    let (responses, elapsed) =
+        fetch_dep_tree(packages, &app.volt_cdn(), &app.request_headers(), reporter).await?;
+
+    let mut dependencies: HashMap<String, VoltPackage> = HashMap::new();
+
+    for res in responses.iter() {
+        let current_version = res.versions.get(&res.version).unwrap();
+        dependencies.extend(current_version.to_owned());
+    }
+
+    prune_dependency_tree(app, packages, &mut dependencies, reporter)?;
+
+    reporter.report(InstallEvent::ResolutionFinished {
+        packages: dependencies.len(),
+        elapsed,
+    });
+
+    Ok((responses, dependencies, elapsed))
+}
+
+/// Drops the packages of a flattened tree that shouldn't be installed here and checks what's
+/// left, for a tree fresh from the registry as well as one read back from volt.lock.
+pub fn prune_dependency_tree(
+    app: &App,
+    packages: &[Package],
+    dependencies: &mut HashMap<String, VoltPackage>,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    // Skip packages that don't support the host (or forced) platform
+    let platform = match app.args.value_of("force-platform") {
+        Some(platform) => Platform::parse(platform)?,
+        None => Platform::host(),
+    };
+
+    let total = dependencies.len();
+    let mut unsupported: HashSet<String> = HashSet::new();
+
+    dependencies.retain(|_, package| {
+        let supported = platform.supports(package);
+
+        if !supported {
+            unsupported.insert(package.name.clone());
+        }
+
+        supported
+    });
+
+    check_required_dependencies(dependencies, &unsupported)?;
+
+    if dependencies.len() != total {
+        reporter.report(InstallEvent::Info {
+            message: format!(
+                "skipped {} packages not supported on {}-{}",
+                total - dependencies.len(),
+                platform.os,
+                platform.cpu
+            ),
+        });
+    }
+
+    if app.has_flag("no-optional") {
+        // only drop what nothing requires, a package can be optional for one dependent
+        // and required by another
+        let all = reachable_dependencies(packages, dependencies, true);
+        let required = reachable_dependencies(packages, dependencies, false);
+
+        let optional: Vec<String> = all.difference(&required).cloned().collect();
+
+        for key in optional.iter() {
+            dependencies.remove(key);
+        }
+
+        if !optional.is_empty() {
+            reporter.report(InstallEvent::Info {
+                message: format!(
+                    "skipped {} optional packages (--no-optional)",
+                    optional.len()
+                ),
+            });
+        }
+    }
+
+    let max_depth = app
+        .config
+        .get("max-depth")
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(MAX_DEPTH);
+
+    check_dependency_tree(packages, dependencies, max_depth)?;
+
+    Ok(())
+}
+
+/// Finds the `name@version` key of a dependency in the flattened tree.
+fn find_dependency_key<'a>(
+    tree: &'a HashMap<String, VoltPackage>,
+    keys: &HashMap<&'a str, &'a str>,
+    dependency: &str,
+) -> Option<&'a str> {
+    if let Some((key, _)) = tree.get_key_value(dependency) {
+        return Some(key.as_str());
+    }
+
+    keys.get(dependency).copied()
+}
+
+/// Collects the `name@version` keys reachable from the requested packages, following
+/// edges to optional dependencies only when `follow_optional` is set.
+fn reachable_dependencies(
+    roots: &[Package],
+    tree: &HashMap<String, VoltPackage>,
+    follow_optional: bool,
+) -> HashSet<String> {
+    let keys: HashMap<&str, &str> = tree
+        .iter()
+        .map(|(key, package)| (package.name.as_str(), key.as_str()))
+        .collect();
+
+    let mut reachable: HashSet<String> = HashSet::new();
+
+    let mut stack: Vec<&str> = roots
+        .iter()
+        .filter_map(|root| find_root_key(tree, root))
+        .collect();
+
+    while let Some(key) = stack.pop() {
+        if !reachable.insert(key.to_string()) {
+            continue;
+        }
+
+        let package = &tree[key];
+
+        for dependency in package.dependencies.as_deref().unwrap_or(&[]) {
+            if !follow_optional && package.is_optional_dependency(dependency) {
+                continue;
+            }
+
+            if let Some(child_key) = find_dependency_key(tree, &keys, dependency) {
+                stack.push(child_key);
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Works out which `node_modules` directories each package of a resolved tree goes in.
+///
+/// One version of each name is hoisted to the top level: the version that was requested,
+/// otherwise the one most packages depend on. Every other version is nested in the
+/// `node_modules` of each package depending on it, where node's resolution finds it first.
+/// Only nested packages are returned, keyed by `name@version`; everything else goes in
+/// `node_modules_dir`, the project's `node_modules`.
+pub fn plan_node_modules(
+    node_modules_dir: &Path,
+    roots: &[Package],
+    tree: &HashMap<String, VoltPackage>,
+) -> HashMap<String, Vec<PathBuf>> {
+    let keys: HashMap<&str, &str> = tree
+        .iter()
+        .map(|(key, package)| (package.name.as_str(), key.as_str()))
+        .collect();
+
+    // name@version -> the packages depending on it
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for (key, package) in tree.iter() {
+        for dependency in package.dependencies.as_deref().unwrap_or(&[]) {
+            if let Some(child) = find_dependency_key(tree, &keys, dependency) {
+                dependents.entry(child).or_default().push(key.as_str());
+            }
+        }
+    }
+
+    let mut versions: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for (key, package) in tree.iter() {
+        versions
+            .entry(package.name.as_str())
+            .or_default()
+            .push(key.as_str());
+    }
+
+    let requested: HashSet<&str> = roots
+        .iter()
+        .filter_map(|root| find_root_key(tree, root))
+        .collect();
+
+    let mut nested: Vec<&str> = vec![];
+
+    for candidates in versions
+        .values_mut()
+        .filter(|candidates| candidates.len() > 1)
+    {
+        candidates.sort_unstable();
+
+        let hoisted = candidates
+            .iter()
+            .copied()
+            .find(|key| requested.contains(key))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .copied()
+                    .max_by_key(|key| dependents.get(key).map(Vec::len).unwrap_or(0))
+            })
+            .unwrap();
+
+        nested.extend(candidates.iter().copied().filter(|key| *key != hoisted));
+    }
+
+    let mut placements: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    // a package nested under another nested package has to wait for its parent's placement,
+    // so keep passing over the rest until nothing changes
+    loop {
+        let before = placements.len();
+
+        for key in nested.iter().copied() {
+            if placements.contains_key(key) {
+                continue;
+            }
+
+            let parents = dependents.get(key).map(Vec::as_slice).unwrap_or(&[]);
+
+            if parents
+                .iter()
+                .any(|parent| nested.contains(parent) && !placements.contains_key(*parent))
+            {
+                continue;
+            }
+
+            let mut directories: Vec<PathBuf> = vec![];
+
+            for parent in parents {
+                let parent_directories = placements
+                    .get(*parent)
+                    .cloned()
+                    .unwrap_or_else(|| vec![node_modules_dir.to_path_buf()]);
+
+                directories.extend(
+                    parent_directories
+                        .iter()
+                        .map(|directory| directory.join(&tree[*parent].name).join("node_modules")),
+                );
+            }
+
+            placements.insert(key.to_string(), directories);
+        }
+
+        if placements.len() == before {
+            break;
+        }
+    }
+
+    // nested packages depending on each other in a cycle, nest them under the top level only
+    for key in nested {
+        if !placements.contains_key(key) {
+            let directories = dependents
+                .get(key)
+                .map(Vec::as_slice)
+                .unwrap_or(&[])
+                .iter()
+                .map(|parent| {
+                    node_modules_dir
+                        .join(&tree[*parent].name)
+                        .join("node_modules")
+                })
+                .collect();
+
+            placements.insert(key.to_string(), directories);
+        }
+    }
+
+    placements
+}
+
+/// Finds the `name@version` key of a requested package, whose name may differ in case
+/// from the canonical name in the tree. When the tree holds several versions of it, the
+/// requested one is the highest inside the requested range.
+fn find_root_key<'a>(tree: &'a HashMap<String, VoltPackage>, root: &Package) -> Option<&'a str> {
+    let name = normalize_name(&root.name);
+    let range = root
+        .version
+        .as_deref()
+        .and_then(|range| range.parse::<Range>().ok());
+
+    let mut candidates: Vec<(Option<Version>, &str)> = tree
+        .iter()
+        .filter(|(_, package)| normalize_name(&package.name) == name)
+        .map(|(key, package)| (package.version.parse::<Version>().ok(), key.as_str()))
+        .collect();
+
+    candidates.sort();
+
+    candidates
+        .iter()
+        .rev()
+        .find(|(version, _)| match (&range, version) {
+            (Some(range), Some(version)) => range.satisfies(version),
+            _ => true,
+        })
+        .or_else(|| candidates.last())
+        .map(|(_, key)| *key)
+}
+
+/// Makes sure no package lost a required (non-optional) dependency to platform filtering.
+///
+/// Installing the dependent anyway would leave it without a package it `require`s, which
+/// only shows up when that code runs.
+fn check_required_dependencies(
+    tree: &HashMap<String, VoltPackage>,
+    unsupported: &HashSet<String>,
+) -> Result<()> {
+    if unsupported.is_empty() {
+        return Ok(());
+    }
+
+    // another version of the same package may have been kept
+    let available: HashSet<&str> = tree.values().map(|package| package.name.as_str()).collect();
+
+    for (key, package) in tree.iter() {
+        for dependency in package.dependencies.as_deref().unwrap_or(&[]) {
+            let name = dependency_name(dependency);
+
+            if unsupported.contains(name)
+                && !available.contains(name)
+                && !package.is_optional_dependency(dependency)
+            {
+                return Err(VoltError::MissingDependency {
+                    dependent: key.clone(),
+                    dependency: dependency.clone(),
+                }
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks the flattened dependency tree from the requested packages, making sure
+/// it has no dependency cycles and doesn't nest deeper than `max_depth`.
+pub fn check_dependency_tree(
+    roots: &[Package],
+    tree: &HashMap<String, VoltPackage>,
+    max_depth: usize,
+) -> Result<()> {
+    // name -> name@version, the tree is flattened so there's one entry per name
+    let keys: HashMap<&str, &str> = tree
+        .iter()
+        .map(|(key, package)| (package.name.as_str(), key.as_str()))
+        .collect();
+
+    // packages whose entire subtree has already been checked
+    let mut visited: HashSet<&str> = HashSet::new();
+
+    for root in roots {
+        let root_key = match find_root_key(tree, root) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        // the current chain from the root, along with the next child to visit
+        let mut stack: Vec<(&str, usize)> = vec![(root_key, 0)];
+
+        while let Some(&(key, index)) = stack.last() {
+            let children = tree[key].dependencies.as_deref().unwrap_or(&[]);
+
+            if index == children.len() {
+                visited.insert(key);
+                stack.pop();
+                continue;
+            }
+
+            stack.last_mut().unwrap().1 += 1;
+
+            let child_key = match find_dependency_key(tree, &keys, &children[index]) {
+                Some(child_key) => child_key,
+                None => continue,
+            };
+
+            if stack.iter().any(|(k, _)| *k == child_key) {
+                let mut path = stack.iter().map(|(k, _)| *k).collect::<Vec<&str>>();
+                path.push(child_key);
+
+                return Err(VoltError::DependencyCycle {
+                    path: path.join(" -> "),
+                }
+                .into());
+            }
+
+            if visited.contains(child_key) {
+                continue;
+            }
+
+            if stack.len() >= max_depth {
+                let mut path = stack.iter().map(|(k, _)| *k).collect::<Vec<&str>>();
+                path.push(child_key);
+
+                return Err(VoltError::DependencyTooDeep {
+                    path: path.join(" -> "),
+                    max_depth,
+                }
+                .into());
+            }
+
+            stack.push((child_key, 0));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn print_elapsed(length: usize, elapsed: f32) {
     if length == 1 {
         if elapsed < 0.001 {
@@ -902,3 +2426,544 @@ pub fn print_elapsed(length: usize, elapsed: f32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{serve, tarball};
+
+    #[test]
+    fn https_tarballs_are_fetched() {
+        let url = "https://registry.npmjs.org/react/-/react-17.0.2.tgz";
+
+        assert!(is_https(url));
+        assert!(check_tarball_url("react", url, false).is_ok());
+    }
+
+    #[test]
+    fn http_tarballs_need_opting_in() {
+        let url = "http://mirror.internal/react/-/react-17.0.2.tgz";
+
+        assert!(!is_https(url));
+        assert!(check_tarball_url("react", url, false).is_err());
+        assert!(check_tarball_url("react", url, true).is_ok());
+    }
+
+    #[test]
+    fn schemes_are_case_insensitive() {
+        assert!(is_https("HTTPS://registry.npmjs.org/react.tgz"));
+        assert!(check_tarball_url("react", "HTTP://mirror.internal/react.tgz", false).is_err());
+    }
+
+    #[test]
+    fn tarballs_unpack_under_the_package_name() {
+        let destination = std::env::temp_dir().join(format!("volt-unpack-{}", std::process::id()));
+        let data = tarball(&[("package/package.json", "{}"), ("package/lib/index.js", "")]);
+
+        unpack_tarball(&data, TarballFormat::Tar, &destination, "react", &None).unwrap();
+
+        assert!(destination.join("react/package.json").is_file());
+        assert!(destination.join("react/lib/index.js").is_file());
+
+        std::fs::remove_dir_all(&destination).ok();
+    }
+
+    #[test]
+    fn truncated_tarballs_are_an_error() {
+        let destination =
+            std::env::temp_dir().join(format!("volt-truncated-{}", std::process::id()));
+        let data = tarball(&[("package/package.json", "{}")]);
+        let truncated = &data[..700];
+
+        let result = unpack_tarball(truncated, TarballFormat::Tar, &destination, "react", &None);
+
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&destination).ok();
+    }
+
+    #[test]
+    fn meta_packages_unpack_and_link() {
+        let dir = std::env::temp_dir().join(format!("volt-meta-{}", std::process::id()));
+        let (store, node_modules) = (dir.join("store"), dir.join("node_modules"));
+
+        let meta = tarball(&[("package/package.json", "{\"name\":\"meta\"}")]);
+        unpack_tarball(&meta, TarballFormat::Tar, &store, "meta", &None).unwrap();
+
+        let empty = tarball(&[]);
+        unpack_tarball(&empty, TarballFormat::Tar, &store, "empty", &None).unwrap();
+
+        link_package_files(&store.join("meta"), &node_modules.join("meta")).unwrap();
+        link_package_files(&store.join("empty"), &node_modules.join("empty")).unwrap();
+
+        assert!(node_modules.join("meta/package.json").is_file());
+        assert!(node_modules.join("empty").is_dir());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn volt_package(key: &str, dependencies: &[&str]) -> (String, VoltPackage) {
+        let (name, version) = split_dependency(key);
+
+        let package = VoltPackage {
+            name: name.to_string(),
+            version: version.unwrap().to_string(),
+            tarball: format!("https://registry.npmjs.org/{}/-/{}.tgz", name, key),
+            bin: None,
+            integrity: String::new(),
+            peer_dependencies: None,
+            peer_dependencies_meta: None,
+            dependencies: Some(dependencies.iter().map(|d| d.to_string()).collect()),
+            optional_dependencies: None,
+            os: None,
+            cpu: None,
+            funding: None,
+            unpacked_size: None,
+        };
+
+        (key.to_string(), package)
+    }
+
+    fn requested(names: &[&str]) -> Vec<Package> {
+        names
+            .iter()
+            .map(|name| Package {
+                name: name.to_string(),
+                version: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diamond_dependencies_nest_the_version_not_requested() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["lodash@4.17.21"]),
+            volt_package("b@1.0.0", &["lodash@3.10.1"]),
+            volt_package("lodash@4.17.21", &[]),
+            volt_package("lodash@3.10.1", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        let node_modules = Path::new("/project/node_modules");
+        let placements = plan_node_modules(node_modules, &requested(&["a", "b", "lodash"]), &tree);
+
+        assert_eq!(placements.len(), 1);
+        assert_eq!(
+            placements["lodash@3.10.1"],
+            vec![node_modules.join("b").join("node_modules")]
+        );
+    }
+
+    #[test]
+    fn the_most_depended_on_version_is_hoisted() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["lodash@4.17.21"]),
+            volt_package("b@1.0.0", &["lodash@3.10.1"]),
+            volt_package("c@1.0.0", &["lodash@3.10.1"]),
+            volt_package("lodash@4.17.21", &[]),
+            volt_package("lodash@3.10.1", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        let node_modules = Path::new("/project/node_modules");
+        let placements = plan_node_modules(node_modules, &requested(&["a", "b", "c"]), &tree);
+
+        assert_eq!(
+            placements["lodash@4.17.21"],
+            vec![node_modules.join("a").join("node_modules")]
+        );
+        assert!(!placements.contains_key("lodash@3.10.1"));
+    }
+
+    #[test]
+    fn packages_nest_under_nested_parents() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["b@2.0.0"]),
+            volt_package("b@2.0.0", &["c@2.0.0"]),
+            volt_package("b@1.0.0", &["c@1.0.0"]),
+            volt_package("c@2.0.0", &[]),
+            volt_package("c@1.0.0", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        let node_modules = Path::new("/project/node_modules");
+        let roots = vec![
+            Package {
+                name: String::from("a"),
+                version: None,
+            },
+            Package {
+                name: String::from("b"),
+                version: Some(String::from("^1.0.0")),
+            },
+            Package {
+                name: String::from("c"),
+                version: Some(String::from("^1.0.0")),
+            },
+        ];
+
+        let placements = plan_node_modules(node_modules, &roots, &tree);
+
+        assert_eq!(
+            placements["b@2.0.0"],
+            vec![node_modules.join("a").join("node_modules")]
+        );
+        assert_eq!(
+            placements["c@2.0.0"],
+            vec![node_modules.join("a/node_modules/b/node_modules")]
+        );
+    }
+
+    #[test]
+    fn dependency_cycles_are_reported_with_their_path() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["b@1.0.0"]),
+            volt_package("b@1.0.0", &["c@1.0.0"]),
+            volt_package("c@1.0.0", &["a@1.0.0"]),
+        ]
+        .into_iter()
+        .collect();
+
+        let error = check_dependency_tree(&requested(&["a"]), &tree, MAX_DEPTH).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            VoltError::DependencyCycle {
+                path: String::from("a@1.0.0 -> b@1.0.0 -> c@1.0.0 -> a@1.0.0"),
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn shared_dependencies_are_not_cycles() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["b@1.0.0", "c@1.0.0"]),
+            volt_package("b@1.0.0", &["d@1.0.0"]),
+            volt_package("c@1.0.0", &["d@1.0.0"]),
+            volt_package("d@1.0.0", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(check_dependency_tree(&requested(&["a"]), &tree, MAX_DEPTH).is_ok());
+    }
+
+    #[test]
+    fn trees_deeper_than_the_limit_are_refused() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("a@1.0.0", &["b@1.0.0"]),
+            volt_package("b@1.0.0", &["c@1.0.0"]),
+            volt_package("c@1.0.0", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(check_dependency_tree(&requested(&["a"]), &tree, 3).is_ok());
+        assert!(check_dependency_tree(&requested(&["a"]), &tree, 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn redirected_tarballs_are_verified_without_the_token() {
+        let data = tarball(&[("package/package.json", "{}")]);
+        let body = data.clone();
+
+        // redirected to another host name, as registries do to their storage backend
+        let (port, server) = serve(2, move |request, port| {
+            if request.starts_with("GET /redirect") {
+                let location = format!("Location: http://localhost:{}/tarball\r\n", port);
+                ("302 Found", location, vec![])
+            } else {
+                ("200 OK", String::new(), body.clone())
+            }
+        });
+
+        let (_, mut package) = volt_package("react@17.0.2", &[]);
+        package.integrity = integrity_of(&data, Algorithm::Sha512).unwrap().to_string();
+
+        let url = format!("http://127.0.0.1:{}/redirect", port);
+        let (bytes, _) = fetch_tarball(
+            &url,
+            Some("secret"),
+            &[],
+            &package,
+            &std::env::temp_dir(),
+            1024 * 1024,
+        )
+        .await
+        .unwrap();
+
+        assert!(verify_checksum(&package.integrity, &bytes).is_ok());
+
+        let requests = server.join().unwrap();
+
+        assert!(requests[0].contains("authorization: bearer secret"));
+        assert!(requests[1].starts_with("get /tarball"));
+        assert!(!requests[1].contains("authorization"));
+    }
+
+    #[tokio::test]
+    async fn tarballs_not_matching_their_integrity_are_refused() {
+        let data = tarball(&[("package/package.json", "{}")]);
+        let body = tarball(&[("package/package.json", "{ \"tampered\": true }")]);
+
+        let (port, server) = serve(1, move |_, _| ("200 OK", String::new(), body.clone()));
+
+        let (_, mut package) = volt_package("tampered@1.0.0", &[]);
+        package.integrity = integrity_of(&data, Algorithm::Sha512).unwrap().to_string();
+
+        let tmp_dir = std::env::temp_dir();
+        let url = format!("http://127.0.0.1:{}/tarball", port);
+        let result = fetch_tarball(&url, None, &[], &package, &tmp_dir, 1024 * 1024).await;
+
+        server.join().unwrap();
+
+        assert!(matches!(
+            result.unwrap_err().downcast::<VoltError>(),
+            Ok(VoltError::ChecksumVerificationError)
+        ));
+        assert!(!tmp_dir.join("volt-tampered-1.0.0.tgz.part").exists());
+    }
+
+    #[test]
+    fn requested_names_match_the_canonical_name() {
+        let tree: HashMap<String, VoltPackage> = vec![
+            volt_package("react@17.0.2", &["loose-envify@1.4.0"]),
+            volt_package("loose-envify@1.4.0", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        for typed in &["react", "React", " REACT "] {
+            let root = &requested(&[typed])[0];
+
+            assert_eq!(find_root_key(&tree, root), Some("react@17.0.2"));
+        }
+
+        assert_eq!(find_root_key(&tree, &requested(&["preact"])[0]), None);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_resolved_without_packages() {
+        let reporter = events::TerminalReporter::new();
+        let (responses, _) = fetch_dep_tree(&vec![], constants::VOLT_CDN, &[], &reporter)
+            .await
+            .unwrap();
+
+        assert!(responses.is_empty());
+    }
+
+    #[test]
+    fn packages_without_dependencies_go_at_the_top_level() {
+        let (key, mut package) = volt_package("is-number@7.0.0", &[]);
+        package.dependencies = None;
+
+        let tree: HashMap<String, VoltPackage> = vec![(key, package)].into_iter().collect();
+        let roots = requested(&["is-number"]);
+
+        assert!(check_dependency_tree(&roots, &tree, MAX_DEPTH).is_ok());
+        assert!(plan_node_modules(Path::new("/project/node_modules"), &roots, &tree).is_empty());
+    }
+
+    #[test]
+    fn scoped_entries_are_extracted_under_the_scope() {
+        let destination = |path: &str| entry_destination(Path::new(path), "@babel/core");
+
+        assert_eq!(
+            destination("package/lib/index.js"),
+            Path::new("@babel/core/lib/index.js")
+        );
+        assert_eq!(
+            destination("core/package/index.js"),
+            Path::new("@babel/core/package/index.js")
+        );
+        assert_eq!(
+            destination("package/../../escape.js"),
+            Path::new("@babel/core/escape.js")
+        );
+    }
+
+    #[test]
+    fn scoped_tarballs_unpack_under_the_scope() {
+        let destination = std::env::temp_dir().join(format!("volt-scoped-{}", std::process::id()));
+        let data = tarball(&[("package/package.json", "{}"), ("package/lib/index.js", "")]);
+
+        unpack_tarball(
+            &data,
+            TarballFormat::Tar,
+            &destination,
+            "@babel/core",
+            &None,
+        )
+        .unwrap();
+
+        assert!(destination.join("@babel/core/package.json").is_file());
+        assert!(destination.join("@babel/core/lib/index.js").is_file());
+
+        std::fs::remove_dir_all(&destination).ok();
+    }
+
+    #[test]
+    fn scoped_bins_point_into_their_scope() {
+        let (_, mut package) = volt_package("@angular/cli@12.2.0", &[]);
+        let mut bin = HashMap::new();
+        bin.insert("ng".to_string(), "./bin/ng.js".to_string());
+        bin.insert(
+            "@angular/schematics".to_string(),
+            "bin/schematics.js".to_string(),
+        );
+        bin.insert("escape".to_string(), "../../escape.js".to_string());
+        package.bin = Some(bin);
+
+        let mut targets = bin_targets(&package);
+        targets.sort();
+
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    "ng".to_string(),
+                    Path::new("..")
+                        .join("@angular/cli")
+                        .join("bin")
+                        .join("ng.js")
+                ),
+                (
+                    "schematics".to_string(),
+                    Path::new("..")
+                        .join("@angular/cli")
+                        .join("bin")
+                        .join("schematics.js")
+                ),
+            ]
+        );
+    }
+
+    fn tagged_packument() -> serde_json::Value {
+        // latest points at the 1.x line, 2.0.0 is newer, 3.0.0-beta.0 is a prerelease
+        serde_json::json!({
+            "name": "lib",
+            "dist-tags": { "latest": "1.2.0", "next": "3.0.0-beta.0" },
+            "versions": {
+                "1.2.0": { "version": "1.2.0" },
+                "2.0.0": { "version": "2.0.0" },
+                "3.0.0-beta.0": { "version": "3.0.0-beta.0" }
+            }
+        })
+    }
+
+    #[test]
+    fn versions_missing_from_the_response_are_an_error() {
+        let response: JSONVoltResponse = serde_json::from_value(serde_json::json!({
+            "latest": "2.0.0",
+            "schema": 0,
+            "1.0.0": {}
+        }))
+        .unwrap();
+
+        assert!(convert("lib", response.latest.clone(), response).is_err());
+    }
+
+    #[test]
+    fn stable_ranges_skip_prereleases() {
+        let response: JSONVoltResponse = serde_json::from_value(serde_json::json!({
+            "latest": "1.1.0",
+            "schema": 0,
+            "1.1.0": {},
+            "1.2.0-beta.1": {}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            select_version(&response, "^1.0.0").as_deref(),
+            Some("1.1.0")
+        );
+        assert_eq!(
+            select_version(&response, "^1.2.0-beta.0").as_deref(),
+            Some("1.2.0-beta.1")
+        );
+        assert_eq!(
+            select_version(&response, "1.2.0-beta.1").as_deref(),
+            Some("1.2.0-beta.1")
+        );
+    }
+
+    #[test]
+    fn the_newest_version_is_the_highest_stable_one() {
+        let packument: NpmPackage = serde_json::from_value(tagged_packument()).unwrap();
+
+        assert_eq!(newest_version(&packument).as_deref(), Some("2.0.0"));
+
+        let prereleases: NpmPackage = serde_json::from_value(serde_json::json!({
+            "versions": { "1.0.0-rc.1": {}, "1.0.0-rc.2": {} }
+        }))
+        .unwrap();
+
+        assert_eq!(newest_version(&prereleases).as_deref(), Some("1.0.0-rc.2"));
+    }
+
+    #[tokio::test]
+    async fn requested_tags_resolve_through_dist_tags() {
+        let packument = serde_json::to_vec(&tagged_packument()).unwrap();
+        let (port, _) = serve(4, move |_, _| ("200 OK", String::new(), packument.clone()));
+
+        let registry = format!("http://127.0.0.1:{}", port);
+        let args = clap::App::new("volt")
+            .arg(
+                clap::Arg::new("registry")
+                    .long("registry")
+                    .takes_value(true),
+            )
+            .get_matches_from(vec!["volt", "--registry", &registry]);
+        let app = App::for_tests(args, crate::core::model::config::VoltConfig::default());
+
+        let resolve = |packages: Vec<Package>, default| resolve_tags(&app, packages, default);
+        let version = |packages: Vec<Package>| packages[0].version.clone().unwrap();
+
+        let latest = resolve(requested(&["lib"]), DefaultVersion::Tag("latest"));
+        assert_eq!(version(latest.await.unwrap()), "1.2.0");
+
+        let newest = resolve(requested(&["lib"]), DefaultVersion::Newest);
+        assert_eq!(version(newest.await.unwrap()), "2.0.0");
+
+        let next = npm::parse_versions(&vec![String::from("lib@next")]).unwrap();
+        assert_eq!(
+            version(resolve(next, DefaultVersion::Newest).await.unwrap()),
+            "3.0.0-beta.0"
+        );
+
+        let missing = resolve(requested(&["lib"]), DefaultVersion::Tag("beta")).await;
+        assert!(missing.unwrap_err().to_string().contains("beta"));
+    }
+
+    #[test]
+    fn unreadable_git_configs_are_skipped() {
+        let args = clap::App::new("volt")
+            .arg(clap::Arg::new("verbose").long("verbose"))
+            .get_matches_from(vec!["volt", "--verbose"]);
+        let app = App::for_tests(args, crate::core::model::config::VoltConfig::default());
+
+        let dir = std::env::temp_dir().join(format!("volt-gitconfig-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let valid = dir.join("valid");
+        std::fs::write(&valid, "[user]\n\tname = Volt\n").unwrap();
+
+        let invalid = dir.join("invalid");
+        std::fs::write(&invalid, "[user\n").unwrap();
+
+        let read = |path: &Path| read_git_config(&app, path, "user", None, "name");
+
+        assert_eq!(read(&valid).as_deref(), Some("Volt"));
+        assert_eq!(read(&dir.join("missing")), None);
+        assert_eq!(read(&invalid), None);
+
+        // a path that can't be read as a file
+        assert_eq!(read(&dir), None);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
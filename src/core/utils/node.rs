@@ -0,0 +1,254 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Download and select the node runtime a project is pinned to.
+
+use flate2::read::GzDecoder;
+use miette::Result;
+use node_semver::{Range, Version};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use tar::Archive;
+
+use super::constants::NODE_DIST_URL;
+use super::errors::VoltError;
+use super::package::PackageJson;
+use super::platform::Platform;
+
+/// A release listed in node's `index.json`, newest first.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NodeRelease {
+    pub version: String,
+    /// `false`, or the codename of an lts release line (e.g. `Gallium`).
+    #[serde(default)]
+    pub lts: Value,
+}
+
+/// Directory in `~/.volt` holding the downloaded node runtimes, one per version.
+pub fn node_dir(volt_dir: &Path) -> PathBuf {
+    volt_dir.join(".node")
+}
+
+/// Directory containing the `node` executable of an installed runtime.
+pub fn node_bin_dir(volt_dir: &Path, version: &str) -> PathBuf {
+    let install = node_dir(volt_dir).join(version);
+
+    if cfg!(target_os = "windows") {
+        install
+    } else {
+        install.join("bin")
+    }
+}
+
+/// Reads the node version a project asks for, from `.nvmrc` or the `engines.node` field of package.json.
+pub fn requested_node_version(project_dir: &Path) -> Option<String> {
+    if let Ok(nvmrc) = read_to_string(project_dir.join(".nvmrc")) {
+        let version = nvmrc.trim();
+
+        if !version.is_empty() {
+            return Some(version.to_string());
+        }
+    }
+
+    read_to_string(project_dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<PackageJson>(&data).ok())
+        .and_then(|package| package.engines.get("node").cloned())
+}
+
+/// Picks the release matching a requested version: a semver range, `node`/`latest`, `lts/*` or `lts/<codename>`.
+pub fn select_node_version(releases: &[NodeRelease], requested: &str) -> Option<String> {
+    let requested = requested.trim().trim_start_matches('v').to_lowercase();
+
+    let version = match requested.as_str() {
+        "node" | "latest" | "current" => releases.first(),
+        "lts/*" => releases.iter().find(|release| release.lts.is_string()),
+        lts if lts.starts_with("lts/") => releases.iter().find(|release| {
+            release
+                .lts
+                .as_str()
+                .map(|name| name.to_lowercase())
+                .as_deref()
+                == lts.strip_prefix("lts/")
+        }),
+        range => {
+            let range: Range = range.parse().ok()?;
+
+            return releases
+                .iter()
+                .filter_map(|release| release.version.trim_start_matches('v').parse().ok())
+                .filter(|version: &Version| range.satisfies(version))
+                .max()
+                .map(|version| version.to_string());
+        }
+    };
+
+    version.map(|release| release.version.trim_start_matches('v').to_string())
+}
+
+/// Picks the highest installed runtime satisfying a semver range, without touching the network.
+pub fn installed_node_version(volt_dir: &Path, requested: &str) -> Option<String> {
+    let range: Range = requested.trim().trim_start_matches('v').parse().ok()?;
+
+    std::fs::read_dir(node_dir(volt_dir))
+        .ok()?
+        .flatten()
+        .filter(|entry| node_bin_dir(volt_dir, &entry.file_name().to_string_lossy()).exists())
+        .filter_map(|entry| entry.file_name().to_string_lossy().parse().ok())
+        .filter(|version: &Version| range.satisfies(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
+/// Fetches the list of node releases from nodejs.org.
+pub async fn fetch_node_releases() -> Result<Vec<NodeRelease>> {
+    let url = format!("{}/index.json", NODE_DIST_URL);
+
+    let text = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| VoltError::NodeDownloadError {
+            source: e,
+            version: String::from("index"),
+        })?
+        .text()
+        .await
+        .map_err(|e| VoltError::NodeDownloadError {
+            source: e,
+            version: String::from("index"),
+        })?;
+
+    Ok(serde_json::from_str(&text).map_err(|_| VoltError::DeserializeError)?)
+}
+
+/// Downloads a node runtime into `~/.volt/.node/<version>` if it isn't already there.
+pub async fn install_node(volt_dir: &Path, version: &str) -> Result<PathBuf> {
+    let bin_dir = node_bin_dir(volt_dir, version);
+
+    if bin_dir.exists() {
+        return Ok(bin_dir);
+    }
+
+    let platform = Platform::host();
+    let install = node_dir(volt_dir).join(version);
+
+    // windows builds ship node.exe on its own, everything else is a tarball
+    let url = if platform.os == "win32" {
+        format!(
+            "{}/v{}/win-{}/node.exe",
+            NODE_DIST_URL, version, platform.cpu
+        )
+    } else {
+        format!(
+            "{}/v{}/node-v{}-{}-{}.tar.gz",
+            NODE_DIST_URL, version, version, platform.os, platform.cpu
+        )
+    };
+
+    let download_error = |e| VoltError::NodeDownloadError {
+        source: e,
+        version: version.to_string(),
+    };
+
+    let bytes = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(download_error)?
+        .bytes()
+        .await
+        .map_err(download_error)?;
+
+    // extract next to the final directory so an interrupted download is never picked up
+    let staging = node_dir(volt_dir).join(format!(".{}", version));
+
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).map_err(|e| VoltError::RemoveDirError {
+            source: e,
+            name: staging.to_string_lossy().to_string(),
+        })?;
+    }
+
+    std::fs::create_dir_all(&staging).map_err(VoltError::CreateDirError)?;
+
+    if platform.os == "win32" {
+        std::fs::write(staging.join("node.exe"), &bytes).map_err(|e| {
+            VoltError::WriteFileError {
+                source: e,
+                name: String::from("node.exe"),
+            }
+        })?;
+    } else {
+        let extract_error = |e| VoltError::NodeExtractError {
+            source: e,
+            version: version.to_string(),
+        };
+
+        let mut archive = Archive::new(GzDecoder::new(&*bytes));
+
+        for entry in archive.entries().map_err(extract_error)? {
+            let mut entry = entry.map_err(extract_error)?;
+
+            // node-v16.8.0-linux-x64/bin/node -> bin/node
+            let path: PathBuf = entry
+                .path()
+                .map_err(extract_error)?
+                .components()
+                .skip(1)
+                .collect();
+
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            let target = staging.join(path);
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(VoltError::CreateDirError)?;
+            }
+
+            entry.unpack(&target).map_err(extract_error)?;
+        }
+    }
+
+    std::fs::rename(&staging, &install).map_err(|e| VoltError::NodeExtractError {
+        source: e,
+        version: version.to_string(),
+    })?;
+
+    Ok(bin_dir)
+}
+
+/// Finds (downloading if necessary) the runtime for a requested version, returning its version and bin directory.
+pub async fn ensure_node(volt_dir: &Path, requested: &str) -> Result<(String, PathBuf)> {
+    if let Some(version) = installed_node_version(volt_dir, requested) {
+        let bin_dir = node_bin_dir(volt_dir, &version);
+        return Ok((version, bin_dir));
+    }
+
+    let releases = fetch_node_releases().await?;
+
+    let version = select_node_version(&releases, requested).ok_or_else(|| {
+        VoltError::NodeVersionNotFound {
+            version: requested.to_string(),
+        }
+    })?;
+
+    let bin_dir = install_node(volt_dir, &version).await?;
+
+    Ok((version, bin_dir))
+}
@@ -1,6 +1,10 @@
 use miette::Result;
+use node_semver::{Range, Version};
+use std::str::FromStr;
 
 use crate::commands::add::Package;
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::voltapi::{normalize_name, split_dependency};
 
 // use crate::core::utils::constants::MAX_RETRIES;
 // use crate::core::utils::errors::VoltError;
@@ -16,35 +20,186 @@ use crate::commands::add::Package;
 // use serde_json::Value;
 // use ssri::{Algorithm, Integrity};
 
+/// A semver range that follows npm's prerelease rule: a prerelease only satisfies a range
+/// naming a prerelease of the same `major.minor.patch`.
+///
+/// `^1.0.0` never picks `1.2.0-beta.1`, which is what its publisher meant to keep from
+/// users of the stable line, while `^1.2.0-beta.0` allows `1.2.0-beta.1` (but not `1.3.0-rc.0`).
+pub struct VersionRange {
+    range: Range,
+    /// `major.minor.patch` of every prerelease named in the range.
+    prereleases: Vec<(u64, u64, u64)>,
+}
+
+impl VersionRange {
+    pub fn satisfies(&self, version: &Version) -> bool {
+        if !self.range.satisfies(version) {
+            return false;
+        }
+
+        version.pre_release.is_empty()
+            || self
+                .prereleases
+                .contains(&(version.major, version.minor, version.patch))
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = ();
+
+    fn from_str(range: &str) -> std::result::Result<Self, Self::Err> {
+        let prereleases = range
+            .split(|c: char| c.is_whitespace() || c == '|')
+            .map(|comparator| comparator.trim_start_matches(|c| "^~<>=v".contains(c)))
+            .filter_map(|comparator| comparator.parse::<Version>().ok())
+            .filter(|version| !version.pre_release.is_empty())
+            .map(|version| (version.major, version.minor, version.patch))
+            .collect();
+
+        Ok(Self {
+            range: range.parse().map_err(|_| ())?,
+            prereleases,
+        })
+    }
+}
+
+/// Whether `name` is a complete package name, `react` or `@scope/name` but not a bare
+/// `@scope` or `@scope/`.
+pub fn is_valid_name(name: &str) -> bool {
+    match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, name)) => !scope.is_empty() && !name.is_empty() && !name.contains('/'),
+            None => false,
+        },
+        None => !name.is_empty() && !name.contains('/'),
+    }
+}
+
+/// Parses package specs typed on the command line: `react`, `react@17`, `@types/node` or
+/// `@types/node@16`.
 pub fn parse_versions(packages: &Vec<String>) -> Result<Vec<Package>> {
     let mut parsed: Vec<Package> = vec![];
 
     for package in packages.iter() {
-        let split = package.split("@").map(|s| s.trim()).collect::<Vec<&str>>();
-        let length = split.len();
-
-        if length == 1 {
-            parsed.push(Package {
-                name: split[0].to_string(),
-                version: None,
-            });
-        } else if length == 2 && !package.contains("/") {
-            parsed.push(Package {
-                name: split[0].to_string(),
-                version: Some(split[1].to_string()),
-            });
-        } else if length == 2 && package.contains("/") {
-            parsed.push(Package {
-                name: format!("@{}", split[1]),
-                version: None,
-            });
-        } else if length == 3 && package.contains("/") {
-            parsed.push(Package {
-                name: format!("@{}", split[1]),
-                version: Some(split[2].to_string()),
-            });
+        let (name, version) = split_dependency(package.trim());
+        let name = name.trim();
+
+        if !is_valid_name(name) {
+            return Err(VoltError::InvalidPackageName {
+                name: package.to_string(),
+            }
+            .into());
         }
+
+        parsed.push(Package {
+            name: name.to_string(),
+            version: version
+                .map(|version| version.trim())
+                .filter(|version| !version.is_empty())
+                .map(String::from),
+        });
     }
 
     Ok(parsed)
 }
+
+/// Drops packages requested more than once (`volt add express express`), keeping the first.
+///
+/// The same package requested with different versions (`lodash lodash@4`) is an error,
+/// there's no telling which one was meant.
+pub fn dedupe_packages(packages: Vec<Package>) -> Result<Vec<Package>> {
+    let mut deduped: Vec<Package> = Vec::with_capacity(packages.len());
+
+    for package in packages {
+        let name = normalize_name(&package.name);
+
+        match deduped
+            .iter()
+            .find(|existing| normalize_name(&existing.name) == name)
+        {
+            Some(existing) if existing.version == package.version => {}
+            Some(existing) => {
+                let spec = |package: &Package| match &package.version {
+                    Some(version) => format!("{}@{}", package.name, version),
+                    None => package.name.clone(),
+                };
+
+                return Err(VoltError::ConflictingSpecs {
+                    name: package.name.clone(),
+                    first: spec(existing),
+                    second: spec(&package),
+                }
+                .into());
+            }
+            None => deduped.push(package),
+        }
+    }
+
+    Ok(deduped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specs(specs: &[&str]) -> Vec<Package> {
+        parse_versions(&specs.iter().map(|spec| spec.to_string()).collect()).unwrap()
+    }
+
+    #[test]
+    fn duplicate_specs_are_installed_once() {
+        let packages = dedupe_packages(specs(&["express", "react", "express"])).unwrap();
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(names, vec!["express", "react"]);
+
+        let packages = dedupe_packages(specs(&["lodash@4", "Lodash@4"])).unwrap();
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].version.as_deref(), Some("4"));
+    }
+
+    #[test]
+    fn conflicting_specs_are_an_error() {
+        assert!(dedupe_packages(specs(&["lodash", "lodash@4"])).is_err());
+        assert!(dedupe_packages(specs(&["lodash@3", "lodash@4"])).is_err());
+    }
+
+    #[test]
+    fn scoped_specs_keep_their_scope() {
+        let packages = specs(&["@types/node", "@babel/preset-env@^7.15.0", "react@17"]);
+        let parsed: Vec<(&str, Option<&str>)> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_deref()))
+            .collect();
+
+        assert_eq!(
+            parsed,
+            vec![
+                ("@types/node", None),
+                ("@babel/preset-env", Some("^7.15.0")),
+                ("react", Some("17")),
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_scopes_are_not_package_names() {
+        for spec in &["@types", "@types/", "@/node", "a/b", ""] {
+            assert!(parse_versions(&vec![spec.to_string()]).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn prereleases_only_match_ranges_that_name_them() {
+        let stable: VersionRange = "^1.0.0".parse().unwrap();
+        let beta: VersionRange = "^1.2.0-beta.0".parse().unwrap();
+        let version = |version: &str| version.parse::<Version>().unwrap();
+
+        assert!(stable.satisfies(&version("1.1.0")));
+        assert!(!stable.satisfies(&version("1.2.0-beta.1")));
+        assert!(beta.satisfies(&version("1.2.0-beta.1")));
+        assert!(beta.satisfies(&version("1.3.0")));
+        assert!(!beta.satisfies(&version("1.3.0-rc.0")));
+    }
+}
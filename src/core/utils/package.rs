@@ -14,17 +14,22 @@
     limitations under the License.
 */
 
-use std::path::PathBuf;
-use std::{collections::HashMap, fs::read_to_string};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::read_to_string,
+};
 
 use super::errors::VoltError;
 use miette::{IntoDiagnostic, Result};
 use serde::{Deserialize, Serialize};
-
-use crate::commands::add::Package;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use serde_json::Value;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(default, rename_all = "camelCase")]
 pub struct NpmPackage {
     #[serde(rename = "_id")]
     pub id: String,
@@ -143,6 +148,15 @@ pub struct Dist {
     pub unpacked_size: i64,
     #[serde(rename = "npm-signature")]
     pub npm_signature: String,
+    /// ECDSA signatures of `name@version:integrity` by the registry's keys.
+    pub signatures: Vec<DistSignature>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DistSignature {
+    pub keyid: String,
+    pub sig: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -170,6 +184,41 @@ pub struct NpmOperationalInternal {
     pub tmp: String,
 }
 
+/// A section of package.json that dependencies are saved to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DependencyType {
+    Dependencies,
+    DevDependencies,
+    PeerDependencies,
+    OptionalDependencies,
+}
+
+impl DependencyType {
+    /// Parses the short names `--include` and `--omit` take: prod, dev, optional and peer.
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind.trim() {
+            "prod" => Some(Self::Dependencies),
+            "dev" => Some(Self::DevDependencies),
+            "optional" => Some(Self::OptionalDependencies),
+            "peer" => Some(Self::PeerDependencies),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the indentation a JSON document uses from its first indented line, two spaces if it has none.
+fn detect_indent(data: &str) -> String {
+    data.lines()
+        .skip(1)
+        .map(|line| {
+            line.chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect::<String>()
+        })
+        .find(|indent| !indent.is_empty())
+        .unwrap_or_else(|| String::from("  "))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PackageJson {
     pub name: String,
@@ -183,60 +232,154 @@ pub struct PackageJson {
     #[serde(rename = "devDependencies")]
     #[serde(default)]
     pub dev_dependencies: HashMap<String, String>,
+    #[serde(rename = "peerDependencies")]
+    #[serde(default)]
+    pub peer_dependencies: HashMap<String, String>,
+    #[serde(rename = "optionalDependencies")]
+    #[serde(default)]
+    pub optional_dependencies: HashMap<String, String>,
     #[serde(default)]
     pub scripts: HashMap<String, String>,
+    #[serde(default)]
+    pub engines: HashMap<String, String>,
 }
 
 impl PackageJson {
-    pub fn open(_path: &str) -> Result<(Self, PathBuf)> {
-        for parent in std::env::current_exe()
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Self, PathBuf)> {
+        let pkg_path = std::env::current_dir()
             .map_err(|e| VoltError::EnvironmentError {
                 env: String::from("CURRENT_DIR"),
                 source: e,
             })?
-            .parent()
-            .expect("this should have a parent!")
-            .ancestors()
-        {
-            let pkg_path = parent.join("package.json");
-
-            if pkg_path.exists() {
-                let data = read_to_string(&pkg_path).map_err(|e| VoltError::ReadFileError {
-                    source: e,
-                    name: pkg_path.to_str().unwrap().to_string(),
-                })?;
-
-                return Ok((
-                    serde_json::from_str(data.as_str()).into_diagnostic()?,
-                    pkg_path,
-                ));
-            } else {
-                miette::bail!("No package.json found!")
+            .join(path);
+
+        if !pkg_path.exists() {
+            miette::bail!("No package.json found!")
+        }
+
+        let data = read_to_string(&pkg_path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: pkg_path.to_str().unwrap().to_string(),
+        })?;
+
+        Ok((
+            serde_json::from_str(data.as_str()).into_diagnostic()?,
+            pkg_path,
+        ))
+    }
+
+    /// Writes the dependency sections back to the package.json at `path`.
+    ///
+    /// Only the dependency sections are replaced, every other field in the file is kept as is,
+    /// in its original order. The file's indentation, line endings and trailing newline are
+    /// kept too, so adding a dependency only changes the lines for that dependency.
+    ///
+    /// The file is replaced in one step, it's never left half written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = read_to_string(path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?;
+
+        let mut document: Value = serde_json::from_str(&data).into_diagnostic()?;
+
+        let sections = [
+            ("dependencies", &self.dependencies),
+            ("devDependencies", &self.dev_dependencies),
+            ("peerDependencies", &self.peer_dependencies),
+            ("optionalDependencies", &self.optional_dependencies),
+        ];
+
+        for (key, dependencies) in sections.iter() {
+            // don't add empty sections, but do empty one whose last dependency moved out
+            if dependencies.is_empty() && document.get(key).is_none() {
+                continue;
             }
+
+            let sorted: BTreeMap<&String, &String> = dependencies.iter().collect();
+            document[*key] = serde_json::to_value(sorted).into_diagnostic()?;
+        }
+
+        let indent = detect_indent(&data);
+        let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+
+        let mut serialized = Vec::new();
+        let mut serializer = Serializer::with_formatter(&mut serialized, formatter);
+        document.serialize(&mut serializer).into_diagnostic()?;
+
+        let mut output = String::from_utf8(serialized).into_diagnostic()?;
+
+        if data.ends_with('\n') {
+            output.push('\n');
         }
 
-        miette::bail!("No package.json found!")
+        if data.contains("\r\n") {
+            output = output.replace('\n', "\r\n");
+        }
+
+        // leave the file (and its modification time) alone when nothing changed
+        if output == data {
+            return Ok(());
+        }
+
+        // written next to package.json and renamed over it, so an interrupted volt add
+        // leaves the old package.json instead of a truncated one
+        let staging = path.with_extension("json.volt-tmp");
+
+        let written = File::create(&staging)
+            .and_then(|mut file| {
+                file.write_all(output.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&staging, path));
+
+        if let Err(e) = written {
+            std::fs::remove_file(&staging).ok();
+
+            return Err(VoltError::WriteFileError {
+                source: e,
+                name: path.to_string_lossy().to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
     }
 
-    // pub fn save(&self) -> Result<()> {
-    //     let mut file = File::create("package.json").into_diagnostic()?;
+    pub fn add_dependency(&mut self, name: String, version: String) {
+        self.add_dependency_to(DependencyType::Dependencies, name, version);
+    }
 
-    //     file.write(
-    //         serde_json::to_string_pretty(self)
-    //             .into_diagnostic()?
-    //             .as_bytes(),
-    //     )
-    //     .map_err(|e| VoltError::WriteFileError {
-    //         source: e,
-    //         name: String::from("package.json"),
-    //     })?;
+    /// Saves a dependency to a section of package.json.
+    ///
+    /// `dependencies`, `devDependencies` and `optionalDependencies` exclude each other, so a
+    /// dependency saved to one is moved out of the others. `peerDependencies` sits alongside
+    /// them, a library usually lists its peers as dev dependencies as well.
+    pub fn add_dependency_to(&mut self, section: DependencyType, name: String, version: String) {
+        if section != DependencyType::PeerDependencies {
+            self.dependencies.remove(&name);
+            self.dev_dependencies.remove(&name);
+            self.optional_dependencies.remove(&name);
+        }
 
-    //     Ok(())
-    // }
+        let dependencies = match section {
+            DependencyType::Dependencies => &mut self.dependencies,
+            DependencyType::DevDependencies => &mut self.dev_dependencies,
+            DependencyType::PeerDependencies => &mut self.peer_dependencies,
+            DependencyType::OptionalDependencies => &mut self.optional_dependencies,
+        };
 
-    pub fn add_dependency(&mut self, package: Package) {
-        self.dependencies
-            .insert(package.name, package.version.unwrap_or_default());
+        dependencies.insert(name, version);
+    }
+
+    /// The dependencies listed in a section of package.json.
+    pub fn dependencies_in(&self, section: DependencyType) -> &HashMap<String, String> {
+        match section {
+            DependencyType::Dependencies => &self.dependencies,
+            DependencyType::DevDependencies => &self.dev_dependencies,
+            DependencyType::PeerDependencies => &self.peer_dependencies,
+            DependencyType::OptionalDependencies => &self.optional_dependencies,
+        }
     }
 
     // pub fn add_dev_dependency(&mut self, package: Package) {
@@ -0,0 +1,136 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use miette::Result;
+
+use super::errors::VoltError;
+use super::voltapi::VoltPackage;
+
+/// An operating system and cpu architecture pair, using node's naming (`process.platform`, `process.arch`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Platform {
+    pub os: String,
+    pub cpu: String,
+}
+
+impl Platform {
+    /// The platform volt is currently running on.
+    pub fn host() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            "windows" => "win32",
+            os => os,
+        };
+
+        let cpu = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "ia32",
+            "aarch64" => "arm64",
+            "powerpc64" => "ppc64",
+            cpu => cpu,
+        };
+
+        Self {
+            os: os.to_string(),
+            cpu: cpu.to_string(),
+        }
+    }
+
+    /// Parses a platform in the form `<os>-<arch>` (e.g. `linux-x64`).
+    pub fn parse(platform: &str) -> Result<Self> {
+        match platform.split_once('-') {
+            Some((os, cpu)) if !os.is_empty() && !cpu.is_empty() => Ok(Self {
+                os: os.to_string(),
+                cpu: cpu.to_string(),
+            }),
+            _ => Err(VoltError::InvalidPlatform {
+                platform: platform.to_string(),
+            })?,
+        }
+    }
+
+    /// Checks a package's `os` and `cpu` fields against this platform.
+    pub fn supports(&self, package: &VoltPackage) -> bool {
+        matches_list(&package.os, &self.os) && matches_list(&package.cpu, &self.cpu)
+    }
+}
+
+/// Matches a value against an npm `os`/`cpu` list, where entries prefixed with `!` are exclusions.
+fn matches_list(list: &Option<Vec<String>>, value: &str) -> bool {
+    let list = match list {
+        Some(list) if !list.is_empty() => list,
+        _ => return true,
+    };
+
+    if list
+        .iter()
+        .any(|entry| entry.strip_prefix('!') == Some(value))
+    {
+        return false;
+    }
+
+    let mut allowed = list
+        .iter()
+        .filter(|entry| !entry.starts_with('!'))
+        .peekable();
+
+    // a list of only exclusions allows everything else
+    allowed.peek().is_none() || allowed.any(|entry| entry == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(os: &[&str], cpu: &[&str]) -> VoltPackage {
+        serde_json::from_value(serde_json::json!({
+            "name": "fsevents",
+            "version": "2.3.2",
+            "tarball": "",
+            "integrity": "",
+            "os": if os.is_empty() { None } else { Some(os) },
+            "cpu": if cpu.is_empty() { None } else { Some(cpu) }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn platforms_are_parsed_as_os_and_arch() {
+        assert_eq!(
+            Platform::parse("linux-x64").unwrap(),
+            Platform {
+                os: "linux".to_string(),
+                cpu: "x64".to_string()
+            }
+        );
+        assert!(Platform::parse("linux").is_err());
+        assert!(Platform::parse("-x64").is_err());
+    }
+
+    #[test]
+    fn os_and_cpu_lists_are_matched() {
+        let linux = Platform::parse("linux-x64").unwrap();
+        let mac = Platform::parse("darwin-arm64").unwrap();
+
+        assert!(linux.supports(&package(&[], &[])));
+        assert!(mac.supports(&package(&["darwin"], &[])));
+        assert!(!linux.supports(&package(&["darwin"], &[])));
+        assert!(!mac.supports(&package(&["darwin"], &["x64"])));
+        assert!(linux.supports(&package(&["!win32"], &["x64", "arm64"])));
+        assert!(!linux.supports(&package(&["!linux"], &[])));
+        assert!(!linux.supports(&package(&["linux", "!linux"], &[])));
+    }
+}
@@ -13,17 +13,134 @@
 
 //! Handle an unknown command (can be listed in scripts).
 
+use std::env;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command as Process, Stdio};
 use std::sync::Arc;
 
 // use crate::core::utils::errors;
 // use crate::core::utils::package::PackageJson;
+use crate::core::utils::errors::VoltError;
 use crate::App;
 use crate::Command;
 use async_trait::async_trait;
-// use colored::Colorize;
+use colored::Colorize;
+use indicatif::ProgressBar;
 use miette::Result;
 pub struct Script {}
 
+/// Collects every `node_modules/.bin` from `directory` up to the filesystem root, nearest first,
+/// the same directories node walks when resolving a package.
+pub fn bin_dirs(directory: &Path) -> Vec<PathBuf> {
+    directory
+        .ancestors()
+        .filter(|dir| dir.file_name().is_none_or(|name| name != "node_modules"))
+        .map(|dir| dir.join("node_modules").join(".bin"))
+        .filter(|bin| bin.is_dir())
+        .collect()
+}
+
+/// Builds the PATH a script runs with: `extra`, then the `.bin` directories above `directory`, then the inherited PATH.
+pub fn script_path(directory: &Path, extra: &[PathBuf]) -> Option<OsString> {
+    let path = env::var_os("PATH").unwrap_or_default();
+
+    let paths = extra
+        .iter()
+        .cloned()
+        .chain(bin_dirs(directory))
+        .chain(env::split_paths(&path));
+
+    env::join_paths(paths).ok()
+}
+
+#[allow(dead_code)]
+/// Runs a package's lifecycle script (e.g. `postinstall`) without tearing up the progress bar.
+///
+/// With `verbose` set, output is streamed line by line above the progress bar.
+/// Otherwise it is buffered and only printed if the script fails.
+pub fn run_lifecycle_script(
+    progress_bar: &ProgressBar,
+    package: &str,
+    directory: &Path,
+    script: &str,
+    verbose: bool,
+) -> Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Process::new("cmd.exe");
+        command.arg("/C").arg(script);
+        command
+    } else {
+        let mut command = Process::new("sh");
+        command.arg("-c").arg(script);
+        command
+    };
+
+    command.current_dir(directory);
+
+    if let Some(path) = script_path(directory, &[]) {
+        command.env("PATH", path);
+    }
+
+    let failed = |code: Option<i32>| VoltError::LifecycleScriptError {
+        package: package.to_string(),
+        script: script.to_string(),
+        code: code
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "signal".to_string()),
+    };
+
+    if verbose {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|_| failed(None))?;
+
+        let stderr = child.stderr.take().unwrap();
+        let stderr_bar = progress_bar.clone();
+        let prefix = format!("{}", package.bright_cyan());
+        let stderr_prefix = prefix.clone();
+
+        let stderr_thread = std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(|line| line.ok()) {
+                stderr_bar.println(format!("{} {}", stderr_prefix, line));
+            }
+        });
+
+        for line in BufReader::new(child.stdout.take().unwrap())
+            .lines()
+            .map_while(|line| line.ok())
+        {
+            progress_bar.println(format!("{} {}", prefix, line));
+        }
+
+        stderr_thread.join().ok();
+
+        let status = child.wait().map_err(|_| failed(None))?;
+
+        if !status.success() {
+            return Err(failed(status.code()).into());
+        }
+    } else {
+        let output = command.output().map_err(|_| failed(None))?;
+
+        if !output.status.success() {
+            // printed above the bar, like the streamed output
+            for stream in [&output.stdout, &output.stderr].iter() {
+                for line in String::from_utf8_lossy(stream).lines() {
+                    progress_bar.println(line);
+                }
+            }
+
+            return Err(failed(output.status.code()).into());
+        }
+    }
+
+    Ok(())
+}
+
 #[async_trait]
 impl Command for Script {
     fn help() -> String {
@@ -0,0 +1,419 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Verifies the ECDSA signatures the npm registry publishes for each package version.
+//!
+//! The registry signs `name@version:integrity` with keys served from `/-/npm/v1/keys`,
+//! and lists the signatures under `dist.signatures` in the packument.
+//!
+//! Keys are trusted on first use: once a registry's keys are cached in `~/.volt`, fetching
+//! them again only adds keys volt hasn't seen, it never replaces the cached ones.
+
+use isahc::{AsyncReadResponseExt, Request, RequestExt};
+use lazy_static::lazy_static;
+use miette::Result;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use p256::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use super::app::App;
+use super::errors::VoltError;
+use super::is_https;
+use super::voltapi::VoltPackage;
+use crate::core::model::http_manager::get_package;
+
+lazy_static! {
+    /// Keys already loaded this run, by registry.
+    static ref REGISTRY_KEYS: Mutex<BTreeMap<String, Vec<RegistryKey>>> =
+        Mutex::new(BTreeMap::new());
+}
+
+/// A public key from a registry's `/-/npm/v1/keys`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistryKey {
+    pub keyid: String,
+    /// Base64 of the DER encoded `SubjectPublicKeyInfo`.
+    pub key: String,
+    #[serde(default)]
+    pub expires: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KeysResponse {
+    keys: Vec<RegistryKey>,
+}
+
+/// Where the keys of every registry volt has verified against are kept between runs.
+fn keys_path(volt_dir: &Path) -> PathBuf {
+    volt_dir.join(".registry-keys.json")
+}
+
+async fn fetch_keys(app: &App, registry: &str) -> Result<Vec<RegistryKey>> {
+    let keys_error = || VoltError::RegistryKeysError {
+        registry: registry.to_string(),
+    };
+
+    if !is_https(registry) && !app.allow_insecure_tarballs() {
+        return Err(VoltError::InsecureRegistryKeys {
+            registry: registry.to_string(),
+        }
+        .into());
+    }
+
+    let mut request = Request::get(format!("{}/-/npm/v1/keys", registry.trim_end_matches('/')));
+
+    for (name, value) in app.registry_headers(registry) {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let mut response = request
+        .body(())
+        .map_err(VoltError::RequestBuilderError)?
+        .send_async()
+        .await
+        .map_err(VoltError::NetworkError)?;
+
+    if !response.status().is_success() {
+        return Err(keys_error().into());
+    }
+
+    let text = response.text().await.map_err(VoltError::IoTextRecError)?;
+    let keys: KeysResponse = serde_json::from_str(&text).map_err(|_| keys_error())?;
+
+    Ok(keys.keys)
+}
+
+/// The signing keys of `registry`, from this run, `~/.volt` or the registry, in that order.
+///
+/// `refresh` goes to the registry regardless, for a signature made with a key that's newer
+/// than the cached ones, and only adds the keys that weren't cached yet.
+async fn registry_keys(app: &App, registry: &str, refresh: bool) -> Result<Vec<RegistryKey>> {
+    let mut loaded = REGISTRY_KEYS.lock().await;
+
+    if !refresh {
+        if let Some(keys) = loaded.get(registry) {
+            return Ok(keys.clone());
+        }
+    }
+
+    let path = keys_path(&app.volt_dir);
+
+    let mut cached: BTreeMap<String, Vec<RegistryKey>> = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+
+    let keys = match cached.get(registry) {
+        Some(keys) if !refresh => keys.clone(),
+        _ => {
+            let mut keys = cached.get(registry).cloned().unwrap_or_default();
+
+            add_new_keys(&mut keys, fetch_keys(app, registry).await?);
+
+            cached.insert(registry.to_string(), keys.clone());
+
+            // only a cache, the keys are fetched again next time if it can't be written
+            std::fs::write(&path, serde_json::to_string_pretty(&cached).unwrap()).ok();
+
+            keys
+        }
+    };
+
+    loaded.insert(registry.to_string(), keys.clone());
+
+    Ok(keys)
+}
+
+/// Adds the `fetched` keys whose keyid isn't in `keys` yet, leaving the known ones as they are.
+fn add_new_keys(keys: &mut Vec<RegistryKey>, fetched: Vec<RegistryKey>) {
+    for key in fetched {
+        if !keys.iter().any(|known| known.keyid == key.keyid) {
+            keys.push(key);
+        }
+    }
+}
+
+/// Seconds since the unix epoch of an RFC 3339 UTC timestamp like `2025-01-29T00:00:00.000Z`,
+/// the only form the registry uses for `expires`.
+fn parse_timestamp(timestamp: &str) -> Option<i64> {
+    let field = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = timestamp.get(range)?;
+
+        if digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+
+    let separated = separators
+        .iter()
+        .all(|&(at, c)| timestamp.as_bytes().get(at).map(u8::to_ascii_uppercase) == Some(c));
+
+    if !separated {
+        return None;
+    }
+
+    // only fractions of a second may come between the seconds and the `Z`
+    let rest = timestamp.get(19..)?;
+    let fraction = rest.strip_suffix('Z').or_else(|| rest.strip_suffix('z'))?;
+
+    let fraction_read = match fraction.strip_prefix('.') {
+        Some(digits) => digits.bytes().all(|b| b.is_ascii_digit()),
+        None => fraction.is_empty(),
+    };
+
+    if !fraction_read {
+        return None;
+    }
+
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // days since the epoch of a proleptic gregorian date, counting years from march
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// Whether `key` expired before `now`, in seconds since the unix epoch. An `expires` volt can't
+/// read counts as expired.
+fn expired(key: &RegistryKey, now: i64) -> bool {
+    match key.expires.as_deref().map(parse_timestamp) {
+        Some(Some(at)) => at <= now,
+        Some(None) => true,
+        None => false,
+    }
+}
+
+/// Checks a base64 DER `signature` of `message` against a registry key.
+fn verify(key: &RegistryKey, message: &str, signature: &str) -> bool {
+    let key = base64::decode(&key.key)
+        .ok()
+        .and_then(|der| PublicKey::from_public_key_der(&der).ok())
+        .map(|key| VerifyingKey::from(&key));
+
+    let signature = base64::decode(signature)
+        .ok()
+        .and_then(|der| Signature::from_der(&der).ok());
+
+    match (key, signature) {
+        (Some(key), Some(signature)) => key.verify(message.as_bytes(), &signature).is_ok(),
+        _ => false,
+    }
+}
+
+/// Verifies the registry's signatures of a package against the integrity volt is about to
+/// install it with, failing when it has none or any of them doesn't match.
+pub async fn verify_signatures(app: &App, package: &VoltPackage) -> Result<()> {
+    let lookup_error = || VoltError::VersionLookupError {
+        name: package.name.clone(),
+    };
+
+    let registry = app.registry_for(&package.name);
+
+    let packument = get_package(&registry, &package.name, &app.registry_headers(&registry))
+        .await
+        .map_err(|_| lookup_error())?
+        .ok_or_else(lookup_error)?;
+
+    let signatures = packument
+        .versions
+        .get(&package.version)
+        .map(|version| version.dist.signatures.clone())
+        .unwrap_or_default();
+
+    if signatures.is_empty() {
+        return Err(VoltError::MissingSignature {
+            package: format!("{}@{}", package.name, package.version),
+        }
+        .into());
+    }
+
+    let message = format!("{}@{}:{}", package.name, package.version, package.integrity);
+
+    let mut keys = registry_keys(app, &registry, false).await?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+
+    for signature in signatures {
+        if !keys.iter().any(|key| key.keyid == signature.keyid) {
+            keys = registry_keys(app, &registry, true).await?;
+        }
+
+        let key = keys.iter().find(|key| key.keyid == signature.keyid);
+
+        if let Some(key) = key.filter(|key| expired(key, now)) {
+            return Err(VoltError::ExpiredRegistryKey {
+                package: format!("{}@{}", package.name, package.version),
+                keyid: signature.keyid,
+                expires: key.expires.clone().unwrap_or_default(),
+            }
+            .into());
+        }
+
+        if !key.is_some_and(|key| verify(key, &message, &signature.sig)) {
+            return Err(VoltError::InvalidSignature {
+                package: format!("{}@{}", package.name, package.version),
+                keyid: signature.keyid,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::config::VoltConfig;
+    use crate::core::utils::testing::serve;
+
+    fn key(keyid: &str, key: &str, expires: Option<&str>) -> RegistryKey {
+        RegistryKey {
+            keyid: keyid.to_string(),
+            key: key.to_string(),
+            expires: expires.map(String::from),
+        }
+    }
+
+    fn app(root: &Path, config: VoltConfig) -> App {
+        let args = clap::App::new("install").get_matches_from(vec!["install"]);
+
+        App::for_tests_in(root, args, config)
+    }
+
+    #[test]
+    fn registry_timestamps_are_read() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp("2025-01-29T00:00:00.000Z"),
+            Some(1_738_108_800)
+        );
+        assert_eq!(parse_timestamp("2000-03-01T12:30:15.5Z"), Some(951_913_815));
+
+        assert_eq!(parse_timestamp("2025-01-29"), None);
+        assert_eq!(parse_timestamp("2025-13-29T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2025-01-29T00:00:00+01:00"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn keys_expire_at_their_expiry() {
+        let now = parse_timestamp("2025-01-29T00:00:00.000Z").unwrap();
+
+        assert!(!expired(&key("a", "", None), now));
+        assert!(!expired(
+            &key("a", "", Some("2025-01-30T00:00:00.000Z")),
+            now
+        ));
+        assert!(expired(
+            &key("a", "", Some("2025-01-29T00:00:00.000Z")),
+            now
+        ));
+        assert!(expired(
+            &key("a", "", Some("2021-01-01T00:00:00.000Z")),
+            now
+        ));
+        assert!(expired(&key("a", "", Some("never")), now));
+    }
+
+    #[test]
+    fn fetched_keys_never_replace_known_ones() {
+        let mut keys = vec![key("a", "trusted", None)];
+
+        add_new_keys(
+            &mut keys,
+            vec![key("a", "forged", None), key("b", "new", None)],
+        );
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key, "trusted");
+        assert_eq!(keys[1].keyid, "b");
+    }
+
+    #[tokio::test]
+    async fn keys_are_not_fetched_over_http() {
+        let root = std::env::temp_dir().join(format!("volt-keys-http-{}", std::process::id()));
+
+        let result = fetch_keys(&app(&root, VoltConfig::default()), "http://127.0.0.1:1").await;
+
+        assert!(matches!(
+            result.unwrap_err().downcast::<VoltError>(),
+            Ok(VoltError::InsecureRegistryKeys { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn refreshing_keys_keeps_the_cached_ones() {
+        let root = std::env::temp_dir().join(format!("volt-keys-refresh-{}", std::process::id()));
+
+        let (port, handle) = serve(1, |_, _| {
+            let body = serde_json::json!({
+                "keys": [
+                    { "keyid": "a", "key": "forged" },
+                    { "keyid": "b", "key": "new" }
+                ]
+            });
+
+            ("200 OK", String::new(), serde_json::to_vec(&body).unwrap())
+        });
+
+        let registry = format!("http://127.0.0.1:{}", port);
+
+        let mut config = VoltConfig::default();
+        config.set("allow-insecure-tarballs", "true", false);
+        let app = app(&root, config);
+
+        let mut cached = BTreeMap::new();
+        cached.insert(registry.clone(), vec![key("a", "trusted", None)]);
+
+        std::fs::create_dir_all(&app.volt_dir).unwrap();
+        std::fs::write(
+            keys_path(&app.volt_dir),
+            serde_json::to_string(&cached).unwrap(),
+        )
+        .unwrap();
+
+        let keys = registry_keys(&app, &registry, true).await.unwrap();
+
+        handle.join().unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key, "trusted");
+        assert_eq!(keys[1].key, "new");
+    }
+}
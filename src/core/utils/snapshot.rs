@@ -0,0 +1,124 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! The `node_modules/.volt-lock.json` snapshot of what the last install put in `node_modules`.
+
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use ssri::Algorithm;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::app::App;
+use super::errors::VoltError;
+use super::hasher::digest;
+use super::voltapi::VoltPackage;
+use crate::commands::add::Package;
+
+/// Flags that change what gets resolved.
+const TREE_FLAGS: [&str; 4] = ["no-optional", "force-platform", "registry", "newest"];
+
+/// Contents of `node_modules/.volt-lock.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Hash of the requested packages and the flags they were installed with.
+    pub inputs: String,
+    /// Hash of the resolved tree, every `name@version` and its integrity.
+    pub tree: String,
+    pub packages: usize,
+}
+
+impl Snapshot {
+    pub fn path(node_modules_dir: &Path) -> PathBuf {
+        node_modules_dir.join(".volt-lock.json")
+    }
+
+    /// Reads the snapshot, `None` when `node_modules` has none or it can't be parsed.
+    pub fn load(node_modules_dir: &Path) -> Option<Self> {
+        std::fs::read_to_string(Self::path(node_modules_dir))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    pub fn save(&self, node_modules_dir: &Path) -> Result<()> {
+        let path = Self::path(node_modules_dir);
+
+        std::fs::create_dir_all(node_modules_dir).map_err(VoltError::CreateDirError)?;
+
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap()).map_err(|e| {
+            VoltError::WriteFileError {
+                source: e,
+                name: path.to_string_lossy().to_string(),
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Hex sha256 of some lines, sorted first so the order they were collected in doesn't matter.
+fn hash_lines(mut lines: Vec<String>) -> Result<String> {
+    lines.sort();
+
+    let hash = digest(
+        lines.iter().map(|line| format!("{}\n", line)),
+        Algorithm::Sha256,
+    )?;
+
+    Ok(hash.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Hashes what an install was asked for, before anything is resolved.
+pub fn inputs_hash(app: &App, packages: &[Package]) -> Result<String> {
+    let mut lines: Vec<String> = packages
+        .iter()
+        .map(|package| {
+            format!(
+                "{}@{}",
+                package.name,
+                package.version.as_deref().unwrap_or("latest")
+            )
+        })
+        .collect();
+
+    for flag in TREE_FLAGS.iter() {
+        if app.args.is_present(flag) {
+            lines.push(format!(
+                "--{}={}",
+                flag,
+                app.args.value_of(flag).unwrap_or_default()
+            ));
+        }
+    }
+
+    // from --node-linker or .voltrc
+    lines.push(format!("linker={:?}", app.node_linker()?));
+    lines.push(format!(
+        "install-peers={}",
+        app.has_setting("install-peers", "install-peers")
+    ));
+
+    hash_lines(lines)
+}
+
+/// Hashes a resolved tree, so two resolutions can be compared in one go.
+pub fn tree_hash(tree: &HashMap<String, VoltPackage>) -> Result<String> {
+    hash_lines(
+        tree.iter()
+            .map(|(key, package)| format!("{} {}", key, package.integrity))
+            .collect(),
+    )
+}
@@ -0,0 +1,89 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Keeps the resolved dependency tree on disk for `--low-memory` installs.
+
+use miette::Result;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::PathBuf;
+
+use super::errors::VoltError;
+use super::voltapi::VoltPackage;
+
+/// A dependency tree written to a temp file, one package per line, read back one package
+/// at a time. The file is removed when this is dropped.
+pub struct SpilledTree {
+    path: PathBuf,
+    lines: Lines<BufReader<File>>,
+}
+
+impl SpilledTree {
+    /// Writes packages to a temp file and opens it for reading from the start.
+    pub fn create<'a>(packages: impl Iterator<Item = &'a VoltPackage>) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("volt-tree-{}.jsonl", std::process::id()));
+
+        let write_error = |e| VoltError::WriteFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        };
+
+        let mut writer = BufWriter::new(File::create(&path).map_err(write_error)?);
+
+        for package in packages {
+            let line = serde_json::to_string(package).map_err(|_| VoltError::DeserializeError)?;
+            writeln!(writer, "{}", line).map_err(write_error)?;
+        }
+
+        writer.flush().map_err(write_error)?;
+        drop(writer);
+
+        let file = File::open(&path).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        })?;
+
+        Ok(Self {
+            path,
+            lines: BufReader::new(file).lines(),
+        })
+    }
+}
+
+impl Iterator for SpilledTree {
+    type Item = Result<VoltPackage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.lines.next()? {
+            Ok(line) => line,
+            Err(e) => {
+                return Some(Err(VoltError::ReadFileError {
+                    source: e,
+                    name: self.path.to_string_lossy().to_string(),
+                }
+                .into()))
+            }
+        };
+
+        Some(serde_json::from_str(&line).map_err(|_| VoltError::DeserializeError.into()))
+    }
+}
+
+impl Drop for SpilledTree {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
@@ -0,0 +1,118 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Counters for the summary printed by `--stats`. Nothing here leaves the machine.
+
+use colored::Colorize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Totals collected while packages are installed, shared by every install running concurrently.
+#[derive(Default)]
+pub struct InstallStats {
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    bytes_from_cache: AtomicU64,
+    bytes_stored: AtomicU64,
+    http2_downloads: AtomicU64,
+}
+
+impl InstallStats {
+    /// Records a package materialized from its copy in `~/.volt`.
+    pub fn cache_hit(&self, bytes: u64) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        self.bytes_from_cache.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records a package downloaded from the registry and extracted into `~/.volt`.
+    pub fn cache_miss(&self, downloaded: u64, stored: u64, http2: bool) {
+        if http2 {
+            self.http2_downloads.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        self.bytes_downloaded
+            .fetch_add(downloaded, Ordering::Relaxed);
+        self.bytes_stored.fetch_add(stored, Ordering::Relaxed);
+    }
+
+    /// Prints the summary, with the wall-clock time of each phase in the order it ran.
+    pub fn print(&self, packages: usize, phases: &[(&str, Duration)]) {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        println!("\n{}", "Install statistics".bright_blue());
+        println!("  packages:          {}", packages);
+        println!(
+            "  cache:             {} hits, {} misses",
+            load(&self.cache_hits),
+            load(&self.cache_misses)
+        );
+        println!(
+            "  downloaded:        {} ({} of {} over HTTP/2)",
+            format_bytes(load(&self.bytes_downloaded)),
+            load(&self.http2_downloads),
+            load(&self.cache_misses)
+        );
+        println!(
+            "  read from cache:   {}",
+            format_bytes(load(&self.bytes_from_cache))
+        );
+        println!(
+            "  added to ~/.volt:  {}",
+            format_bytes(load(&self.bytes_stored))
+        );
+
+        for (phase, elapsed) in phases {
+            println!(
+                "  {:<18} {:.2}s",
+                format!("{}:", phase),
+                elapsed.as_secs_f32()
+            );
+        }
+    }
+}
+
+/// Total size of the files under `path`, skipping anything that can't be read.
+pub fn directory_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Formats a byte count for humans, e.g. `1.4 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
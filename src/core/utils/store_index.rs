@@ -0,0 +1,150 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Hashes of the files extracted into the store, so an entry changed or corrupted after it was
+//! extracted is noticed instead of being copied into every project that installs it.
+
+use crate::core::utils::errors::VoltError;
+use crate::core::utils::hasher::integrity_of;
+
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use ssri::Algorithm;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File in a store entry with the sha512 of every file extracted for it.
+const INDEX_FILE: &str = ".volt-index";
+
+#[derive(Serialize, Deserialize)]
+struct StoreIndex {
+    /// Integrity of each file, by its path relative to the entry.
+    files: BTreeMap<String, String>,
+}
+
+/// What [`check_entry`] found in a store entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryState {
+    /// Every indexed file is there with the content it was extracted with.
+    Intact,
+    /// Extracted by a volt from before the index, there's nothing to check it against.
+    Unindexed,
+    /// The first file found missing or changed.
+    Corrupt(String),
+}
+
+/// Hashes of the files under `directory`, leaving out volt's own files at its top.
+fn hash_files(directory: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(directory).min_depth(1) {
+        let entry = entry.map_err(|e| VoltError::ReadFileError {
+            source: e.into(),
+            name: directory.to_string_lossy().to_string(),
+        })?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        // .volt-index, .volt-integrity
+        if entry.depth() == 1 && entry.file_name().to_string_lossy().starts_with(".volt-") {
+            continue;
+        }
+
+        let data = std::fs::read(entry.path()).map_err(|e| VoltError::ReadFileError {
+            source: e,
+            name: entry.path().to_string_lossy().to_string(),
+        })?;
+
+        let relative = entry.path().strip_prefix(directory).unwrap();
+
+        files.insert(
+            relative.to_string_lossy().replace('\\', "/"),
+            integrity_of(&data, Algorithm::Sha512)?.to_string(),
+        );
+    }
+
+    Ok(files)
+}
+
+/// Records the hashes of the files of a store entry, once it's fully extracted.
+pub fn write_index(store_directory: &Path) -> Result<()> {
+    let index = StoreIndex {
+        files: hash_files(store_directory)?,
+    };
+
+    let path = store_directory.join(INDEX_FILE);
+
+    std::fs::write(&path, serde_json::to_string(&index).unwrap()).map_err(|e| {
+        VoltError::WriteFileError {
+            source: e,
+            name: path.to_string_lossy().to_string(),
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Hashes the files of a store entry again and compares them with its index.
+pub fn check_entry(store_directory: &Path) -> EntryState {
+    let index: StoreIndex = match std::fs::read_to_string(store_directory.join(INDEX_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+    {
+        Some(index) => index,
+        None => return EntryState::Unindexed,
+    };
+
+    let files = match hash_files(store_directory) {
+        Ok(files) => files,
+        Err(_) => return EntryState::Corrupt(store_directory.to_string_lossy().to_string()),
+    };
+
+    for (path, integrity) in index.files.iter() {
+        if files.get(path) != Some(integrity) {
+            return EntryState::Corrupt(path.clone());
+        }
+    }
+
+    EntryState::Intact
+}
+
+/// Every package entry in the store, `~/.volt/react-17.0.2` and
+/// `~/.volt/@types/@types/node-16.7.1`.
+///
+/// volt's own directories (`.links`, `.node`, staging directories) aren't entries.
+pub fn store_entries(volt_dir: &Path) -> Vec<PathBuf> {
+    let mut entries = vec![];
+
+    for entry in std::fs::read_dir(volt_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || !path.is_dir() {
+            continue;
+        }
+
+        if name.starts_with('@') {
+            entries.extend(store_entries(&path));
+        } else {
+            entries.push(path);
+        }
+    }
+
+    entries.sort();
+    entries
+}
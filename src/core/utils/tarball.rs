@@ -0,0 +1,197 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Work out how a downloaded tarball is encoded and read it back as a plain tar stream.
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use miette::Result;
+use std::io::Read;
+
+use super::errors::VoltError;
+
+/// First two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A gzip stream's 10 byte header and 8 byte footer, the smallest a tarball can be.
+const GZIP_MIN_SIZE: usize = 18;
+
+/// `ustar` magic of a posix tar header, at offset 257 of the first block.
+const USTAR_MAGIC: &[u8] = b"ustar";
+const USTAR_OFFSET: usize = 257;
+
+/// How a tarball's contents are stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TarballFormat {
+    /// A `.tgz`, what registries almost always serve.
+    Gzip,
+    /// An uncompressed `.tar`.
+    Tar,
+}
+
+impl TarballFormat {
+    /// Detects the format from the magic bytes rather than trusting the file extension.
+    pub fn detect(data: &[u8], package: &str) -> Result<Self> {
+        // an error page or a truncated download
+        if data.len() < GZIP_MIN_SIZE {
+            return Err(VoltError::CorruptTarball {
+                package: package.to_string(),
+            }
+            .into());
+        }
+
+        if data.starts_with(&GZIP_MAGIC) {
+            return Ok(TarballFormat::Gzip);
+        }
+
+        if data.get(USTAR_OFFSET..USTAR_OFFSET + USTAR_MAGIC.len()) == Some(USTAR_MAGIC) {
+            return Ok(TarballFormat::Tar);
+        }
+
+        Err(VoltError::UnsupportedTarballFormat {
+            package: package.to_string(),
+        })?
+    }
+
+    /// Reads the tar stream out of a tarball in this format.
+    pub fn reader<'a>(self, data: &'a [u8]) -> Box<dyn Read + 'a> {
+        match self {
+            TarballFormat::Gzip => Box::new(GzDecoder::new(data)),
+            TarballFormat::Tar => Box::new(data),
+        }
+    }
+}
+
+/// Undoes an HTTP `Content-Encoding`, giving back the tarball as it was published.
+///
+/// Decoding stops once the output passes `limit` bytes, so a small body can't expand
+/// into an unbounded allocation.
+pub fn decode_content(data: &[u8], encoding: &str, package: &str, limit: u64) -> Result<Vec<u8>> {
+    let decode_error = |e| VoltError::ContentDecodeError {
+        source: e,
+        package: package.to_string(),
+    };
+
+    let mut decoded = Vec::new();
+
+    match encoding.trim().to_lowercase().as_str() {
+        "identity" => decoded.extend_from_slice(data),
+        "gzip" | "x-gzip" => {
+            GzDecoder::new(data)
+                .take(limit + 1)
+                .read_to_end(&mut decoded)
+                .map_err(decode_error)?;
+        }
+        // http's deflate is zlib wrapped
+        "deflate" => {
+            ZlibDecoder::new(data)
+                .take(limit + 1)
+                .read_to_end(&mut decoded)
+                .map_err(decode_error)?;
+        }
+        encoding => {
+            return Err(VoltError::UnsupportedContentEncoding {
+                encoding: encoding.to_string(),
+                package: package.to_string(),
+            }
+            .into());
+        }
+    }
+
+    if decoded.len() as u64 > limit {
+        return Err(VoltError::TarballTooLarge {
+            package: package.to_string(),
+            size: decoded.len() as u64,
+            limit,
+        }
+        .into());
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiny_tarballs_are_corrupt() {
+        for size in &[0, 3, 9] {
+            let data = vec![0x1f; *size];
+            let error = TarballFormat::detect(&data, "react").unwrap_err();
+
+            assert_eq!(
+                error.to_string(),
+                "the tarball for react is truncated or corrupt"
+            );
+        }
+    }
+
+    #[test]
+    fn formats_are_detected_from_magic_bytes() {
+        let mut gzip = vec![0x1f, 0x8b];
+        gzip.resize(GZIP_MIN_SIZE, 0);
+
+        let mut tar = vec![0; 512];
+        tar[USTAR_OFFSET..USTAR_OFFSET + USTAR_MAGIC.len()].copy_from_slice(USTAR_MAGIC);
+
+        assert_eq!(
+            TarballFormat::detect(&gzip, "react").unwrap(),
+            TarballFormat::Gzip
+        );
+        assert_eq!(
+            TarballFormat::detect(&tar, "react").unwrap(),
+            TarballFormat::Tar
+        );
+        assert!(TarballFormat::detect(b"<html>not found</html>", "react").is_err());
+    }
+
+    #[test]
+    fn content_encodings_are_undone() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"package").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        assert_eq!(
+            decode_content(&gzipped, "gzip", "react", 1024).unwrap(),
+            b"package"
+        );
+        assert_eq!(
+            decode_content(&gzipped, " X-GZIP ", "react", 1024).unwrap(),
+            b"package"
+        );
+        assert_eq!(
+            decode_content(b"package", "identity", "react", 1024).unwrap(),
+            b"package"
+        );
+        assert!(decode_content(&gzipped, "br", "react", 1024).is_err());
+    }
+
+    #[test]
+    fn decoding_stops_past_the_limit() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 4096]).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        assert!(decode_content(&gzipped, "gzip", "react", 1024).is_err());
+        assert!(decode_content(&gzipped, "gzip", "react", 4096).is_ok());
+    }
+}
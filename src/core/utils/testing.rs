@@ -0,0 +1,196 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Helpers shared by tests that install packages: tarballs, a local http server and a
+//! registry on top of it.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use ssri::{Algorithm, IntegrityOpts};
+
+use super::events::{InstallEvent, Reporter};
+
+/// A tar archive holding `files`, as `(path, contents)`.
+pub fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap()
+}
+
+/// Status line, extra headers and body of a test server response.
+pub type Reply = (&'static str, String, Vec<u8>);
+
+/// Reads a request's line and headers from `stream` and writes `respond`'s answer, returning
+/// the request lowercased.
+fn answer<F>(mut stream: TcpStream, port: u16, respond: &F) -> String
+where
+    F: Fn(&str, u16) -> Reply,
+{
+    let mut request = String::new();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    // the request line and headers, up to the blank line ending them
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        request.push_str(&line);
+
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+    }
+
+    let (status, headers, body) = respond(&request, port);
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        headers,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(&body).unwrap();
+
+    request.to_lowercase()
+}
+
+/// Serves `requests` connections on localhost, answering each with `respond(request, port)`,
+/// and returns the requests it got.
+pub fn serve<F>(requests: usize, respond: F) -> (u16, std::thread::JoinHandle<Vec<String>>)
+where
+    F: Fn(&str, u16) -> Reply + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let handle = std::thread::spawn(move || {
+        listener
+            .incoming()
+            .take(requests)
+            .map(|stream| answer(stream.unwrap(), port, &respond))
+            .collect()
+    });
+
+    (port, handle)
+}
+
+/// Serves localhost for as long as the test runs, answering every connection with
+/// `respond(request, port)`. The requests it got so far are in the returned list.
+pub fn serve_forever<F>(respond: F) -> (u16, Arc<Mutex<Vec<String>>>)
+where
+    F: Fn(&str, u16) -> Reply + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let received = Arc::new(Mutex::new(vec![]));
+    let requests = received.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let request = answer(stream, port, &respond);
+            requests.lock().unwrap().push(request);
+        }
+    });
+
+    (port, received)
+}
+
+/// A registry on localhost publishing `name@version` packages without dependencies, as
+/// packuments at `/<name>`, volt responses at `/cdn/<name>/data.json` and tarballs at
+/// `/<name>/-/<name>-<version>.tgz`.
+///
+/// Returns the registry's url and the requests it got.
+pub fn fake_registry(packages: &[(&str, &str)]) -> (String, Arc<Mutex<Vec<String>>>) {
+    let packages: Vec<(String, String, Vec<u8>)> = packages
+        .iter()
+        .map(|(name, version)| {
+            let manifest = format!(r#"{{ "name": "{}", "version": "{}" }}"#, name, version);
+            let data = tarball(&[
+                ("package/package.json", &manifest),
+                ("package/index.js", "module.exports = 1;\n"),
+            ]);
+
+            (name.to_string(), version.to_string(), data)
+        })
+        .collect();
+
+    let (port, received) = serve_forever(move |request, port| {
+        let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+        for (name, version, data) in &packages {
+            let tarball_path = format!("/{0}/-/{0}-{1}.tgz", name, version);
+
+            let body = if path == format!("/{}", name) {
+                serde_json::json!({
+                    "name": name,
+                    "dist-tags": { "latest": version },
+                    "versions": { version.as_str(): { "version": version } }
+                })
+            } else if path == format!("/cdn/{}/data.json", name) {
+                let integrity = IntegrityOpts::new()
+                    .algorithm(Algorithm::Sha512)
+                    .chain(data)
+                    .result();
+
+                serde_json::json!({
+                    "latest": version,
+                    "schema": 0,
+                    version.as_str(): {
+                        format!("{}@{}", name, version): {
+                            "integrity": integrity.to_string(),
+                            "tarball": format!("http://127.0.0.1:{}{}", port, tarball_path)
+                        }
+                    }
+                })
+            } else if path == tarball_path {
+                return ("200 OK", String::new(), data.clone());
+            } else {
+                continue;
+            };
+
+            return ("200 OK", String::new(), serde_json::to_vec(&body).unwrap());
+        }
+
+        ("404 Not Found", String::new(), vec![])
+    });
+
+    (format!("http://127.0.0.1:{}", port), received)
+}
+
+/// Keeps every event of an install, in the order they were reported.
+#[derive(Default)]
+pub struct RecordingReporter {
+    pub events: Mutex<Vec<InstallEvent>>,
+}
+
+impl Reporter for RecordingReporter {
+    fn report(&self, event: InstallEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
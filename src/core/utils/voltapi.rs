@@ -13,7 +13,9 @@
 
 use std::io::Write;
 
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -28,27 +30,207 @@ pub struct VoltPackage {
     pub name: String,
     pub version: String,
     pub tarball: String,
+    #[serde(default)]
     pub bin: Option<HashMap<String, String>>,
     pub integrity: String,
+    #[serde(default)]
     pub peer_dependencies: Option<Vec<String>>,
+    #[serde(default)]
+    pub peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
+    #[serde(default)]
     pub dependencies: Option<Vec<String>>,
+    #[serde(default)]
+    pub optional_dependencies: Option<Vec<String>>,
+    #[serde(default)]
+    pub os: Option<Vec<String>>,
+    #[serde(default)]
+    pub cpu: Option<Vec<String>>,
+    #[serde(default)]
+    pub funding: Option<Funding>,
+    /// Size of the unpacked tarball in bytes, when the registry reports it.
+    #[serde(default)]
+    pub unpacked_size: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JSONVoltResponse {
     pub latest: String,
     pub schema: u8,
-    #[serde(flatten)]
+    #[serde(flatten, deserialize_with = "versions")]
     pub versions: HashMap<String, HashMap<String, JSONVoltPackage>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JSONVoltPackage {
+    #[serde(default)]
     pub integrity: String,
+    #[serde(default)]
     pub tarball: String,
+    /// sha1 hex digest, the only checksum legacy packages published before SRI have.
+    #[serde(default)]
+    pub shasum: Option<String>,
+    /// Keyed by command name. The string form npm allows is stored under an empty key, since
+    /// the command is named after the package and that is only known once converted.
+    #[serde(default, deserialize_with = "deserialize_bin")]
     pub bin: Option<HashMap<String, String>>,
+    #[serde(default)]
     pub dependencies: Option<Vec<String>>,
+    #[serde(default, alias = "optionalDependencies")]
+    pub optional_dependencies: Option<Vec<String>>,
+    #[serde(default)]
     pub peer_dependencies: Option<Vec<String>>,
+    #[serde(default, alias = "peerDependenciesMeta")]
+    pub peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub os: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub cpu: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub funding: Option<Funding>,
+    #[serde(default, alias = "unpackedSize", deserialize_with = "lenient")]
+    pub unpacked_size: Option<u64>,
+}
+
+/// Reads an optional field some packages publish in an unexpected shape as absent, instead of
+/// failing the whole packument over metadata volt can install without. Dependency fields
+/// aren't read this way, a package installed without its dependencies would be broken.
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Reads the versions of a response, skipping top-level fields that aren't objects so a newer
+/// registry adding one doesn't break older volts. A version that doesn't read is an error.
+fn versions<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, HashMap<String, JSONVoltPackage>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    HashMap::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .filter(|(_, packages)| packages.is_object())
+        .map(|(version, packages)| {
+            serde_json::from_value(packages)
+                .map(|packages| (version, packages))
+                .map_err(de::Error::custom)
+        })
+        .collect()
+}
+
+/// Accepts `os` and `cpu` written as a single string as well as a list.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(entry) => Some(vec![entry]),
+        value => serde_json::from_value(value).ok(),
+    })
+}
+
+/// Accepts `bin` as a map of command names to paths, or as a single path.
+fn deserialize_bin<'de, D>(deserializer: D) -> Result<Option<HashMap<String, String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(path) => {
+            let mut bin = HashMap::new();
+            bin.insert(String::new(), path);
+            Some(bin)
+        }
+        value => serde_json::from_value(value).ok(),
+    })
+}
+
+/// An entry of a package's `peerDependenciesMeta`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct PeerDependencyMeta {
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// The `funding` field of a package, which npm allows to be a url, an object or a list of either.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Funding {
+    Url(String),
+    Object {
+        #[serde(rename = "type", default)]
+        kind: Option<String>,
+        url: String,
+    },
+    List(Vec<Funding>),
+}
+
+impl Funding {
+    /// All funding urls, in the order the package lists them.
+    pub fn urls(&self) -> Vec<String> {
+        match self {
+            Self::Url(url) => vec![url.clone()],
+            Self::Object { url, .. } => vec![url.clone()],
+            Self::List(list) => list.iter().flat_map(|funding| funding.urls()).collect(),
+        }
+    }
+}
+
+/// Splits a dependency written as `react`, `react@^17.0.0` or `@types/node@16.7.1` into its
+/// package name and version.
+pub fn split_dependency(dependency: &str) -> (&str, Option<&str>) {
+    // the @ of a scope isn't a version separator
+    match dependency.rfind('@') {
+        Some(index) if index > 0 => (&dependency[..index], Some(&dependency[index + 1..])),
+        _ => (dependency, None),
+    }
+}
+
+/// The package name of a dependency written as `react`, `react@^17.0.0` or `@types/node@16.7.1`.
+pub fn dependency_name(dependency: &str) -> &str {
+    split_dependency(dependency).0
+}
+
+/// The scope of a package name including its `@` (`@types` for `@types/node`), `None` when
+/// the package isn't scoped.
+pub fn package_scope(name: &str) -> Option<&str> {
+    if !name.starts_with('@') {
+        return None;
+    }
+
+    name.split('/').next()
+}
+
+/// Normalizes a package name for comparison, so a name typed as ` React` matches the
+/// registry's canonical `react`.
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl VoltPackage {
+    /// Whether a peer dependency (`react` or `react@^17.0.0`) is marked optional in `peerDependenciesMeta`.
+    pub fn is_optional_peer(&self, peer: &str) -> bool {
+        self.peer_dependencies_meta
+            .as_ref()
+            .and_then(|meta| meta.get(dependency_name(peer)))
+            .map(|meta| meta.optional)
+            .unwrap_or(false)
+    }
+
+    /// Whether a dependency is listed in the package's `optionalDependencies`.
+    pub fn is_optional_dependency(&self, dependency: &str) -> bool {
+        let name = dependency_name(dependency);
+
+        self.optional_dependencies
+            .as_deref()
+            .unwrap_or(&[])
+            .iter()
+            .any(|optional| dependency_name(optional) == name)
+    }
 }
 
 impl VoltResponse {
@@ -58,3 +240,94 @@ impl VoltResponse {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies_split_after_the_scope() {
+        assert_eq!(split_dependency("react"), ("react", None));
+        assert_eq!(
+            split_dependency("react@^17.0.0"),
+            ("react", Some("^17.0.0"))
+        );
+        assert_eq!(split_dependency("@types/node"), ("@types/node", None));
+        assert_eq!(
+            split_dependency("@babel/preset-env@7.15.0"),
+            ("@babel/preset-env", Some("7.15.0"))
+        );
+        assert_eq!(dependency_name("@types/node@16.7.1"), "@types/node");
+    }
+
+    #[test]
+    fn scopes_include_their_at() {
+        assert_eq!(package_scope("@types/node"), Some("@types"));
+        assert_eq!(package_scope("@babel/preset-env"), Some("@babel"));
+        assert_eq!(package_scope("react"), None);
+    }
+
+    #[test]
+    fn unexpected_registry_metadata_is_tolerated() {
+        let response: JSONVoltResponse = serde_json::from_value(serde_json::json!({
+            "latest": "1.0.0",
+            "schema": 0,
+            "generated": "2021-09-01T00:00:00Z",
+            "mirrors": ["https://mirror.example"],
+            "1.0.0": {
+                "cli@1.0.0": {
+                    "integrity": "sha512-abc",
+                    "tarball": "https://r.example/cli-1.0.0.tgz",
+                    "bin": "bin/cli.js",
+                    "os": "linux",
+                    "funding": 42,
+                    "deprecatedField": "yes"
+                },
+                "tools@1.0.0": {
+                    "integrity": "sha512-def",
+                    "tarball": "https://r.example/tools-1.0.0.tgz",
+                    "bin": { "tool-a": "a.js", "tool-b": "b.js" },
+                    "dependencies": ["cli@1.0.0"],
+                    "unpackedSize": "large"
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(response.versions.len(), 1);
+
+        let packages = &response.versions["1.0.0"];
+        let cli = &packages["cli@1.0.0"];
+        let tools = &packages["tools@1.0.0"];
+
+        assert_eq!(cli.bin.as_ref().unwrap()[""], "bin/cli.js");
+        assert_eq!(cli.os, Some(vec!["linux".to_string()]));
+        assert_eq!(cli.dependencies, None);
+        assert_eq!(cli.funding, None);
+
+        assert_eq!(tools.bin.as_ref().unwrap().len(), 2);
+        assert_eq!(tools.bin.as_ref().unwrap()["tool-b"], "b.js");
+        assert_eq!(tools.dependencies, Some(vec!["cli@1.0.0".to_string()]));
+        assert_eq!(tools.unpacked_size, None);
+    }
+
+    #[test]
+    fn malformed_dependencies_are_an_error() {
+        let response = |package: serde_json::Value| {
+            serde_json::from_value::<JSONVoltResponse>(serde_json::json!({
+                "latest": "1.0.0",
+                "schema": 0,
+                "1.0.0": { "tools@1.0.0": package }
+            }))
+        };
+
+        for field in &["dependencies", "optionalDependencies", "peer_dependencies"] {
+            let mut package = serde_json::json!({ "integrity": "sha512-def", "tarball": "t" });
+            package[field] = serde_json::json!({ "not": "a list" });
+
+            assert!(response(package).is_err(), "{} was accepted", field);
+        }
+
+        assert!(response(serde_json::json!("not a package")).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Find the members of a workspace declared in the root package.json.
+
+use miette::Result;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::errors::VoltError;
+
+/// Reads the `workspaces` field of a package.json, either a list of patterns or `{ "packages": [...] }`.
+pub fn workspace_patterns(root: &Path) -> Vec<String> {
+    let manifest = std::fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<Value>(&data).ok());
+
+    let workspaces = match manifest
+        .as_ref()
+        .and_then(|manifest| manifest.get("workspaces"))
+    {
+        Some(Value::Object(config)) => config.get("packages"),
+        workspaces => workspaces,
+    };
+
+    workspaces
+        .and_then(Value::as_array)
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Expands a workspace pattern into the directories it names: `dir`, `dir/*` or `dir/**`.
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let pattern = pattern.trim_start_matches("./");
+
+    if let Some(parent) = pattern.strip_suffix("/**") {
+        WalkDir::new(root.join(parent))
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != "node_modules")
+            .flatten()
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect()
+    } else if let Some(parent) = pattern.strip_suffix("/*") {
+        std::fs::read_dir(root.join(parent))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        vec![root.join(pattern)]
+    }
+}
+
+/// Lists the members of the workspace rooted at `root` as `(package name, directory)` pairs.
+pub fn workspace_members(root: &Path) -> Vec<(String, PathBuf)> {
+    let patterns = workspace_patterns(root);
+
+    let excluded: Vec<PathBuf> = patterns
+        .iter()
+        .filter_map(|pattern| pattern.strip_prefix('!'))
+        .flat_map(|pattern| expand_pattern(root, pattern))
+        .collect();
+
+    let mut members: Vec<(String, PathBuf)> = patterns
+        .iter()
+        .filter(|pattern| !pattern.starts_with('!'))
+        .flat_map(|pattern| expand_pattern(root, pattern))
+        .filter(|dir| !excluded.contains(dir))
+        .filter_map(|dir| {
+            let manifest = std::fs::read_to_string(dir.join("package.json")).ok()?;
+            let manifest: Value = serde_json::from_str(&manifest).ok()?;
+            let name = manifest.get("name")?.as_str()?.to_string();
+
+            Some((name, dir))
+        })
+        .collect();
+
+    members.sort();
+    members.dedup();
+
+    members
+}
+
+/// Finds the directory of a workspace member by its package name (or directory name).
+pub fn find_workspace_member(root: &Path, name: &str) -> Result<PathBuf> {
+    if workspace_patterns(root).is_empty() {
+        return Err(VoltError::NotAWorkspace.into());
+    }
+
+    let members = workspace_members(root);
+
+    members
+        .iter()
+        .find(|(member, _)| member == name)
+        .or_else(|| {
+            members
+                .iter()
+                .find(|(_, dir)| dir.file_name().is_some_and(|dir| dir == name))
+        })
+        .map(|(_, dir)| dir.clone())
+        .ok_or_else(|| {
+            VoltError::WorkspaceMemberNotFound {
+                name: name.to_string(),
+                members: members
+                    .iter()
+                    .map(|(member, _)| member.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            }
+            .into()
+        })
+}
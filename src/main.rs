@@ -22,7 +22,12 @@ use crate::core::command::Command;
 use crate::core::utils::app::App;
 use clap::{Arg, ArgMatches};
 use colored::Colorize;
-use commands::{compress::Compress, init::Init};
+use commands::{
+    audit::Audit, clean::Clean, compress::Compress, config::Config, diff::Diff, fund::Fund,
+    import::Import, init::Init, install::Install, link::Link, list::List, outdated::Outdated,
+    resolve::Resolve, run::Run, store::Store, unlink::Unlink, update::Update, use_node::Use,
+    view::View, why_not::WhyNot,
+};
 
 use crate::commands::add::*;
 
@@ -36,10 +41,82 @@ pub async fn map_subcommand(matches: ArgMatches) -> miette::Result<()> {
             let app = Arc::new(App::initialize(args)?);
             Init::exec(app).await
         }
+        Some(("clean", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Clean::exec(app).await
+        }
         Some(("compress", args)) => {
             let app = Arc::new(App::initialize(args)?);
             Compress::exec(app).await
         }
+        Some(("audit", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Audit::exec(app).await
+        }
+        Some(("config", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Config::exec(app).await
+        }
+        Some(("diff", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Diff::exec(app).await
+        }
+        Some(("fund", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Fund::exec(app).await
+        }
+        Some(("import", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Import::exec(app).await
+        }
+        Some(("install", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Install::exec(app).await
+        }
+        Some(("list", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            List::exec(app).await
+        }
+        Some(("outdated", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Outdated::exec(app).await
+        }
+        Some(("update", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Update::exec(app).await
+        }
+        Some(("resolve", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Resolve::exec(app).await
+        }
+        Some(("link", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Link::exec(app).await
+        }
+        Some(("unlink", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Unlink::exec(app).await
+        }
+        Some(("run", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Run::exec(app).await
+        }
+        Some(("store", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Store::exec(app).await
+        }
+        Some(("use", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            Use::exec(app).await
+        }
+        Some(("view", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            View::exec(app).await
+        }
+        Some(("why-not", args)) => {
+            let app = Arc::new(App::initialize(args)?);
+            WhyNot::exec(app).await
+        }
         _ => Ok(()),
     }
 }
@@ -55,12 +132,48 @@ Usage: {} [{}] [{}]
 Displays help information.
 
 Commands:
-  {} add"#,
+  {} add
+  {} audit
+  {} clean
+  {} config
+  {} diff
+  {} fund
+  {} import
+  {} install
+  {} link
+  {} list
+  {} outdated
+  {} resolve
+  {} run
+  {} store
+  {} unlink
+  {} update
+  {} use
+  {} view
+  {} why-not"#,
         "volt".bright_green().bold(),
         "1.0.0",
         "volt".bright_green().bold(),
         "command".bright_cyan(),
         "flags".bright_blue(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
+        "-".bright_magenta(),
         "-".bright_magenta()
     );
 
@@ -70,6 +183,12 @@ Commands:
         "<package-name>".bright_blue()
     );
 
+    let install_usage = format!(
+        "{} install {}",
+        "volt".bright_green().bold(),
+        "[package-name]".bright_blue()
+    );
+
     let init_usage = format!(
         "{} init {}",
         "volt".bright_green().bold(),
@@ -82,11 +201,213 @@ Commands:
         "[flags]".bright_blue(),
     );
 
+    let audit_usage = format!(
+        "{} audit {}",
+        "volt".bright_green().bold(),
+        "[flags]".bright_blue(),
+    );
+
+    let config_usage = format!(
+        "{} config {}",
+        "volt".bright_green().bold(),
+        "<get|set|delete|list>".bright_blue(),
+    );
+
+    let fund_usage = format!("{} fund", "volt".bright_green().bold());
+
+    let list_usage = format!(
+        "{} list {}",
+        "volt".bright_green().bold(),
+        "[flags]".bright_blue()
+    );
+
+    let link_usage = format!(
+        "{} link {}",
+        "volt".bright_green().bold(),
+        "[package-name]".bright_blue()
+    );
+
+    let unlink_usage = format!(
+        "{} unlink {}",
+        "volt".bright_green().bold(),
+        "[package-name]".bright_blue()
+    );
+
+    let import_usage = format!("{} import", "volt".bright_green().bold());
+
+    let update_usage = format!(
+        "{} update {}",
+        "volt".bright_green().bold(),
+        "[flags]".bright_blue()
+    );
+
+    let outdated_usage = format!(
+        "{} outdated {}",
+        "volt".bright_green().bold(),
+        "[flags]".bright_blue()
+    );
+
+    let run_usage = format!(
+        "{} run {}",
+        "volt".bright_green().bold(),
+        "<script>".bright_blue()
+    );
+
+    let store_usage = format!(
+        "{} store {}",
+        "volt".bright_green().bold(),
+        "<add|verify>".bright_blue()
+    );
+
+    let use_usage = format!(
+        "{} use {}",
+        "volt".bright_green().bold(),
+        "[version]".bright_blue()
+    );
+
+    let clean_usage = format!(
+        "{} clean {}",
+        "volt".bright_green().bold(),
+        "[--store]".bright_blue()
+    );
+
+    let diff_usage = format!(
+        "{} diff {} {}",
+        "volt".bright_green().bold(),
+        "<package-name>@<version>".bright_blue(),
+        "<package-name>@<version>".bright_blue()
+    );
+
+    let view_usage = format!(
+        "{} view {} {}",
+        "volt".bright_green().bold(),
+        "<package-name>".bright_blue(),
+        "[field]".bright_blue()
+    );
+
+    let why_not_usage = format!(
+        "{} why-not {}",
+        "volt".bright_green().bold(),
+        "<package-name>@<version>".bright_blue()
+    );
+
+    let resolve_usage = format!(
+        "{} resolve {}",
+        "volt".bright_green().bold(),
+        "<package-name>".bright_blue()
+    );
+
+    let install_args = [
+        Arg::new("save-dev")
+            .short('D')
+            .long("save-dev")
+            .alias("dev")
+            .conflicts_with_all(&["save-peer", "save-optional"])
+            .about("Save to devDependencies."),
+        Arg::new("save-peer")
+            .long("save-peer")
+            .conflicts_with("save-optional")
+            .about("Save to peerDependencies."),
+        Arg::new("save-optional")
+            .long("save-optional")
+            .about("Save to optionalDependencies."),
+        Arg::new("no-save")
+            .long("no-save")
+            .about("Install without saving to package.json."),
+        Arg::new("save-exact")
+            .short('E')
+            .long("save-exact")
+            .about("Save the exact version instead of a ^ range."),
+        Arg::new("save-prefix")
+            .long("save-prefix")
+            .takes_value(true)
+            .about("Prefix saved before new dependency versions (defaults to ^)."),
+        Arg::new("tag")
+            .long("tag")
+            .takes_value(true)
+            .about("Install the version behind a dist-tag when no version is given."),
+        Arg::new("newest")
+            .long("newest")
+            .conflicts_with("tag")
+            .about("Install the highest published version instead of `latest`."),
+        Arg::new("no-fund")
+            .long("no-fund")
+            .about("Don't print the funding message after installing."),
+        Arg::new("force-platform")
+            .long("force-platform")
+            .takes_value(true)
+            .about("Install packages for <os>-<arch> instead of the host platform."),
+        Arg::new("node-linker")
+            .long("node-linker")
+            .takes_value(true)
+            .possible_values(&["copy", "hardlink"])
+            .about("How packages are materialized into node_modules."),
+        Arg::new("registry")
+            .long("registry")
+            .takes_value(true)
+            .about("Registry to fetch packages from for this install."),
+        Arg::new("progress")
+            .long("progress")
+            .takes_value(true)
+            .possible_values(&["bar", "ndjson"])
+            .about("Report progress as bars or as one json object per line on stderr."),
+        Arg::new("no-optional")
+            .long("no-optional")
+            .about("Don't install optionalDependencies."),
+        Arg::new("stats")
+            .long("stats")
+            .about("Print cache, download and timing statistics after installing."),
+        Arg::new("low-memory")
+            .long("low-memory")
+            .about("Keep the resolved tree on disk and install a few packages at a time."),
+        Arg::new("legacy-peer-deps")
+            .long("legacy-peer-deps")
+            .about("Install even when peer dependencies conflict, warning about them."),
+        Arg::new("verify-store")
+            .long("verify-store")
+            .about("Check packages in the store for changed files before using them."),
+        Arg::new("adaptive-concurrency")
+            .long("adaptive-concurrency")
+            .about("Adjust how many packages download at once to the connection's throughput."),
+        Arg::new("allow-insecure-tarballs")
+            .long("allow-insecure-tarballs")
+            .about("Allow fetching tarballs the registry serves over plain http."),
+        Arg::new("record-order")
+            .long("record-order")
+            .takes_value(true)
+            .about("Write the order packages are fetched, extracted and linked in to a file."),
+        Arg::new("install-peers")
+            .long("install-peers")
+            .about("Install missing peer dependencies along with the packages needing them."),
+        Arg::new("verify-signatures")
+            .long("verify-signatures")
+            .about("Fail on packages without a valid registry signature."),
+        Arg::new("fetch-only")
+            .long("fetch-only")
+            .about("Download the tree into the store without touching the project."),
+        Arg::new("link-strategy")
+            .long("link-strategy")
+            .takes_value(true)
+            .possible_values(&["hoisted", "isolated", "pnpm-strict"])
+            .about("How the installed tree is laid out in node_modules."),
+        Arg::new("max-tarball-size")
+            .long("max-tarball-size")
+            .takes_value(true)
+            .about("Refuse tarballs larger than this many bytes (default 512 MiB)."),
+    ];
+
     let app = clap::App::new("volt")
         .version("1.0.0")
         .author("XtremeDevX <xtremedevx@gmail.com>")
         .about("Manage your NPM packages")
         .override_help(volt_help.as_str())
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .about("Output verbose messages on internal operations."),
+        )
         .subcommand(
             clap::App::new("add")
                 .about("Add a package to the dependencies for your project.")
@@ -96,7 +417,49 @@ Commands:
                         .about("Packages to add to the dependencies for your project.")
                         .multiple_values(true)
                         .required(true),
-                ),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .about("Add the packages to this workspace member instead of the root.")
+                        .long("workspace")
+                        .takes_value(true),
+                )
+                .args(&install_args),
+        )
+        .subcommand(
+            clap::App::new("install")
+                .about("Install the dependencies for your project, or add packages to it.")
+                .override_usage(install_usage.as_str())
+                .arg(
+                    Arg::new("package-names")
+                        .about("Packages to add to the dependencies for your project.")
+                        .multiple_values(true),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .conflicts_with("package-names")
+                        .about("Install nothing, fail if node_modules doesn't match volt.lock."),
+                )
+                .arg(
+                    Arg::new("include")
+                        .long("include")
+                        .takes_value(true)
+                        .multiple_values(true)
+                        .use_delimiter(true)
+                        .possible_values(&["prod", "dev", "optional", "peer"])
+                        .about("Also install these package.json sections."),
+                )
+                .arg(
+                    Arg::new("omit")
+                        .long("omit")
+                        .takes_value(true)
+                        .multiple_values(true)
+                        .use_delimiter(true)
+                        .possible_values(&["prod", "dev", "optional", "peer"])
+                        .about("Skip these package.json sections."),
+                )
+                .args(&install_args),
         )
         .subcommand(
             clap::App::new("init")
@@ -108,6 +471,294 @@ Commands:
             clap::App::new("compress")
                 .about("Interactively create and edit your package.json file.")
                 .override_usage(compress_usage.as_str()),
+        )
+        .subcommand(
+            clap::App::new("audit")
+                .about("Check installed packages for known vulnerabilities.")
+                .override_usage(audit_usage.as_str())
+                .arg(
+                    Arg::new("audit-level")
+                        .long("audit-level")
+                        .takes_value(true)
+                        .possible_values(&["info", "low", "moderate", "high", "critical"])
+                        .about("Minimum severity that fails the audit."),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .about("Output the report as json."),
+                )
+                .subcommand(
+                    clap::App::new("fix")
+                        .about("Install patched versions of vulnerable dependencies.")
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .about("Also apply upgrades outside the ranges in package.json."),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::App::new("clean")
+                .about("Remove the project's node_modules.")
+                .override_usage(clean_usage.as_str())
+                .arg(
+                    Arg::new("store")
+                        .long("store")
+                        .about("Also remove the project's packages from the volt store."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("config")
+                .about("Manage volt configuration stored in .voltrc files.")
+                .override_usage(config_usage.as_str())
+                .subcommand(
+                    clap::App::new("get")
+                        .about("Print the value of a key.")
+                        .arg(Arg::new("key").required(true)),
+                )
+                .subcommand(
+                    clap::App::new("set")
+                        .about("Set the value of a key.")
+                        .arg(Arg::new("key").required(true))
+                        .arg(Arg::new("value").required(true))
+                        .arg(
+                            Arg::new("global")
+                                .short('g')
+                                .long("global")
+                                .about("Write to ~/.voltrc instead of ./.voltrc"),
+                        ),
+                )
+                .subcommand(
+                    clap::App::new("delete")
+                        .about("Remove a key.")
+                        .arg(Arg::new("key").required(true))
+                        .arg(
+                            Arg::new("global")
+                                .short('g')
+                                .long("global")
+                                .about("Remove from ~/.voltrc instead of ./.voltrc"),
+                        ),
+                )
+                .subcommand(clap::App::new("list").about("Print all settings.")),
+        )
+        .subcommand(
+            clap::App::new("diff")
+                .about("Compare the published files of two package versions.")
+                .override_usage(diff_usage.as_str())
+                .arg(
+                    Arg::new("first")
+                        .about("Package version to compare from, e.g. react@16.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("second")
+                        .about("Package version to compare to, e.g. react@17.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("name-only")
+                        .long("name-only")
+                        .about("Only list the paths that changed."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("fund")
+                .about("List installed packages that are looking for funding.")
+                .override_usage(fund_usage.as_str()),
+        )
+        .subcommand(
+            clap::App::new("import")
+                .about("Install the versions pinned by package-lock.json.")
+                .override_usage(import_usage.as_str()),
+        )
+        .subcommand(
+            clap::App::new("list")
+                .alias("ls")
+                .about("List installed packages.")
+                .override_usage(list_usage.as_str())
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .about("How many levels of dependencies to list (defaults to 0)."),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .about("Print the listing as json."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("outdated")
+                .about("Check for outdated packages.")
+                .override_usage(outdated_usage.as_str())
+                .arg(
+                    Arg::new("interactive")
+                        .short('i')
+                        .long("interactive")
+                        .about("Pick packages to upgrade from a checklist."),
+                )
+                .arg(
+                    Arg::new("offline")
+                        .long("offline")
+                        .about("Compare node_modules with volt.lock only."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("update")
+                .about("Update dependencies within their ranges in package.json.")
+                .override_usage(update_usage.as_str())
+                .arg(
+                    Arg::new("latest")
+                        .long("latest")
+                        .about("Update to the latest version, rewriting package.json."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("resolve")
+                .about("Print the resolved dependency tree as json without installing.")
+                .override_usage(resolve_usage.as_str())
+                .arg(
+                    Arg::new("package-names")
+                        .about("Packages to resolve.")
+                        .multiple_values(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .takes_value(true)
+                        .about("Resolve the version behind a dist-tag when no version is given."),
+                )
+                .arg(
+                    Arg::new("force-platform")
+                        .long("force-platform")
+                        .takes_value(true)
+                        .about("Resolve packages for <os>-<arch> instead of the host platform."),
+                )
+                .arg(
+                    Arg::new("no-optional")
+                        .long("no-optional")
+                        .about("Leave optionalDependencies out of the tree."),
+                )
+                .arg(
+                    Arg::new("registry")
+                        .long("registry")
+                        .takes_value(true)
+                        .about("Registry to resolve dist-tags against."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("link")
+                .about("Symlink a package in development into the global link store or a project.")
+                .override_usage(link_usage.as_str())
+                .arg(
+                    Arg::new("package-names")
+                        .about("Linked packages to symlink into node_modules.")
+                        .multiple_values(true),
+                )
+                .arg(
+                    Arg::new("windows-link-type")
+                        .long("windows-link-type")
+                        .takes_value(true)
+                        .possible_values(&["junction", "symlink", "auto"])
+                        .about("Link with junctions or symlinks on windows (defaults to auto)."),
+                )
+                .arg(
+                    Arg::new("install-links")
+                        .long("install-links")
+                        .about("Copy linked packages instead of symlinking them."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("unlink")
+                .about("Remove symlinks created by volt link.")
+                .override_usage(unlink_usage.as_str())
+                .arg(
+                    Arg::new("package-names")
+                        .about("Linked packages to remove from node_modules.")
+                        .multiple_values(true),
+                ),
+        )
+        .subcommand(
+            clap::App::new("run")
+                .about("Run a script from package.json.")
+                .override_usage(run_usage.as_str())
+                .arg(
+                    Arg::new("script")
+                        .about("Name of the script to run, lists the scripts when omitted."),
+                )
+                .arg(
+                    Arg::new("script-args")
+                        .about("Extra arguments passed to the script.")
+                        .multiple_values(true)
+                        .last(true),
+                ),
+        )
+        .subcommand(
+            clap::App::new("store")
+                .about("Manage the packages in the volt store.")
+                .alias("cache")
+                .override_usage(store_usage.as_str())
+                .subcommand(
+                    clap::App::new("add")
+                        .about("Add local package tarballs to the store.")
+                        .arg(
+                            Arg::new("tarballs")
+                                .about("Paths of the .tgz files to add.")
+                                .required(true)
+                                .multiple_values(true),
+                        )
+                        .arg(
+                            Arg::new("integrity")
+                                .long("integrity")
+                                .takes_value(true)
+                                .about("Refuse a tarball that doesn't match this integrity."),
+                        ),
+                )
+                .subcommand(
+                    clap::App::new("verify").about("Remove store entries whose files changed."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("use")
+                .about("Download a node version and pin the project to it.")
+                .override_usage(use_usage.as_str())
+                .arg(Arg::new("node-version").about("Node version or range to use.")),
+        )
+        .subcommand(
+            clap::App::new("view")
+                .alias("info")
+                .about("Show registry information about a package.")
+                .override_usage(view_usage.as_str())
+                .arg(
+                    Arg::new("package")
+                        .about("Package to show, optionally with a version, range or dist-tag.")
+                        .required(true),
+                )
+                .arg(Arg::new("field").about("Only print this field, e.g. dist.integrity."))
+                .arg(
+                    Arg::new("registry")
+                        .long("registry")
+                        .takes_value(true)
+                        .about("Registry to fetch the package from."),
+                ),
+        )
+        .subcommand(
+            clap::App::new("why-not")
+                .about("Explain why a version of a package was or wasn't installed.")
+                .override_usage(why_not_usage.as_str())
+                .arg(
+                    Arg::new("package")
+                        .about("Package and exact version to explain, e.g. react@16.14.0.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .about("Output the explanation as json."),
+                ),
         );
 
     let matches = app.get_matches();